@@ -0,0 +1,257 @@
+#ifndef LZO1X_H
+#define LZO1X_H
+
+/* This file is generated with cbindgen by the header test. Do not edit manually. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define LZO_VERSION 0x20a0
+#define LZO_VERSION_STRING "2.10"
+
+#define lzo_init() __lzo_init_v2(LZO_VERSION, (int)sizeof(short), (int)sizeof(int), \
+    (int)sizeof(long), (int)sizeof(uint32_t), (int)sizeof(lzo_uint), (int)sizeof(lzo_bytep), \
+    (int)sizeof(char *), (int)sizeof(lzo_voidp), (int)sizeof(lzo_callback_t))
+
+/* The work memory is never used by this library, but the sizes match liblzo2. */
+#define LZO1X_1_MEM_COMPRESS ((uint32_t)(16384L * sizeof(unsigned char *)))
+#define LZO1X_1_11_MEM_COMPRESS ((uint32_t)(2048L * sizeof(unsigned char *)))
+#define LZO1X_1_12_MEM_COMPRESS ((uint32_t)(4096L * sizeof(unsigned char *)))
+#define LZO1X_1_15_MEM_COMPRESS ((uint32_t)(32768L * sizeof(unsigned char *)))
+#define LZO1X_999_MEM_COMPRESS ((uint32_t)(14 * 16384L * sizeof(short)))
+#define LZO1X_MEM_DECOMPRESS (0)
+#define LZO1X_MEM_OPTIMIZE (0)
+
+#define LZO_E_OK 0
+#define LZO_E_ERROR (-1)
+#define LZO_E_OUT_OF_MEMORY (-2)
+#define LZO_E_NOT_COMPRESSIBLE (-3)
+#define LZO_E_INPUT_OVERRUN (-4)
+#define LZO_E_OUTPUT_OVERRUN (-5)
+#define LZO_E_LOOKBEHIND_OVERRUN (-6)
+#define LZO_E_EOF_NOT_FOUND (-7)
+#define LZO_E_INPUT_NOT_CONSUMED (-8)
+#define LZO_E_NOT_YET_IMPLEMENTED (-9)
+#define LZO_E_INVALID_ARGUMENT (-10)
+#define LZO_E_INVALID_ALIGNMENT (-11)
+#define LZO_E_OUTPUT_NOT_CONSUMED (-12)
+#define LZO_E_INTERNAL_ERROR (-99)
+
+/*
+ Unsigned integer type used for lengths.
+ */
+typedef size_t lzo_uint;
+
+/*
+ Pointer to bytes.
+ */
+typedef uint8_t *lzo_bytep;
+
+/*
+ Pointer to a length.
+ */
+typedef lzo_uint *lzo_uintp;
+
+/*
+ Pointer to memory.
+ */
+typedef void *lzo_voidp;
+
+/*
+ Callbacks for memory allocation and progress reporting.
+
+ Only the progress callback is used.
+ */
+typedef struct lzo_callback_t {
+  /*
+   Allocate memory.
+   */
+  lzo_voidp (*nalloc)(struct lzo_callback_t*, lzo_uint, lzo_uint);
+  /*
+   Free memory.
+   */
+  void (*nfree)(struct lzo_callback_t*, lzo_voidp);
+  /*
+   Report progress.
+   */
+  void (*nprogress)(struct lzo_callback_t*, lzo_uint, lzo_uint, int);
+  /*
+   User data.
+   */
+  lzo_voidp user1;
+  /*
+   User data.
+   */
+  lzo_uint user2;
+  /*
+   User data.
+   */
+  lzo_uint user3;
+} lzo_callback_t;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Initialize the library, checking that the given type sizes match the library.
+
+ This is the function called by the `lzo_init` macro of liblzo2. A size of -1 is not checked.
+
+ Returns `LZO_E_OK` on success, or `LZO_E_ERROR` if a type size does not match.
+ */
+int __lzo_init_v2(unsigned int v,
+                  int s1,
+                  int s2,
+                  int s3,
+                  int s4,
+                  int s5,
+                  int s6,
+                  int s7,
+                  int s8,
+                  int s9);
+
+/*
+ Compress with LZO1X-1, which is compression level 3 of the `lzo1x` crate.
+
+ # Safety
+
+ `src` must be valid for reads of `src_len` bytes, `dst` must be valid for writes of
+ `src_len + src_len / 16 + 64 + 3` bytes, and `dst_len` must be valid for writes.
+ */
+int lzo1x_1_compress(const uint8_t *src,
+                     lzo_uint src_len,
+                     lzo_bytep dst,
+                     lzo_uintp dst_len,
+                     lzo_voidp _wrkmem);
+
+/*
+ Compress with LZO1X-1(11), which is compression level 1 of the `lzo1x` crate.
+
+ # Safety
+
+ See [`lzo1x_1_compress`].
+ */
+int lzo1x_1_11_compress(const uint8_t *src,
+                        lzo_uint src_len,
+                        lzo_bytep dst,
+                        lzo_uintp dst_len,
+                        lzo_voidp _wrkmem);
+
+/*
+ Compress with LZO1X-1(12), which is compression level 2 of the `lzo1x` crate.
+
+ # Safety
+
+ See [`lzo1x_1_compress`].
+ */
+int lzo1x_1_12_compress(const uint8_t *src,
+                        lzo_uint src_len,
+                        lzo_bytep dst,
+                        lzo_uintp dst_len,
+                        lzo_voidp _wrkmem);
+
+/*
+ Compress with LZO1X-1(15), which is compression level 4 of the `lzo1x` crate.
+
+ # Safety
+
+ See [`lzo1x_1_compress`].
+ */
+int lzo1x_1_15_compress(const uint8_t *src,
+                        lzo_uint src_len,
+                        lzo_bytep dst,
+                        lzo_uintp dst_len,
+                        lzo_voidp _wrkmem);
+
+/*
+ Compress with LZO1X-999 at level 8, which is compression level 12 of the `lzo1x` crate.
+
+ # Safety
+
+ See [`lzo1x_1_compress`].
+ */
+int lzo1x_999_compress(const uint8_t *src,
+                       lzo_uint src_len,
+                       lzo_bytep dst,
+                       lzo_uintp dst_len,
+                       lzo_voidp _wrkmem);
+
+/*
+ Compress with LZO1X-999 at the given `compression_level` between 1 and 9,
+ which are compression levels 5 to 13 of the `lzo1x` crate.
+
+ The progress callback of the given `cb` is called regularly, if present.
+
+ Returns `LZO_E_ERROR` if the given `compression_level` is invalid,
+ or `LZO_E_NOT_YET_IMPLEMENTED` if a dictionary is given.
+
+ # Safety
+
+ See [`lzo1x_1_compress`]. Additionally, `cb` must be null or valid for reads.
+ */
+int lzo1x_999_compress_level(const uint8_t *src,
+                             lzo_uint src_len,
+                             lzo_bytep dst,
+                             lzo_uintp dst_len,
+                             lzo_voidp _wrkmem,
+                             const uint8_t *dict,
+                             lzo_uint dict_len,
+                             struct lzo_callback_t *cb,
+                             int compression_level);
+
+/*
+ Decompress LZO1X data, where `dst` must be large enough to hold the decompressed data.
+
+ The input value of `*dst_len` is not used, and on success `*dst_len` is set to the length of the decompressed data.
+ Unlike liblzo2, the input is checked like in [`lzo1x_decompress_safe`].
+
+ # Safety
+
+ `src` must be valid for reads of `src_len` bytes, `dst_len` must be valid for writes,
+ and `dst` must be valid for writes of the length of the decompressed data.
+ */
+int lzo1x_decompress(const uint8_t *src,
+                     lzo_uint src_len,
+                     lzo_bytep dst,
+                     lzo_uintp dst_len,
+                     lzo_voidp _wrkmem);
+
+/*
+ Decompress LZO1X data, where `*dst_len` contains the size of `dst`.
+
+ On success, `*dst_len` is set to the length of the decompressed data.
+
+ # Safety
+
+ `src` must be valid for reads of `src_len` bytes, `dst_len` must be valid for reads and writes,
+ and `dst` must be valid for writes of `*dst_len` bytes.
+ */
+int lzo1x_decompress_safe(const uint8_t *src,
+                          lzo_uint src_len,
+                          lzo_bytep dst,
+                          lzo_uintp dst_len,
+                          lzo_voidp _wrkmem);
+
+/*
+ Optimize LZO1X data in place for decompression speed, where `dst` must be large enough to hold the decompressed data.
+
+ The data is decompressed into `dst`. The input value of `*dst_len` is not used,
+ and on success `*dst_len` is set to the length of the decompressed data.
+
+ # Safety
+
+ `src` must be valid for reads and writes of `src_len` bytes, `dst_len` must be valid for writes,
+ and `dst` must be valid for writes of the length of the decompressed data.
+ */
+int lzo1x_optimize(lzo_bytep src,
+                   lzo_uint src_len,
+                   lzo_bytep dst,
+                   lzo_uintp dst_len,
+                   lzo_voidp _wrkmem);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LZO1X_H */
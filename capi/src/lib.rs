@@ -0,0 +1,526 @@
+#![allow(non_camel_case_types)]
+#![warn(missing_docs)]
+
+//! C ABI for the [`lzo1x`] crate, compatible with the LZO1X functions of liblzo2.
+//!
+//! The library is built as `liblzo2`, and the header `include/lzo/lzo1x.h` declares the exported functions,
+//! such that programs using the LZO1X functions of liblzo2 can be relinked without source changes.
+//!
+//! The differences with liblzo2 are:
+//!
+//! - The work memory is never used, and may be null.
+//! - `lzo1x_decompress` and `lzo1x_optimize` check their input like `lzo1x_decompress_safe`,
+//!   and do not write to `dst` if the input is invalid.
+//! - Dictionaries are not supported by `lzo1x_999_compress_level`.
+//! - Invalid compressed data is always reported as `LZO_E_INPUT_OVERRUN`,
+//!   where liblzo2 can also return `LZO_E_LOOKBEHIND_OVERRUN` or `LZO_E_INPUT_NOT_CONSUMED`.
+
+use std::{
+    ffi::{c_int, c_uint, c_void},
+    mem,
+    ops::ControlFlow,
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use lzo1x::{CompressLevel, DecompressError};
+
+/// Unsigned integer type used for lengths.
+pub type lzo_uint = usize;
+
+/// Pointer to bytes.
+pub type lzo_bytep = *mut u8;
+
+/// Pointer to a length.
+pub type lzo_uintp = *mut lzo_uint;
+
+/// Pointer to memory.
+pub type lzo_voidp = *mut c_void;
+
+/// Callbacks for memory allocation and progress reporting.
+///
+/// Only the progress callback is used.
+#[repr(C)]
+pub struct lzo_callback_t {
+    /// Allocate memory.
+    pub nalloc: Option<unsafe extern "C" fn(*mut lzo_callback_t, lzo_uint, lzo_uint) -> lzo_voidp>,
+    /// Free memory.
+    pub nfree: Option<unsafe extern "C" fn(*mut lzo_callback_t, lzo_voidp)>,
+    /// Report progress.
+    pub nprogress: Option<unsafe extern "C" fn(*mut lzo_callback_t, lzo_uint, lzo_uint, c_int)>,
+    /// User data.
+    pub user1: lzo_voidp,
+    /// User data.
+    pub user2: lzo_uint,
+    /// User data.
+    pub user3: lzo_uint,
+}
+
+/// No error.
+pub const LZO_E_OK: c_int = 0;
+/// Generic error.
+pub const LZO_E_ERROR: c_int = -1;
+/// Out of memory.
+pub const LZO_E_OUT_OF_MEMORY: c_int = -2;
+/// Not compressible.
+pub const LZO_E_NOT_COMPRESSIBLE: c_int = -3;
+/// Input overrun.
+pub const LZO_E_INPUT_OVERRUN: c_int = -4;
+/// Output overrun.
+pub const LZO_E_OUTPUT_OVERRUN: c_int = -5;
+/// Lookbehind overrun.
+pub const LZO_E_LOOKBEHIND_OVERRUN: c_int = -6;
+/// End of stream not found.
+pub const LZO_E_EOF_NOT_FOUND: c_int = -7;
+/// Input not consumed.
+pub const LZO_E_INPUT_NOT_CONSUMED: c_int = -8;
+/// Not yet implemented.
+pub const LZO_E_NOT_YET_IMPLEMENTED: c_int = -9;
+/// Invalid argument.
+pub const LZO_E_INVALID_ARGUMENT: c_int = -10;
+/// Invalid alignment.
+pub const LZO_E_INVALID_ALIGNMENT: c_int = -11;
+/// Output not consumed.
+pub const LZO_E_OUTPUT_NOT_CONSUMED: c_int = -12;
+/// Internal error.
+pub const LZO_E_INTERNAL_ERROR: c_int = -99;
+
+/// Initialize the library.
+///
+/// Always succeeds, and returns `LZO_E_OK`.
+#[unsafe(no_mangle)]
+pub extern "C" fn lzo_init() -> c_int {
+    LZO_E_OK
+}
+
+/// Initialize the library, checking that the given type sizes match the library.
+///
+/// This is the function called by the `lzo_init` macro of liblzo2. A size of -1 is not checked.
+///
+/// Returns `LZO_E_OK` on success, or `LZO_E_ERROR` if a type size does not match.
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn __lzo_init_v2(
+    v: c_uint,
+    s1: c_int,
+    s2: c_int,
+    s3: c_int,
+    s4: c_int,
+    s5: c_int,
+    s6: c_int,
+    s7: c_int,
+    s8: c_int,
+    s9: c_int,
+) -> c_int {
+    let sizes = [
+        (s1, mem::size_of::<i16>()),
+        (s2, mem::size_of::<c_int>()),
+        (s3, mem::size_of::<std::ffi::c_long>()),
+        (s4, mem::size_of::<u32>()),
+        (s5, mem::size_of::<lzo_uint>()),
+        (s6, mem::size_of::<lzo_bytep>()),
+        (s7, mem::size_of::<*mut u8>()),
+        (s8, mem::size_of::<lzo_voidp>()),
+        (s9, mem::size_of::<lzo_callback_t>()),
+    ];
+
+    if v == 0 || sizes.iter().any(|&(s, size)| s != -1 && s as usize != size) {
+        return LZO_E_ERROR;
+    }
+
+    LZO_E_OK
+}
+
+/// Compress with LZO1X-1, which is compression level 3 of the `lzo1x` crate.
+///
+/// # Safety
+///
+/// `src` must be valid for reads of `src_len` bytes, `dst` must be valid for writes of
+/// `src_len + src_len / 16 + 64 + 3` bytes, and `dst_len` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lzo1x_1_compress(
+    src: *const u8,
+    src_len: lzo_uint,
+    dst: lzo_bytep,
+    dst_len: lzo_uintp,
+    _wrkmem: lzo_voidp,
+) -> c_int {
+    unsafe {
+        compress(
+            src,
+            src_len,
+            dst,
+            dst_len,
+            CompressLevel::new(3),
+            ptr::null_mut(),
+        )
+    }
+}
+
+/// Compress with LZO1X-1(11), which is compression level 1 of the `lzo1x` crate.
+///
+/// # Safety
+///
+/// See [`lzo1x_1_compress`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lzo1x_1_11_compress(
+    src: *const u8,
+    src_len: lzo_uint,
+    dst: lzo_bytep,
+    dst_len: lzo_uintp,
+    _wrkmem: lzo_voidp,
+) -> c_int {
+    unsafe {
+        compress(
+            src,
+            src_len,
+            dst,
+            dst_len,
+            CompressLevel::new(1),
+            ptr::null_mut(),
+        )
+    }
+}
+
+/// Compress with LZO1X-1(12), which is compression level 2 of the `lzo1x` crate.
+///
+/// # Safety
+///
+/// See [`lzo1x_1_compress`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lzo1x_1_12_compress(
+    src: *const u8,
+    src_len: lzo_uint,
+    dst: lzo_bytep,
+    dst_len: lzo_uintp,
+    _wrkmem: lzo_voidp,
+) -> c_int {
+    unsafe {
+        compress(
+            src,
+            src_len,
+            dst,
+            dst_len,
+            CompressLevel::new(2),
+            ptr::null_mut(),
+        )
+    }
+}
+
+/// Compress with LZO1X-1(15), which is compression level 4 of the `lzo1x` crate.
+///
+/// # Safety
+///
+/// See [`lzo1x_1_compress`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lzo1x_1_15_compress(
+    src: *const u8,
+    src_len: lzo_uint,
+    dst: lzo_bytep,
+    dst_len: lzo_uintp,
+    _wrkmem: lzo_voidp,
+) -> c_int {
+    unsafe {
+        compress(
+            src,
+            src_len,
+            dst,
+            dst_len,
+            CompressLevel::new(4),
+            ptr::null_mut(),
+        )
+    }
+}
+
+/// Compress with LZO1X-999 at level 8, which is compression level 12 of the `lzo1x` crate.
+///
+/// # Safety
+///
+/// See [`lzo1x_1_compress`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lzo1x_999_compress(
+    src: *const u8,
+    src_len: lzo_uint,
+    dst: lzo_bytep,
+    dst_len: lzo_uintp,
+    _wrkmem: lzo_voidp,
+) -> c_int {
+    unsafe {
+        compress(
+            src,
+            src_len,
+            dst,
+            dst_len,
+            CompressLevel::new(12),
+            ptr::null_mut(),
+        )
+    }
+}
+
+/// Compress with LZO1X-999 at the given `compression_level` between 1 and 9,
+/// which are compression levels 5 to 13 of the `lzo1x` crate.
+///
+/// The progress callback of the given `cb` is called regularly, if present.
+///
+/// Returns `LZO_E_ERROR` if the given `compression_level` is invalid,
+/// or `LZO_E_NOT_YET_IMPLEMENTED` if a dictionary is given.
+///
+/// # Safety
+///
+/// See [`lzo1x_1_compress`]. Additionally, `cb` must be null or valid for reads.
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn lzo1x_999_compress_level(
+    src: *const u8,
+    src_len: lzo_uint,
+    dst: lzo_bytep,
+    dst_len: lzo_uintp,
+    _wrkmem: lzo_voidp,
+    dict: *const u8,
+    dict_len: lzo_uint,
+    cb: *mut lzo_callback_t,
+    compression_level: c_int,
+) -> c_int {
+    if !(1..=9).contains(&compression_level) {
+        return LZO_E_ERROR;
+    }
+
+    if !dict.is_null() && dict_len > 0 {
+        return LZO_E_NOT_YET_IMPLEMENTED;
+    }
+
+    let level = CompressLevel::new(compression_level as u8 + 4);
+
+    unsafe { compress(src, src_len, dst, dst_len, level, cb) }
+}
+
+/// Decompress LZO1X data, where `dst` must be large enough to hold the decompressed data.
+///
+/// The input value of `*dst_len` is not used, and on success `*dst_len` is set to the length of the decompressed data.
+/// Unlike liblzo2, the input is checked like in [`lzo1x_decompress_safe`].
+///
+/// # Safety
+///
+/// `src` must be valid for reads of `src_len` bytes, `dst_len` must be valid for writes,
+/// and `dst` must be valid for writes of the length of the decompressed data.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lzo1x_decompress(
+    src: *const u8,
+    src_len: lzo_uint,
+    dst: lzo_bytep,
+    dst_len: lzo_uintp,
+    _wrkmem: lzo_voidp,
+) -> c_int {
+    if dst_len.is_null() {
+        return LZO_E_INVALID_ARGUMENT;
+    }
+
+    let Some(src) = (unsafe { slice(src, src_len) }) else {
+        return LZO_E_INVALID_ARGUMENT;
+    };
+
+    guard(|| {
+        let dst = match unsafe { decompressed_slice(src, dst) } {
+            Ok(dst) => dst,
+            Err(err) => {
+                unsafe { *dst_len = 0 };
+
+                return err;
+            }
+        };
+
+        match lzo1x::decompress_into(src, dst) {
+            Ok(len) => {
+                unsafe { *dst_len = len };
+
+                LZO_E_OK
+            }
+            Err(err) => {
+                unsafe { *dst_len = 0 };
+
+                error_code(err)
+            }
+        }
+    })
+}
+
+/// Decompress LZO1X data, where `*dst_len` contains the size of `dst`.
+///
+/// On success, `*dst_len` is set to the length of the decompressed data.
+///
+/// # Safety
+///
+/// `src` must be valid for reads of `src_len` bytes, `dst_len` must be valid for reads and writes,
+/// and `dst` must be valid for writes of `*dst_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lzo1x_decompress_safe(
+    src: *const u8,
+    src_len: lzo_uint,
+    dst: lzo_bytep,
+    dst_len: lzo_uintp,
+    _wrkmem: lzo_voidp,
+) -> c_int {
+    if dst_len.is_null() {
+        return LZO_E_INVALID_ARGUMENT;
+    }
+
+    let (Some(src), Some(dst)) = (unsafe { slice(src, src_len) }, unsafe {
+        slice_mut(dst, *dst_len)
+    }) else {
+        return LZO_E_INVALID_ARGUMENT;
+    };
+
+    guard(|| match lzo1x::decompress_into(src, dst) {
+        Ok(len) => {
+            unsafe { *dst_len = len };
+
+            LZO_E_OK
+        }
+        Err(err) => {
+            unsafe { *dst_len = 0 };
+
+            error_code(err)
+        }
+    })
+}
+
+/// Optimize LZO1X data in place for decompression speed, where `dst` must be large enough to hold the decompressed data.
+///
+/// The data is decompressed into `dst`. The input value of `*dst_len` is not used,
+/// and on success `*dst_len` is set to the length of the decompressed data.
+///
+/// # Safety
+///
+/// `src` must be valid for reads and writes of `src_len` bytes, `dst_len` must be valid for writes,
+/// and `dst` must be valid for writes of the length of the decompressed data.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lzo1x_optimize(
+    src: lzo_bytep,
+    src_len: lzo_uint,
+    dst: lzo_bytep,
+    dst_len: lzo_uintp,
+    _wrkmem: lzo_voidp,
+) -> c_int {
+    if dst_len.is_null() {
+        return LZO_E_INVALID_ARGUMENT;
+    }
+
+    let Some(src) = (unsafe { slice_mut(src, src_len) }) else {
+        return LZO_E_INVALID_ARGUMENT;
+    };
+
+    guard(|| {
+        let dst = match unsafe { decompressed_slice(src, dst) } {
+            Ok(dst) => dst,
+            Err(err) => {
+                unsafe { *dst_len = 0 };
+
+                return err;
+            }
+        };
+
+        // Only optimize data which is known to be valid.
+        let len = match lzo1x::decompress_into(src, dst) {
+            Ok(len) => len,
+            Err(err) => {
+                unsafe { *dst_len = 0 };
+
+                return error_code(err);
+            }
+        };
+
+        lzo1x::optimize(src, len);
+
+        unsafe { *dst_len = len };
+
+        LZO_E_OK
+    })
+}
+
+unsafe fn compress(
+    src: *const u8,
+    src_len: lzo_uint,
+    dst: lzo_bytep,
+    dst_len: lzo_uintp,
+    level: CompressLevel,
+    cb: *mut lzo_callback_t,
+) -> c_int {
+    if dst.is_null() || dst_len.is_null() {
+        return LZO_E_INVALID_ARGUMENT;
+    }
+
+    let Some(src) = (unsafe { slice(src, src_len) }) else {
+        return LZO_E_INVALID_ARGUMENT;
+    };
+
+    let progress = unsafe { cb.as_ref() }.and_then(|callback| callback.nprogress);
+
+    guard(|| {
+        let result = match progress {
+            Some(progress) => lzo1x::compress_with_callback(src, level, |src_pos, dst_len| {
+                unsafe { progress(cb, src_pos, dst_len, 0) };
+
+                ControlFlow::Continue(())
+            })
+            .ok(),
+            None => lzo1x::try_compress(src, level).ok(),
+        };
+
+        let Some(compressed) = result else {
+            return LZO_E_OUT_OF_MEMORY;
+        };
+
+        // The caller guarantees that `dst` is large enough for the worst case compressed length.
+        unsafe {
+            ptr::copy_nonoverlapping(compressed.as_ptr(), dst, compressed.len());
+            *dst_len = compressed.len();
+        }
+
+        LZO_E_OK
+    })
+}
+
+/// Run the given function, returning `LZO_E_INTERNAL_ERROR` if it panics.
+fn guard(f: impl FnOnce() -> c_int) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(LZO_E_INTERNAL_ERROR)
+}
+
+fn error_code(err: DecompressError) -> c_int {
+    match err {
+        DecompressError::InvalidInput => LZO_E_INPUT_OVERRUN,
+        DecompressError::OutputLength => LZO_E_OUTPUT_OVERRUN,
+    }
+}
+
+/// Create a slice for the decompressed data of the given `src` from the given `dst` pointer,
+/// returning an error code if `src` does not contain valid compressed data.
+///
+/// The length of the decompressed data is determined without writing to `dst`,
+/// as the size of `dst` is not known.
+unsafe fn decompressed_slice<'a>(src: &[u8], dst: *mut u8) -> Result<&'a mut [u8], c_int> {
+    let len = lzo1x::analyze(src).map_err(error_code)?.decompressed_len;
+
+    unsafe { slice_mut(dst, len) }.ok_or(LZO_E_INVALID_ARGUMENT)
+}
+
+/// Create a slice from the given pointer and length, returning `None` for a null pointer with a non-zero length.
+unsafe fn slice<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { slice::from_raw_parts(ptr, len) })
+    }
+}
+
+/// Create a mutable slice from the given pointer and length, returning `None` for a null pointer with a non-zero length.
+unsafe fn slice_mut<'a>(ptr: *mut u8, len: usize) -> Option<&'a mut [u8]> {
+    if len == 0 {
+        Some(&mut [])
+    } else if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { slice::from_raw_parts_mut(ptr, len) })
+    }
+}
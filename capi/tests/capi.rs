@@ -0,0 +1,218 @@
+use std::{ffi::c_int, ptr};
+
+use lzo1x::{CompressLevel, compress};
+use lzo2::{
+    __lzo_init_v2, LZO_E_ERROR, LZO_E_INPUT_OVERRUN, LZO_E_INVALID_ARGUMENT,
+    LZO_E_NOT_YET_IMPLEMENTED, LZO_E_OK, LZO_E_OUTPUT_OVERRUN, lzo_callback_t, lzo_init, lzo_uint,
+    lzo1x_1_11_compress, lzo1x_1_12_compress, lzo1x_1_15_compress, lzo1x_1_compress,
+    lzo1x_999_compress, lzo1x_999_compress_level, lzo1x_decompress, lzo1x_decompress_safe,
+    lzo1x_optimize,
+};
+
+type CompressFn = unsafe extern "C" fn(
+    *const u8,
+    lzo_uint,
+    *mut u8,
+    *mut lzo_uint,
+    *mut std::ffi::c_void,
+) -> c_int;
+
+#[test]
+fn init() {
+    assert_eq!(lzo_init(), LZO_E_OK);
+    assert_eq!(
+        __lzo_init_v2(1, 2, 4, -1, 4, size_of::<usize>() as c_int, -1, -1, -1, -1),
+        LZO_E_OK
+    );
+    assert_eq!(
+        __lzo_init_v2(1, 2, 4, -1, 4, 3, -1, -1, -1, -1),
+        LZO_E_ERROR
+    );
+}
+
+#[test]
+fn round_trip() {
+    let data = test_data();
+
+    let functions: [(CompressFn, u8); 5] = [
+        (lzo1x_1_11_compress, 1),
+        (lzo1x_1_12_compress, 2),
+        (lzo1x_1_compress, 3),
+        (lzo1x_1_15_compress, 4),
+        (lzo1x_999_compress, 12),
+    ];
+
+    for (function, level) in functions {
+        let mut compressed = vec![0; data.len() + data.len() / 16 + 64 + 3];
+        let mut compressed_len = 0;
+
+        let result = unsafe {
+            function(
+                data.as_ptr(),
+                data.len(),
+                compressed.as_mut_ptr(),
+                &mut compressed_len,
+                ptr::null_mut(),
+            )
+        };
+
+        assert_eq!(result, LZO_E_OK);
+
+        compressed.truncate(compressed_len);
+        assert_eq!(compressed, compress(&data, CompressLevel::new(level)));
+
+        let mut decompressed = vec![0; data.len() + 100];
+
+        // Like in liblzo2, the input value of `*dst_len` is not used by `lzo1x_decompress` and `lzo1x_optimize`.
+        let mut decompressed_len = 0;
+
+        let result = unsafe {
+            lzo1x_decompress(
+                compressed.as_ptr(),
+                compressed.len(),
+                decompressed.as_mut_ptr(),
+                &mut decompressed_len,
+                ptr::null_mut(),
+            )
+        };
+
+        assert_eq!(result, LZO_E_OK);
+        assert_eq!(&decompressed[..decompressed_len], data);
+
+        let mut decompressed_len = 0;
+
+        let result = unsafe {
+            lzo1x_optimize(
+                compressed.as_mut_ptr(),
+                compressed.len(),
+                decompressed.as_mut_ptr(),
+                &mut decompressed_len,
+                ptr::null_mut(),
+            )
+        };
+
+        assert_eq!(result, LZO_E_OK);
+        assert_eq!(&decompressed[..decompressed_len], data);
+
+        let mut decompressed_len = data.len() - 1;
+
+        let result = unsafe {
+            lzo1x_decompress_safe(
+                compressed.as_ptr(),
+                compressed.len(),
+                decompressed.as_mut_ptr(),
+                &mut decompressed_len,
+                ptr::null_mut(),
+            )
+        };
+
+        assert_eq!(result, LZO_E_OUTPUT_OVERRUN);
+
+        let mut decompressed_len = decompressed.len();
+
+        let result = unsafe {
+            lzo1x_decompress_safe(
+                compressed.as_ptr(),
+                compressed.len() - 1,
+                decompressed.as_mut_ptr(),
+                &mut decompressed_len,
+                ptr::null_mut(),
+            )
+        };
+
+        assert_eq!(result, LZO_E_INPUT_OVERRUN);
+        assert_eq!(decompressed_len, 0);
+
+        let mut decompressed_len = 0;
+
+        let result = unsafe {
+            lzo1x_decompress(
+                compressed.as_ptr(),
+                compressed.len() - 1,
+                decompressed.as_mut_ptr(),
+                &mut decompressed_len,
+                ptr::null_mut(),
+            )
+        };
+
+        assert_eq!(result, LZO_E_INPUT_OVERRUN);
+        assert_eq!(decompressed_len, 0);
+    }
+}
+
+#[test]
+fn compress_level() {
+    let data = test_data();
+
+    unsafe extern "C" fn progress(cb: *mut lzo_callback_t, _: lzo_uint, _: lzo_uint, _: c_int) {
+        unsafe { (*cb).user2 += 1 };
+    }
+
+    let mut callback = lzo_callback_t {
+        nalloc: None,
+        nfree: None,
+        nprogress: Some(progress),
+        user1: ptr::null_mut(),
+        user2: 0,
+        user3: 0,
+    };
+
+    let mut compressed = vec![0; data.len() + data.len() / 16 + 64 + 3];
+    let mut compressed_len = 0;
+
+    let mut compress_level = |dict: &[u8], cb: *mut lzo_callback_t, level| unsafe {
+        lzo1x_999_compress_level(
+            data.as_ptr(),
+            data.len(),
+            compressed.as_mut_ptr(),
+            &mut compressed_len,
+            ptr::null_mut(),
+            dict.as_ptr(),
+            dict.len(),
+            cb,
+            level,
+        )
+    };
+
+    assert_eq!(compress_level(&[], &mut callback, 9), LZO_E_OK);
+    assert!(callback.user2 > 0);
+
+    assert_eq!(compress_level(&[], ptr::null_mut(), 0), LZO_E_ERROR);
+    assert_eq!(compress_level(&[], ptr::null_mut(), 10), LZO_E_ERROR);
+    assert_eq!(
+        compress_level(&[0; 4], ptr::null_mut(), 1),
+        LZO_E_NOT_YET_IMPLEMENTED
+    );
+
+    assert_eq!(
+        compressed[..compressed_len],
+        compress(&data, CompressLevel::new(13))
+    );
+}
+
+#[test]
+fn invalid_arguments() {
+    let mut len = 10;
+
+    let result = unsafe {
+        lzo1x_decompress_safe(ptr::null(), 10, ptr::null_mut(), &mut len, ptr::null_mut())
+    };
+
+    assert_eq!(result, LZO_E_INVALID_ARGUMENT);
+
+    let result = unsafe {
+        lzo1x_decompress_safe(
+            [0x11, 0, 0].as_ptr(),
+            3,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+    };
+
+    assert_eq!(result, LZO_E_INVALID_ARGUMENT);
+}
+
+fn test_data() -> Vec<u8> {
+    (0..50_000u32).flat_map(|i| (i / 7).to_le_bytes()).collect()
+}
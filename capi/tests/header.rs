@@ -0,0 +1,24 @@
+use std::{env, fs, path::Path};
+
+/// Check that the committed header matches the exported functions.
+///
+/// Run with `LZO1X_UPDATE_HEADER=1` to regenerate the header.
+#[test]
+fn header() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let path = crate_dir.join("include/lzo/lzo1x.h");
+
+    let mut header = Vec::new();
+    cbindgen::generate(crate_dir)
+        .expect("failed to generate header")
+        .write(&mut header);
+
+    if env::var_os("LZO1X_UPDATE_HEADER").is_some() {
+        fs::write(&path, &header).unwrap();
+    } else {
+        assert!(
+            fs::read(&path).unwrap() == header,
+            "include/lzo/lzo1x.h is out of date, run the tests with LZO1X_UPDATE_HEADER=1 to regenerate it"
+        );
+    }
+}
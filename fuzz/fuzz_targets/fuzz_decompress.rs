@@ -0,0 +1,26 @@
+#![no_main]
+
+extern crate libfuzzer_sys;
+
+use libfuzzer_sys::{
+    arbitrary::{Arbitrary, Unstructured},
+    fuzz_target,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+
+    let stream = match lzo1x::ArbitraryStream::arbitrary(&mut u) {
+        Ok(stream) => stream,
+        Err(_) => return,
+    };
+
+    let mut decompressed = vec![0; stream.decompressed.len()];
+    lzo1x::decompress(&stream.compressed, &mut decompressed).unwrap();
+
+    assert!(decompressed == stream.decompressed);
+
+    if let Ok(corrupt) = stream.mutate(&mut u) {
+        let _ = lzo1x::decompress(&corrupt, &mut decompressed);
+    }
+});
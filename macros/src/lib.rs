@@ -0,0 +1,138 @@
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+
+//! Macros for embedding LZO1X compressed data with the `lzo1x` crate.
+
+use std::{env, fs, path::PathBuf};
+
+use lzo1x::CompressLevel;
+use proc_macro::{Literal, TokenStream, TokenTree};
+
+/// Compress a file at compile time, and embed the compressed data as a [`lzo1x::Embedded`] instance.
+///
+/// The path of the file is relative to the directory containing the manifest of the crate.
+/// A compression level between 1 and 13 can optionally be given, which defaults to 3.
+///
+/// # Examples
+///
+/// ```
+/// static LIB: lzo1x::Embedded = lzo1x_macros::include_lzo!("src/lib.rs", 13);
+///
+/// assert!(LIB.compressed().len() < LIB.len());
+/// ```
+#[proc_macro]
+pub fn include_lzo(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(output) => output,
+        Err(msg) => format!("::core::compile_error!({msg:?})").parse().unwrap(),
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+    let mut tokens = input.into_iter();
+
+    let path = match tokens.next() {
+        Some(TokenTree::Literal(literal)) => parse_str(&literal.to_string()),
+        _ => None,
+    }
+    .ok_or("expected a string literal path")?;
+
+    let level = match tokens.next() {
+        None => CompressLevel::default(),
+        Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => match tokens.next() {
+            None => CompressLevel::default(),
+            Some(TokenTree::Literal(literal)) => parse_level(&literal.to_string())
+                .ok_or("expected a compression level between 1 and 13")?,
+            _ => return Err("expected a compression level".into()),
+        },
+        _ => return Err("expected `,`".into()),
+    };
+
+    match tokens.next() {
+        None => {}
+        Some(TokenTree::Punct(punct)) if punct.as_char() == ',' && tokens.next().is_none() => {}
+        Some(_) => return Err("unexpected tokens".into()),
+    }
+
+    let manifest_dir = env::var_os("CARGO_MANIFEST_DIR").ok_or("CARGO_MANIFEST_DIR is not set")?;
+    let path = PathBuf::from(manifest_dir).join(path);
+
+    let data =
+        fs::read(&path).map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+    let compressed = lzo1x::compress(&data, level);
+
+    let path = path.to_str().ok_or("path is not valid UTF-8")?;
+
+    // The `include_bytes!` makes the compiler track changes to the file.
+    let output = format!(
+        "{{ const _: &[u8] = ::core::include_bytes!({path:?}); ::lzo1x::Embedded::new({}, {}) }}",
+        Literal::byte_string(&compressed),
+        data.len()
+    );
+
+    Ok(output.parse().unwrap())
+}
+
+/// Parse the value of a string literal, as given by its source text.
+fn parse_str(literal: &str) -> Option<String> {
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let raw = &raw[hashes..raw.len().checked_sub(hashes)?];
+
+        return raw.strip_prefix('"')?.strip_suffix('"').map(str::to_owned);
+    }
+
+    let mut chars = literal.strip_prefix('"')?.strip_suffix('"')?.chars();
+    let mut value = String::new();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+
+        match chars.next()? {
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            '0' => value.push('\0'),
+            '\\' => value.push('\\'),
+            '\'' => value.push('\''),
+            '"' => value.push('"'),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                value.push(u8::from_str_radix(&hex, 16).ok().filter(u8::is_ascii)? as char);
+            }
+            'u' => {
+                let hex: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                value.push(char::from_u32(
+                    u32::from_str_radix(hex.strip_prefix('{')?, 16).ok()?,
+                )?);
+            }
+            '\n' => {
+                let rest = chars.as_str().trim_start();
+                chars = rest.chars();
+            }
+            _ => return None,
+        }
+    }
+
+    Some(value)
+}
+
+/// Parse a compression level from the source text of an integer literal.
+fn parse_level(literal: &str) -> Option<CompressLevel> {
+    let end = literal
+        .find(|c: char| !c.is_ascii_digit() && c != '_')
+        .unwrap_or(literal.len());
+
+    let (digits, suffix) = literal.split_at(end);
+
+    if !matches!(suffix, "" | "u8" | "u16" | "u32" | "u64" | "usize" | "i32") {
+        return None;
+    }
+
+    let level: u8 = digits.replace('_', "").parse().ok()?;
+
+    (1..=13).contains(&level).then(|| CompressLevel::new(level))
+}
@@ -0,0 +1,24 @@
+use std::fs;
+
+use lzo1x_macros::include_lzo;
+
+static DEFAULT: lzo1x::Embedded = include_lzo!("src/lib.rs");
+static LEVEL: lzo1x::Embedded = include_lzo!(r"src/lib.rs", 13,);
+static ESCAPED: lzo1x::Embedded = include_lzo!("src\x2flib.rs", 1u8);
+
+#[test]
+fn include() {
+    let data = fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/src/lib.rs")).unwrap();
+
+    for embedded in [&DEFAULT, &LEVEL, &ESCAPED] {
+        assert_eq!(embedded.len(), data.len());
+        assert_eq!(embedded.get(), data);
+        assert_eq!(embedded.to_vec(), data);
+    }
+
+    assert!(LEVEL.compressed().len() < DEFAULT.compressed().len());
+    assert_eq!(
+        DEFAULT.compressed(),
+        lzo1x::compress(&data, lzo1x::CompressLevel::default())
+    );
+}
@@ -0,0 +1,389 @@
+use core::fmt::{self, Display, Formatter};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::{
+    DecompressError,
+    decompress::{Insn, State, decode_insn},
+};
+
+// Estimated decode cost of a single instruction, relative to the cost of copying a single byte.
+const INSN_COST: u64 = 8;
+
+// Additional estimated decode cost per byte of a match which overlaps its own output,
+// which has to be copied in small pieces.
+const OVERLAP_COST: u64 = 2;
+
+/// Statistics of compressed data, as returned by [`analyze`].
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Analysis {
+    /// Length of the compressed data.
+    pub compressed_len: usize,
+    /// Length of the decompressed data.
+    pub decompressed_len: usize,
+    /// Number of blocks, each ended by an end marker.
+    pub blocks: usize,
+    /// Literal runs, including the 1-3 literals following a match.
+    pub literals: InsnStats,
+    /// M1 matches of 2-3 bytes with a distance of up to 3 KiB, which may only follow literals.
+    pub m1: InsnStats,
+    /// M2 matches of 3-8 bytes with a distance of up to 2 KiB.
+    pub m2: InsnStats,
+    /// M3 matches of any length with a distance of up to 16 KiB.
+    pub m3: InsnStats,
+    /// M4 matches of any length with a distance of up to 48 KiB.
+    pub m4: InsnStats,
+    /// Distribution of match lengths, where index `i` counts the matches of `2^i` up to `2^(i + 1)` bytes.
+    /// The last index also counts all longer matches.
+    pub match_len_histogram: [usize; 16],
+    /// Distribution of match distances, where index `i` counts the matches with a distance of `2^i` up to `2^(i + 1)`.
+    pub match_dist_histogram: [usize; 16],
+    /// Estimated cost of decompressing the data, in units of copying a single byte.
+    ///
+    /// Every instruction costs a fixed amount, and matches which overlap their own output cost extra.
+    /// This is only meaningful to compare different compressed versions of the same data.
+    pub decode_cost: u64,
+}
+
+/// Statistics of a single kind of instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct InsnStats {
+    /// Number of instructions.
+    pub count: usize,
+    /// Number of decompressed bytes produced by the instructions.
+    pub decompressed_len: usize,
+    /// Number of compressed bytes used by the instructions, including any literal bytes.
+    pub compressed_len: usize,
+}
+
+impl InsnStats {
+    fn add(&mut self, decompressed_len: usize, compressed_len: usize) {
+        self.count += 1;
+        self.decompressed_len += decompressed_len;
+        self.compressed_len += compressed_len;
+    }
+}
+
+impl Analysis {
+    /// Returns the statistics of all matches combined.
+    pub fn matches(&self) -> InsnStats {
+        [self.m1, self.m2, self.m3, self.m4].into_iter().fold(
+            InsnStats::default(),
+            |total, stats| InsnStats {
+                count: total.count + stats.count,
+                decompressed_len: total.decompressed_len + stats.decompressed_len,
+                compressed_len: total.compressed_len + stats.compressed_len,
+            },
+        )
+    }
+
+    /// Returns the share of the decompressed data which is produced by matches, between 0 and 1.
+    pub fn match_ratio(&self) -> f64 {
+        if self.decompressed_len == 0 {
+            return 0.0;
+        }
+
+        self.matches().decompressed_len as f64 / self.decompressed_len as f64
+    }
+
+    /// Returns the compression ratio, which is the length of the compressed data divided by
+    /// the length of the decompressed data.
+    pub fn ratio(&self) -> f64 {
+        if self.decompressed_len == 0 {
+            return 0.0;
+        }
+
+        self.compressed_len as f64 / self.decompressed_len as f64
+    }
+}
+
+impl Display for Analysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} -> {} bytes in {} block(s), ratio {:.3}",
+            self.decompressed_len,
+            self.compressed_len,
+            self.blocks,
+            self.ratio()
+        )?;
+
+        writeln!(
+            f,
+            "{:<10} {:>10} {:>14} {:>12}",
+            "kind", "count", "decompressed", "compressed"
+        )?;
+
+        for (name, stats) in [
+            ("literals", self.literals),
+            ("M1", self.m1),
+            ("M2", self.m2),
+            ("M3", self.m3),
+            ("M4", self.m4),
+        ] {
+            writeln!(
+                f,
+                "{:<10} {:>10} {:>14} {:>12}",
+                name, stats.count, stats.decompressed_len, stats.compressed_len
+            )?;
+        }
+
+        writeln!(
+            f,
+            "matches produce {:.1}% of the output",
+            self.match_ratio() * 100.0
+        )?;
+
+        writeln!(f, "match lengths:")?;
+        write_histogram(f, &self.match_len_histogram)?;
+
+        writeln!(f, "match distances:")?;
+        write_histogram(f, &self.match_dist_histogram)?;
+
+        writeln!(f, "estimated decode cost: {}", self.decode_cost)
+    }
+}
+
+fn write_histogram(f: &mut Formatter<'_>, histogram: &[usize]) -> fmt::Result {
+    for (i, &count) in histogram.iter().enumerate() {
+        if count > 0 {
+            writeln!(
+                f,
+                "  {:>6}..{:<6} {:>10}",
+                1u32 << i,
+                1u32 << (i + 1),
+                count
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Statistics of a region of the decompressed data, as returned by [`analyze_regions`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Region {
+    /// Offset of the region in the decompressed data.
+    pub offset: usize,
+    /// Length of the decompressed data of the region.
+    pub decompressed_len: usize,
+    /// Number of compressed bytes attributed to the region.
+    pub compressed_len: usize,
+    /// Number of decompressed bytes of the region which are stored as literals.
+    pub literal_len: usize,
+}
+
+/// Analyze the compressed data given in `src`, without decompressing it.
+///
+/// The given `src` may consist of multiple blocks, such as the data written by an [`Encoder`](crate::Encoder).
+///
+/// #### Errors
+///
+/// This function returns an error if the given `src` does not contain valid compressed data.
+///
+/// # Examples
+///
+/// ```
+/// let data: Vec<u8> = (0..10_000u32).flat_map(|i| (i / 7).to_le_bytes()).collect();
+/// let compressed = lzo1x::compress(&data, lzo1x::CompressLevel::default());
+///
+/// let analysis = lzo1x::analyze(&compressed).unwrap();
+///
+/// assert_eq!(analysis.decompressed_len, data.len());
+/// assert!(analysis.match_ratio() > 0.5);
+///
+/// println!("{analysis}");
+/// ```
+pub fn analyze(src: &[u8]) -> Result<Analysis, DecompressError> {
+    let mut analysis = Analysis {
+        compressed_len: src.len(),
+        ..Analysis::default()
+    };
+
+    walk(src, |step| {
+        analysis.decode_cost += INSN_COST;
+
+        match step.insn {
+            Insn::Literal { len } => {
+                analysis.literals.add(len, step.insn_len + len);
+                analysis.decode_cost += len as u64;
+            }
+            Insn::Match { len, dist, lit } => {
+                let stats = match step.op {
+                    64.. => &mut analysis.m2,
+                    32.. => &mut analysis.m3,
+                    16.. => &mut analysis.m4,
+                    _ => &mut analysis.m1,
+                };
+
+                stats.add(len, step.insn_len);
+
+                if lit > 0 {
+                    analysis.literals.add(lit, lit);
+                }
+
+                analysis.match_len_histogram[len.ilog2().min(15) as usize] += 1;
+                analysis.match_dist_histogram[dist.ilog2().min(15) as usize] += 1;
+
+                analysis.decode_cost += (len + lit) as u64;
+
+                if dist < len {
+                    analysis.decode_cost += OVERLAP_COST * len as u64;
+                }
+            }
+            Insn::End => {
+                analysis.blocks += 1;
+            }
+        }
+    })
+    .map(|decompressed_len| {
+        analysis.decompressed_len = decompressed_len;
+
+        analysis
+    })
+}
+
+/// Analyze the compressed data given in `src` per region of `region_len` bytes of decompressed data,
+/// which shows where the data compresses badly.
+///
+/// Literal bytes are attributed to the region they are decompressed into,
+/// and the remaining bytes of an instruction to the region in which its decompressed data starts.
+///
+/// #### Errors
+///
+/// This function returns an error if the given `src` does not contain valid compressed data.
+///
+/// #### Panics
+///
+/// Panics if `region_len` is zero.
+///
+/// # Examples
+///
+/// ```
+/// let mut data = vec![0; 4096];
+/// data.extend((0..4096u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8));
+///
+/// let compressed = lzo1x::compress(&data, lzo1x::CompressLevel::default());
+/// let regions = lzo1x::analyze_regions(&compressed, 4096).unwrap();
+///
+/// assert_eq!(regions.len(), 2);
+/// assert!(regions[0].compressed_len < regions[1].compressed_len);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn analyze_regions(src: &[u8], region_len: usize) -> Result<Vec<Region>, DecompressError> {
+    assert!(region_len > 0, "region length must not be zero");
+
+    let mut regions: Vec<Region> = Vec::new();
+
+    let decompressed_len = walk(src, |step| {
+        let (len, lit) = match step.insn {
+            Insn::Literal { len } => (0, len),
+            Insn::Match { len, lit, .. } => (len, lit),
+            Insn::End => (0, 0),
+        };
+
+        let mut add = |pos: usize, compressed_len: usize, literal_len: usize| {
+            let index = pos / region_len;
+
+            while regions.len() <= index {
+                regions.push(Region {
+                    offset: regions.len() * region_len,
+                    ..Region::default()
+                });
+            }
+
+            regions[index].compressed_len += compressed_len;
+            regions[index].literal_len += literal_len;
+        };
+
+        // The end marker belongs to the region of the data before it.
+        let insn_pos = match step.insn {
+            Insn::End => step.dst_pos.saturating_sub(1),
+            _ => step.dst_pos,
+        };
+
+        add(insn_pos, step.insn_len, 0);
+
+        // Every literal byte belongs to the region it is decompressed into.
+        let mut pos = step.dst_pos + len;
+        let end = pos + lit;
+
+        while pos < end {
+            let n = (end - pos).min(region_len - pos % region_len);
+
+            add(pos, n, n);
+            pos += n;
+        }
+    })?;
+
+    for region in &mut regions {
+        region.decompressed_len = (decompressed_len - region.offset).min(region_len);
+    }
+
+    Ok(regions)
+}
+
+/// A single instruction visited by [`walk`].
+struct Step {
+    insn: Insn,
+    /// First byte of the instruction.
+    op: u8,
+    /// Length of the instruction, excluding any literal bytes.
+    insn_len: usize,
+    /// Length of the decompressed data before the instruction.
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    dst_pos: usize,
+}
+
+/// Visit every instruction of the compressed data given in `src`, returning the length of the decompressed data.
+fn walk(src: &[u8], mut visit: impl FnMut(&Step)) -> Result<usize, DecompressError> {
+    let mut src_pos = 0;
+    let mut dst_pos = 0;
+    let mut state = State::Start;
+    let mut ended = false;
+
+    while src_pos < src.len() || state != State::Start {
+        let insn_pos = src_pos;
+        let insn =
+            decode_insn(src, &mut src_pos, state).map_err(|_| DecompressError::InvalidInput)?;
+
+        let (len, lit) = match insn {
+            Insn::Literal { len } => (0, len),
+            Insn::Match { len, dist, lit } => {
+                if dist > dst_pos {
+                    return Err(DecompressError::InvalidInput);
+                }
+
+                (len, lit)
+            }
+            Insn::End => {
+                ended = true;
+
+                (0, 0)
+            }
+        };
+
+        if src_pos + lit > src.len() {
+            return Err(DecompressError::InvalidInput);
+        }
+
+        visit(&Step {
+            insn,
+            op: src[insn_pos],
+            insn_len: src_pos - insn_pos,
+            dst_pos,
+        });
+
+        src_pos += lit;
+        dst_pos += len + lit;
+        state = state.next(&insn);
+    }
+
+    // Empty input does not contain an end of stream.
+    if !ended {
+        return Err(DecompressError::InvalidInput);
+    }
+
+    Ok(dst_pos)
+}
@@ -0,0 +1,396 @@
+use std::{
+    io::{self, ErrorKind},
+    mem,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{
+    CompressLevel, FrameError,
+    frame::{
+        BLOCK_HEADER_LEN, BlockHeader, DEFAULT_BLOCK_SIZE, FOOTER_LEN, HEADER_LEN, check_trailer,
+        clamp_block_size, encode_block, read_header, write_header, write_trailer,
+    },
+};
+
+// Number of bytes to read from the underlying reader at once while the buffer is growing.
+const FILL_LEN: usize = 0x8000;
+
+/// Asynchronous writer which compresses data written to it into a frame.
+///
+/// This is the asynchronous version of [`FrameWriter`](crate::FrameWriter),
+/// and writes the same frame for the same data, compression level and block size.
+/// At most a single block of uncompressed data and a single compressed block are buffered.
+/// Blocks are compressed inside [`AsyncWrite::poll_write`], so large block sizes with high compression levels
+/// can block the executor for a noticeable amount of time.
+///
+/// The frame is only complete after [`AsyncWrite::poll_shutdown`] was called,
+/// after which writing returns an error.
+///
+/// # Examples
+///
+/// ```
+/// use tokio::io::AsyncWriteExt;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let mut writer = lzo1x::AsyncFrameWriter::new(Vec::new(), lzo1x::CompressLevel::default());
+/// writer.write_all(&[0xaa; 100]).await.unwrap();
+/// writer.shutdown().await.unwrap();
+///
+/// let frame = writer.into_inner();
+///
+/// assert_eq!(lzo1x::decompress_frame(&frame).unwrap(), [0xaa; 100]);
+/// # });
+/// ```
+pub struct AsyncFrameWriter<W: AsyncWrite + Unpin> {
+    inner: W,
+    level: CompressLevel,
+    block_size: usize,
+    src: Vec<u8>,
+    dst: Vec<u8>,
+    dst_pos: usize,
+    blocks: Vec<(u32, u32)>,
+    content_size: u64,
+    finished: bool,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncFrameWriter<W> {
+    /// Create a new `AsyncFrameWriter` that writes a frame with the given compression `level` to the given `inner` writer.
+    ///
+    /// The frame uses blocks of 256 KiB.
+    pub fn new(inner: W, level: CompressLevel) -> Self {
+        Self::with_block_size(inner, level, DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Create a new `AsyncFrameWriter` that writes a frame with the given compression `level` and `block_size`
+    /// to the given `inner` writer.
+    ///
+    /// The given `block_size` is clamped to the range 1 B - 1 GiB.
+    pub fn with_block_size(inner: W, level: CompressLevel, block_size: usize) -> Self {
+        let block_size = clamp_block_size(block_size);
+
+        let mut dst = Vec::new();
+        write_header(&mut dst, block_size);
+
+        Self {
+            inner,
+            level,
+            block_size,
+            src: Vec::new(),
+            dst,
+            dst_pos: 0,
+            blocks: Vec::new(),
+            content_size: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the underlying writer.
+    ///
+    /// Any data which was not yet written to the underlying writer is lost.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn encode_block(&mut self) {
+        if self.src.is_empty() {
+            return;
+        }
+
+        let stored_len = encode_block(&self.src, self.level, &mut self.dst);
+        self.blocks.push((stored_len, self.src.len() as u32));
+        self.content_size += self.src.len() as u64;
+
+        self.src.clear();
+    }
+
+    /// Write all buffered compressed data to the underlying writer.
+    fn poll_write_dst(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.dst_pos < self.dst.len() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.dst[self.dst_pos..]))?;
+
+            if n == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+
+            self.dst_pos += n;
+        }
+
+        self.dst.clear();
+        self.dst_pos = 0;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for AsyncFrameWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.finished {
+            return Poll::Ready(Err(io::Error::new(
+                ErrorKind::BrokenPipe,
+                "write after shutdown",
+            )));
+        }
+
+        loop {
+            ready!(this.poll_write_dst(cx))?;
+
+            if this.src.len() < this.block_size {
+                break;
+            }
+
+            this.encode_block();
+        }
+
+        let len = buf.len().min(this.block_size - this.src.len());
+        this.src.extend_from_slice(&buf[..len]);
+
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if !this.finished {
+            this.encode_block();
+        }
+
+        ready!(this.poll_write_dst(cx))?;
+
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if !this.finished {
+            this.encode_block();
+
+            write_trailer(&mut this.dst, &this.blocks, this.content_size);
+            this.finished = true;
+        }
+
+        ready!(this.poll_write_dst(cx))?;
+
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// Asynchronous reader which decompresses a frame.
+///
+/// This is the asynchronous version of [`decompress_frame`](crate::decompress_frame),
+/// which reads the frame sequentially and does not require seeking.
+/// At most a single compressed block and a single decompressed block are buffered.
+/// The underlying reader is read exactly up to the end of the frame.
+/// After an error, every following read returns an error of the same kind.
+///
+/// # Examples
+///
+/// ```
+/// use tokio::io::AsyncReadExt;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let frame = lzo1x::compress_frame(&[0xaa; 100], lzo1x::CompressLevel::default(), 64);
+///
+/// let mut decompressed = Vec::new();
+/// lzo1x::AsyncFrameReader::new(frame.as_slice())
+///     .read_to_end(&mut decompressed)
+///     .await
+///     .unwrap();
+///
+/// assert_eq!(decompressed, [0xaa; 100]);
+/// # });
+/// ```
+pub struct AsyncFrameReader<R: AsyncRead + Unpin> {
+    inner: R,
+    state: ReadState,
+    block_size: usize,
+    src: Vec<u8>,
+    src_len: usize,
+    dst: Vec<u8>,
+    dst_pos: usize,
+    blocks: Vec<(u32, u32)>,
+}
+
+enum ReadState {
+    Header,
+    BlockHeader,
+    BlockData(BlockHeader),
+    Trailer,
+    Done,
+    Failed(ErrorKind),
+}
+
+impl<R: AsyncRead + Unpin> AsyncFrameReader<R> {
+    /// Create a new `AsyncFrameReader` that reads a frame from the given `inner` reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: ReadState::Header,
+            block_size: 0,
+            src: Vec::new(),
+            src_len: 0,
+            dst: Vec::new(),
+            dst_pos: 0,
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read from the underlying reader until the source buffer contains exactly `len` bytes.
+    fn poll_fill(&mut self, cx: &mut Context<'_>, len: usize) -> Poll<io::Result<()>> {
+        self.src.truncate(len);
+
+        while self.src_len < len {
+            // The buffer grows with the data that is read, such that a corrupt length does not cause a large allocation.
+            if self.src_len == self.src.len() {
+                self.src.resize(len.min(self.src_len.max(FILL_LEN) * 2), 0);
+            }
+
+            let mut buf = ReadBuf::new(&mut self.src[self.src_len..]);
+            ready!(Pin::new(&mut self.inner).poll_read(cx, &mut buf))?;
+
+            if buf.filled().is_empty() {
+                return Poll::Ready(Err(ErrorKind::UnexpectedEof.into()));
+            }
+
+            self.src_len += buf.filled().len();
+        }
+
+        self.src_len = 0;
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Advance the state machine by a single step.
+    fn poll_step(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.state {
+            ReadState::Header => {
+                ready!(self.poll_fill(cx, HEADER_LEN))?;
+
+                self.block_size = read_header(&self.src).map_err(invalid_data)?;
+                self.state = ReadState::BlockHeader;
+            }
+            ReadState::BlockHeader => {
+                // Only read the stored length first, as the end mark is shorter than a block header.
+                if self.src_len < 4 {
+                    ready!(self.poll_fill(cx, 4))?;
+
+                    if self.src == [0; 4] {
+                        self.state = ReadState::Trailer;
+
+                        return Poll::Ready(Ok(()));
+                    }
+
+                    self.src_len = 4;
+                }
+
+                ready!(self.poll_fill(cx, BLOCK_HEADER_LEN))?;
+
+                let header = BlockHeader::parse(self.src.as_slice().try_into().unwrap()).unwrap();
+
+                header.check(self.block_size).map_err(invalid_data)?;
+
+                self.blocks
+                    .push((header.raw_stored_len(), header.len as u32));
+                self.state = ReadState::BlockData(header);
+            }
+            ReadState::BlockData(ref header) => {
+                let stored_len = header.stored_len;
+                ready!(self.poll_fill(cx, stored_len))?;
+
+                let ReadState::BlockData(header) =
+                    mem::replace(&mut self.state, ReadState::BlockHeader)
+                else {
+                    unreachable!()
+                };
+
+                self.dst.resize(header.len, 0);
+                self.dst_pos = 0;
+
+                header
+                    .decode(&self.src, &mut self.dst)
+                    .map_err(invalid_data)?;
+            }
+            ReadState::Trailer => {
+                ready!(self.poll_fill(cx, (self.blocks.len() * 8) + FOOTER_LEN))?;
+
+                check_trailer(&self.src, &self.blocks).map_err(invalid_data)?;
+                self.state = ReadState::Done;
+            }
+            ReadState::Done => {}
+            ReadState::Failed(kind) => {
+                return Poll::Ready(Err(io::Error::new(kind, "frame reader failed previously")));
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncFrameReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        while this.dst_pos == this.dst.len() {
+            if let ReadState::Done = this.state {
+                return Poll::Ready(Ok(()));
+            }
+
+            if let Err(err) = ready!(this.poll_step(cx)) {
+                // The partially decoded block is discarded, and the reader does not continue after an error.
+                this.dst.clear();
+                this.dst_pos = 0;
+                this.state = ReadState::Failed(err.kind());
+
+                return Poll::Ready(Err(err));
+            }
+        }
+
+        let len = buf.remaining().min(this.dst.len() - this.dst_pos);
+        buf.put_slice(&this.dst[this.dst_pos..this.dst_pos + len]);
+        this.dst_pos += len;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn invalid_data(err: FrameError) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, err)
+}
@@ -0,0 +1,64 @@
+// Checksums of the lzop file format.
+
+const ADLER32_BASE: u32 = 65521;
+const ADLER32_NMAX: usize = 5552;
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+/// Update the given Adler-32 checksum `adler` with the given `buf`.
+///
+/// The initial checksum value is 1.
+pub fn adler32(adler: u32, buf: &[u8]) -> u32 {
+    let mut s1 = adler & 0xffff;
+    let mut s2 = adler >> 16;
+
+    for chunk in buf.chunks(ADLER32_NMAX) {
+        for &byte in chunk {
+            s1 += byte as u32;
+            s2 += s1;
+        }
+
+        s1 %= ADLER32_BASE;
+        s2 %= ADLER32_BASE;
+    }
+
+    (s2 << 16) | s1
+}
+
+/// Update the given CRC-32 checksum `crc` with the given `buf`.
+///
+/// The initial checksum value is 0.
+pub fn crc32(crc: u32, buf: &[u8]) -> u32 {
+    let mut crc = !crc;
+
+    for &byte in buf {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+
+    !crc
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+
+        while j < 8 {
+            crc = if crc & 1 != 0 {
+                0xedb88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+
+            j += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
@@ -0,0 +1,385 @@
+// The lzop file format, as written by lzop 1.04. All integers are stored in big-endian byte order.
+
+use std::io::{self, ErrorKind, Read, Write};
+
+use lzo1x::{CompressLevel, compress, decompress};
+
+use crate::checksum::{adler32, crc32};
+
+pub const MAGIC: [u8; 9] = [0x89, b'L', b'Z', b'O', 0x00, 0x0d, 0x0a, 0x1a, 0x0a];
+
+pub const SUFFIX: &str = ".lzo";
+
+const VERSION: u16 = 0x1040;
+const LIB_VERSION: u16 = 0x2080;
+const VERSION_NEEDED: u16 = 0x0940;
+
+const M_LZO1X_1: u8 = 1;
+const M_LZO1X_1_15: u8 = 2;
+const M_LZO1X_999: u8 = 3;
+
+const F_ADLER32_D: u32 = 0x00000001;
+const F_ADLER32_C: u32 = 0x00000002;
+pub const F_STDIN: u32 = 0x00000004;
+const F_H_EXTRA_FIELD: u32 = 0x00000040;
+const F_CRC32_D: u32 = 0x00000100;
+const F_CRC32_C: u32 = 0x00000200;
+const F_H_FILTER: u32 = 0x00000800;
+const F_H_CRC32: u32 = 0x00001000;
+const F_OS_UNIX: u32 = 0x03000000;
+
+const BLOCK_SIZE: usize = 256 * 1024;
+const MAX_BLOCK_SIZE: usize = 64 * 1024 * 1024;
+
+pub struct Header {
+    pub method: u8,
+    pub level: u8,
+    pub flags: u32,
+    pub mode: u32,
+    pub mtime: u64,
+    pub name: String,
+}
+
+impl Header {
+    /// Create a header for a file compressed with the given compression `level`.
+    pub fn new(level: u8, flags: u32, mode: u32, mtime: u64, name: String) -> Self {
+        let (method, level) = match level {
+            1..=3 => (M_LZO1X_1, level),
+            4 => (M_LZO1X_1_15, 1),
+            _ => (M_LZO1X_999, level - 4),
+        };
+
+        Self {
+            method,
+            level,
+            flags: flags | F_ADLER32_D | F_OS_UNIX,
+            mode,
+            mtime,
+            name,
+        }
+    }
+
+    /// Returns the name of the compression method.
+    pub fn method_name(&self) -> &'static str {
+        match self.method {
+            M_LZO1X_1 => "LZO1X-1",
+            M_LZO1X_1_15 => "LZO1X-1(15)",
+            M_LZO1X_999 => "LZO1X-999",
+            _ => "unknown",
+        }
+    }
+
+    /// Read the header, including the magic number.
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+
+        if magic != MAGIC {
+            return Err(invalid_data("not a lzop file"));
+        }
+
+        let mut reader = HeaderReader {
+            inner: reader,
+            buf: Vec::new(),
+        };
+
+        let version = reader.u16()?;
+        let _lib_version = reader.u16()?;
+
+        if version < 0x0900 {
+            return Err(invalid_data("unsupported lzop version"));
+        }
+
+        if version >= 0x0940 {
+            let version_needed = reader.u16()?;
+
+            if version_needed > VERSION {
+                return Err(invalid_data("unsupported lzop version"));
+            }
+        }
+
+        let method = reader.u8()?;
+
+        if !matches!(method, M_LZO1X_1 | M_LZO1X_1_15 | M_LZO1X_999) {
+            return Err(invalid_data("unsupported compression method"));
+        }
+
+        let level = if version >= 0x0940 { reader.u8()? } else { 0 };
+        let flags = reader.u32()?;
+
+        if flags & F_H_FILTER != 0 {
+            return Err(invalid_data("unsupported filter"));
+        }
+
+        let mode = reader.u32()?;
+        let mut mtime = reader.u32()? as u64;
+
+        if version >= 0x0940 {
+            mtime |= (reader.u32()? as u64) << 32;
+        }
+
+        let name_len = reader.u8()? as usize;
+        let name = String::from_utf8_lossy(&reader.bytes(name_len)?).into_owned();
+
+        let checksum = header_checksum(flags & F_H_CRC32 != 0, &reader.buf);
+
+        if reader.inner_u32()? != checksum {
+            return Err(invalid_data("header checksum error"));
+        }
+
+        if flags & F_H_EXTRA_FIELD != 0 {
+            reader.buf.clear();
+
+            let len = reader.u32()? as usize;
+            reader.bytes(len)?;
+
+            let checksum = header_checksum(flags & F_H_CRC32 != 0, &reader.buf);
+
+            if reader.inner_u32()? != checksum {
+                return Err(invalid_data("header checksum error"));
+            }
+        }
+
+        Ok(Self {
+            method,
+            level,
+            flags,
+            mode,
+            mtime,
+            name,
+        })
+    }
+
+    /// Write the header, including the magic number.
+    pub fn write(&self, writer: &mut (impl Write + ?Sized)) -> io::Result<()> {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(&VERSION.to_be_bytes());
+        buf.extend_from_slice(&LIB_VERSION.to_be_bytes());
+        buf.extend_from_slice(&VERSION_NEEDED.to_be_bytes());
+        buf.push(self.method);
+        buf.push(self.level);
+        buf.extend_from_slice(&self.flags.to_be_bytes());
+        buf.extend_from_slice(&self.mode.to_be_bytes());
+        buf.extend_from_slice(&(self.mtime as u32).to_be_bytes());
+        buf.extend_from_slice(&((self.mtime >> 32) as u32).to_be_bytes());
+
+        let name = &self.name.as_bytes()[..self.name.len().min(255)];
+        buf.push(name.len() as u8);
+        buf.extend_from_slice(name);
+
+        let checksum = adler32(1, &buf);
+        buf.extend_from_slice(&checksum.to_be_bytes());
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&buf)
+    }
+}
+
+/// Compress all data from the given `reader` into blocks, and write them to the given `writer`.
+///
+/// The header should have been written already.
+pub fn compress_blocks(
+    reader: &mut impl Read,
+    writer: &mut (impl Write + ?Sized),
+    level: CompressLevel,
+    flags: u32,
+) -> io::Result<()> {
+    let mut src = vec![0; BLOCK_SIZE];
+
+    loop {
+        let len = read_full(reader, &mut src)?;
+
+        if len == 0 {
+            break;
+        }
+
+        let src = &src[..len];
+        let dst = compress(src, level);
+
+        writer.write_all(&(src.len() as u32).to_be_bytes())?;
+
+        let data = if dst.len() < src.len() {
+            writer.write_all(&(dst.len() as u32).to_be_bytes())?;
+            dst.as_slice()
+        } else {
+            writer.write_all(&(src.len() as u32).to_be_bytes())?;
+            src
+        };
+
+        if flags & F_ADLER32_D != 0 {
+            writer.write_all(&adler32(1, src).to_be_bytes())?;
+        }
+
+        if flags & F_CRC32_D != 0 {
+            writer.write_all(&crc32(0, src).to_be_bytes())?;
+        }
+
+        if data.len() < src.len() {
+            if flags & F_ADLER32_C != 0 {
+                writer.write_all(&adler32(1, data).to_be_bytes())?;
+            }
+
+            if flags & F_CRC32_C != 0 {
+                writer.write_all(&crc32(0, data).to_be_bytes())?;
+            }
+        }
+
+        writer.write_all(data)?;
+    }
+
+    writer.write_all(&0u32.to_be_bytes())
+}
+
+/// Statistics of the blocks of a file.
+#[derive(Default)]
+pub struct Stats {
+    pub compressed: u64,
+    pub uncompressed: u64,
+}
+
+/// Decompress all blocks from the given `reader`, and write the decompressed data to the given `writer`.
+///
+/// If `writer` is `None`, the blocks are only read and their checksums are not verified.
+pub fn decompress_blocks(
+    reader: &mut impl Read,
+    mut writer: Option<&mut dyn Write>,
+    flags: u32,
+) -> io::Result<Stats> {
+    let mut stats = Stats::default();
+
+    let mut src = Vec::new();
+    let mut dst = Vec::new();
+
+    loop {
+        let dst_len = read_u32(reader)? as usize;
+
+        if dst_len == 0 {
+            break;
+        }
+
+        let src_len = read_u32(reader)? as usize;
+
+        if dst_len > MAX_BLOCK_SIZE || src_len > dst_len {
+            return Err(invalid_data("invalid block size"));
+        }
+
+        let adler32_d = read_checksum(reader, flags & F_ADLER32_D != 0)?;
+        let crc32_d = read_checksum(reader, flags & F_CRC32_D != 0)?;
+
+        let (adler32_c, crc32_c) = if src_len < dst_len {
+            (
+                read_checksum(reader, flags & F_ADLER32_C != 0)?,
+                read_checksum(reader, flags & F_CRC32_C != 0)?,
+            )
+        } else {
+            (None, None)
+        };
+
+        src.resize(src_len, 0);
+        reader.read_exact(&mut src)?;
+
+        stats.compressed += src_len as u64;
+        stats.uncompressed += dst_len as u64;
+
+        let Some(writer) = writer.as_mut() else {
+            continue;
+        };
+
+        if adler32_c.is_some_and(|checksum| checksum != adler32(1, &src))
+            || crc32_c.is_some_and(|checksum| checksum != crc32(0, &src))
+        {
+            return Err(invalid_data("checksum error"));
+        }
+
+        let data = if src_len < dst_len {
+            dst.resize(dst_len, 0);
+            decompress(&src, &mut dst).map_err(|err| invalid_data(&err.to_string()))?;
+
+            dst.as_slice()
+        } else {
+            src.as_slice()
+        };
+
+        if adler32_d.is_some_and(|checksum| checksum != adler32(1, data))
+            || crc32_d.is_some_and(|checksum| checksum != crc32(0, data))
+        {
+            return Err(invalid_data("checksum error"));
+        }
+
+        writer.write_all(data)?;
+    }
+
+    Ok(stats)
+}
+
+struct HeaderReader<'a, R: Read> {
+    inner: &'a mut R,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> HeaderReader<'_, R> {
+    fn bytes(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![0; len];
+        self.inner.read_exact(&mut bytes)?;
+        self.buf.extend_from_slice(&bytes);
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    /// Read a u32 which is not included in the header checksum.
+    fn inner_u32(&mut self) -> io::Result<u32> {
+        read_u32(self.inner)
+    }
+}
+
+fn header_checksum(crc: bool, buf: &[u8]) -> u32 {
+    if crc { crc32(0, buf) } else { adler32(1, buf) }
+}
+
+fn read_checksum(reader: &mut impl Read, present: bool) -> io::Result<Option<u32>> {
+    if present {
+        read_u32(reader).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(u32::from_be_bytes(bytes))
+}
+
+/// Read from the given `reader` until `buf` is full or the end of the reader is reached.
+pub fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(len)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,562 @@
+#![forbid(unsafe_code)]
+
+//! Command-line tool to compress and decompress files with LZO1X, compatible with lzop.
+
+mod checksum;
+mod lzop;
+
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant, SystemTime},
+};
+
+use lzo1x::{CompressLevel, compress, decompress};
+
+use lzop::Header;
+
+const USAGE: &str = "\
+Usage: lzo1x [OPTION]... [FILE]...
+
+Compress or decompress FILEs with LZO1X, in the lzop file format.
+Without FILE, or when FILE is -, read standard input.
+
+Modes:
+  -z, --compress        compress (default)
+  -d, --decompress      decompress
+  -t, --test            test compressed files
+  -l, --list            list compressed files
+  -b, --benchmark       report compression ratio and throughput per level
+
+Options:
+  -1 .. -9              compression level as used by lzop
+      --level=LEVEL     compression level 1-13, where 1-4 use LZO1X-1 and 5-13 use LZO1X-999
+  -c, --stdout          write to standard output
+  -o, --output=FILE     write to FILE
+  -f, --force           overwrite existing output files
+  -U, --delete          delete input files after successful operation
+  -S, --suffix=SUFFIX   use SUFFIX instead of .lzo
+      --raw             read and write headerless LZO1X data
+      --size=SIZE       decompressed size of raw data
+  -h, --help            display this help and exit
+  -V, --version         display version information and exit
+";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Compress,
+    Decompress,
+    Test,
+    List,
+    Benchmark,
+}
+
+struct Options {
+    mode: Mode,
+    level: Option<u8>,
+    stdout: bool,
+    output: Option<PathBuf>,
+    force: bool,
+    delete: bool,
+    suffix: String,
+    raw: bool,
+    size: Option<usize>,
+    files: Vec<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => return ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("lzo1x: {err}");
+            eprintln!("Try 'lzo1x --help' for more information.");
+
+            return ExitCode::from(1);
+        }
+    };
+
+    let mut result = ExitCode::SUCCESS;
+
+    if options.mode == Mode::List && !options.raw {
+        println!(" method      compressed  uncompr. ratio uncompressed_name");
+    }
+
+    for file in &options.files {
+        let name = if is_stdin(file) {
+            "<stdin>".into()
+        } else {
+            file.display().to_string()
+        };
+
+        let result_file = match options.mode {
+            Mode::Compress => compress_file(&options, file),
+            Mode::Decompress | Mode::Test => decompress_file(&options, file),
+            Mode::List => list_file(&options, file, &name),
+            Mode::Benchmark => benchmark_file(&options, file, &name),
+        };
+
+        if let Err(err) = result_file {
+            eprintln!("lzo1x: {name}: {err}");
+            result = ExitCode::from(1);
+        }
+    }
+
+    result
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options {
+        mode: Mode::Compress,
+        level: None,
+        stdout: false,
+        output: None,
+        force: false,
+        delete: false,
+        suffix: lzop::SUFFIX.into(),
+        raw: false,
+        size: None,
+        files: Vec::new(),
+    };
+
+    let mut args = args.peekable();
+    let mut only_files = false;
+
+    while let Some(arg) = args.next() {
+        if only_files || arg == "-" || !arg.starts_with('-') {
+            options.files.push(arg.into());
+
+            continue;
+        }
+
+        if arg == "--" {
+            only_files = true;
+
+            continue;
+        }
+
+        // Split the argument into options with their optional inline values.
+        let opts: Vec<(String, Option<String>)> = if let Some(long) = arg.strip_prefix("--") {
+            match long.split_once('=') {
+                Some((name, value)) => vec![(name.into(), Some(value.into()))],
+                None => vec![(long.into(), None)],
+            }
+        } else {
+            let short = &arg[1..];
+
+            match short.char_indices().find(|&(_, c)| matches!(c, 'o' | 'S')) {
+                Some((i, c)) if i + 1 < short.len() => short[..i]
+                    .chars()
+                    .map(|c| (c.to_string(), None))
+                    .chain([(c.to_string(), Some(short[i + 1..].into()))])
+                    .collect(),
+                _ => short.chars().map(|c| (c.to_string(), None)).collect(),
+            }
+        };
+
+        for (name, value) in opts {
+            let mut value = |name: &str| {
+                value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("option '{name}' requires an argument"))
+            };
+
+            match name.as_str() {
+                "z" | "compress" => options.mode = Mode::Compress,
+                "d" | "decompress" | "uncompress" => options.mode = Mode::Decompress,
+                "t" | "test" => options.mode = Mode::Test,
+                "l" | "list" => options.mode = Mode::List,
+                "b" | "benchmark" => options.mode = Mode::Benchmark,
+                "c" | "stdout" | "to-stdout" => options.stdout = true,
+                "o" | "output" => options.output = Some(value(&name)?.into()),
+                "f" | "force" => options.force = true,
+                "U" | "delete" => options.delete = true,
+                "S" | "suffix" => options.suffix = value(&name)?,
+                "raw" => options.raw = true,
+                "size" => {
+                    let size = value(&name)?;
+
+                    options.size =
+                        Some(size.parse().map_err(|_| format!("invalid size '{size}'"))?);
+                }
+                "level" => {
+                    let level = value(&name)?;
+
+                    match level.parse() {
+                        Ok(level @ 1..=13) => options.level = Some(level),
+                        _ => return Err(format!("invalid compression level '{level}'")),
+                    }
+                }
+                "1" => options.level = Some(4),
+                "2" | "3" | "4" | "5" | "6" => options.level = Some(3),
+                "7" | "8" | "9" => options.level = Some(name.parse::<u8>().unwrap() + 4),
+                "h" | "help" => {
+                    print!("{USAGE}");
+
+                    return Ok(None);
+                }
+                "V" | "version" => {
+                    println!("lzo1x {}", env!("CARGO_PKG_VERSION"));
+
+                    return Ok(None);
+                }
+                _ => return Err(format!("unrecognized option '{name}'")),
+            }
+        }
+    }
+
+    if options.files.is_empty() {
+        options.files.push("-".into());
+    }
+
+    if options.output.is_some() && options.files.len() > 1 {
+        return Err("option '--output' requires a single input file".into());
+    }
+
+    if options.raw && options.mode != Mode::Compress && options.mode != Mode::Benchmark {
+        if options.mode == Mode::List {
+            return Err("option '--list' is not supported for raw data".into());
+        }
+
+        if options.size.is_none() {
+            return Err("option '--raw' requires '--size' for decompression".into());
+        }
+    }
+
+    if options.suffix.is_empty() {
+        return Err("invalid suffix".into());
+    }
+
+    Ok(Some(options))
+}
+
+fn compress_file(options: &Options, path: &Path) -> io::Result<()> {
+    let level = options.level.unwrap_or(3);
+
+    let (mut reader, header) = if is_stdin(path) {
+        let header = Header::new(
+            level,
+            lzop::F_STDIN,
+            0o644,
+            unix_time(SystemTime::now()),
+            String::new(),
+        );
+
+        (Box::new(io::stdin().lock()) as Box<dyn Read>, header)
+    } else {
+        if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with(&options.suffix))
+        {
+            return Err(io::Error::other(format!(
+                "already has {} suffix",
+                options.suffix
+            )));
+        }
+
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let header = Header::new(
+            level,
+            0,
+            file_mode(&metadata),
+            unix_time(metadata.modified()?),
+            name,
+        );
+
+        (Box::new(BufReader::new(file)) as Box<dyn Read>, header)
+    };
+
+    let output = output_path(options, path, |path| {
+        let mut path = path.as_os_str().to_owned();
+        path.push(&options.suffix);
+
+        Ok(path.into())
+    })?;
+
+    if output.is_none() && io::stdout().is_terminal() && !options.force {
+        return Err(io::Error::other(
+            "compressed data not written to a terminal",
+        ));
+    }
+
+    write_output(options, path, output.as_deref(), None, |writer| {
+        let level = CompressLevel::new(level);
+
+        if options.raw {
+            let mut src = Vec::new();
+            reader.read_to_end(&mut src)?;
+
+            return writer.write_all(&compress(&src, level));
+        }
+
+        header.write(writer)?;
+        lzop::compress_blocks(&mut reader, writer, level, header.flags)
+    })
+}
+
+fn decompress_file(options: &Options, path: &Path) -> io::Result<()> {
+    let mut reader = open(path)?;
+
+    if options.raw {
+        let mut src = Vec::new();
+        reader.read_to_end(&mut src)?;
+
+        let mut dst = vec![0; options.size.unwrap()];
+        decompress(&src, &mut dst)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        if options.mode == Mode::Test {
+            return Ok(());
+        }
+
+        let output = output_path(options, path, |path| strip_suffix(options, path))?;
+
+        return write_output(options, path, output.as_deref(), None, |writer| {
+            writer.write_all(&dst)
+        });
+    }
+
+    let header = Header::read(&mut reader)?;
+
+    if options.mode == Mode::Test {
+        lzop::decompress_blocks(&mut reader, Some(&mut io::sink()), header.flags)?;
+
+        return Ok(());
+    }
+
+    let output = output_path(options, path, |path| strip_suffix(options, path))?;
+
+    write_output(options, path, output.as_deref(), Some(&header), |writer| {
+        lzop::decompress_blocks(&mut reader, Some(writer), header.flags)?;
+
+        Ok(())
+    })
+}
+
+fn list_file(_options: &Options, path: &Path, name: &str) -> io::Result<()> {
+    let mut reader = open(path)?;
+
+    let header = Header::read(&mut reader)?;
+    let stats = lzop::decompress_blocks(&mut reader, None, header.flags)?;
+
+    let ratio = if stats.uncompressed > 0 {
+        stats.compressed as f64 / stats.uncompressed as f64 * 100.0
+    } else {
+        0.0
+    };
+
+    let uncompressed_name = if header.name.is_empty() {
+        name.strip_suffix(lzop::SUFFIX).unwrap_or(name)
+    } else {
+        &header.name
+    };
+
+    println!(
+        " {:<11} {:>10} {:>9} {:>5.1}% {}",
+        header.method_name(),
+        stats.compressed,
+        stats.uncompressed,
+        ratio,
+        uncompressed_name
+    );
+
+    Ok(())
+}
+
+fn benchmark_file(options: &Options, path: &Path, name: &str) -> io::Result<()> {
+    let mut src = Vec::new();
+    open(path)?.read_to_end(&mut src)?;
+
+    let levels = match options.level {
+        Some(level) => level..=level,
+        None => 1..=13,
+    };
+
+    println!("{name}: {} bytes", src.len());
+    println!("level  compressed    ratio     compress   decompress");
+
+    for level in levels {
+        let level = CompressLevel::new(level);
+
+        let (compressed, compress_time) = measure(|| compress(&src, level));
+
+        let mut dst = vec![0; src.len()];
+        let (result, decompress_time) = measure(|| decompress(&compressed, &mut dst));
+
+        if result.is_err() || dst != src {
+            return Err(io::Error::other("decompressed data does not match"));
+        }
+
+        let ratio = if src.is_empty() {
+            0.0
+        } else {
+            compressed.len() as f64 / src.len() as f64 * 100.0
+        };
+
+        println!(
+            "{:>5} {:>11} {:>7.2}% {:>7.1} MB/s {:>7.1} MB/s",
+            level,
+            compressed.len(),
+            ratio,
+            throughput(src.len(), compress_time),
+            throughput(src.len(), decompress_time)
+        );
+    }
+
+    Ok(())
+}
+
+/// Run the given function repeatedly for at least 100 ms, and return the last result with the average duration.
+fn measure<T>(mut f: impl FnMut() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let mut runs = 1;
+    let mut result = f();
+
+    while start.elapsed() < Duration::from_millis(100) {
+        result = f();
+        runs += 1;
+    }
+
+    (result, start.elapsed() / runs)
+}
+
+fn throughput(len: usize, duration: Duration) -> f64 {
+    len as f64 / duration.as_secs_f64().max(f64::MIN_POSITIVE) / 1_000_000.0
+}
+
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+fn open(path: &Path) -> io::Result<Box<dyn Read>> {
+    if is_stdin(path) {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+/// Returns the output path for the given input `path`, or `None` for standard output.
+fn output_path(
+    options: &Options,
+    path: &Path,
+    default: impl FnOnce(&Path) -> io::Result<PathBuf>,
+) -> io::Result<Option<PathBuf>> {
+    if options.stdout {
+        Ok(None)
+    } else if let Some(output) = &options.output {
+        Ok(Some(output.clone()))
+    } else if is_stdin(path) {
+        Ok(None)
+    } else {
+        default(path).map(Some)
+    }
+}
+
+fn strip_suffix(options: &Options, path: &Path) -> io::Result<PathBuf> {
+    path.to_str()
+        .and_then(|path| path.strip_suffix(&options.suffix))
+        .filter(|path| !path.is_empty() && !path.ends_with('/'))
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::other("unknown suffix"))
+}
+
+/// Write to the given `output` file or standard output, and delete the `input` file afterwards if requested.
+fn write_output(
+    options: &Options,
+    input: &Path,
+    output: Option<&Path>,
+    header: Option<&Header>,
+    f: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    let Some(output) = output else {
+        let mut stdout = io::stdout().lock();
+        f(&mut stdout)?;
+
+        return stdout.flush();
+    };
+
+    if !options.force && output.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", output.display()),
+        ));
+    }
+
+    let mut writer = BufWriter::new(File::create(output)?);
+
+    let result = f(&mut writer).and_then(|()| writer.flush());
+
+    if let Err(err) = result {
+        drop(writer);
+        let _ = fs::remove_file(output);
+
+        return Err(err);
+    }
+
+    let file = writer.into_inner().map_err(|err| err.into_error())?;
+
+    if let Some(header) = header {
+        if header.mtime != 0 {
+            file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(header.mtime))?;
+        }
+
+        set_file_mode(&file, header.mode)?;
+    }
+
+    if options.delete && !is_stdin(input) {
+        fs::remove_file(input)?;
+    }
+
+    Ok(())
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode()
+}
+
+#[cfg(not(unix))]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    if metadata.permissions().readonly() {
+        0o100444
+    } else {
+        0o100644
+    }
+}
+
+#[cfg(unix)]
+fn set_file_mode(file: &File, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Only the permission bits are restored, as the setuid, setgid and sticky bits of an untrusted file
+    // should not be applied to the decompressed file.
+    if mode & 0o777 != 0 {
+        file.set_permissions(fs::Permissions::from_mode(mode & 0o777))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_file_mode(_file: &File, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! Helpers for embedding compressed data from a build script.
+//!
+//! The helpers write the compressed data to a file, together with a Rust source file containing
+//! an [`Embedded`](crate::Embedded) expression, which can be included in the crate with [`include!`].
+//!
+//! # Examples
+//!
+//! In `build.rs`:
+//!
+//! ```no_run
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! let table: Vec<u8> = (0..=255).collect();
+//!
+//! lzo1x::build::embed(&table, lzo1x::CompressLevel::new(13), format!("{out_dir}/table.lzo")).unwrap();
+//! ```
+//!
+//! In the crate:
+//!
+//! ```ignore
+//! static TABLE: lzo1x::Embedded = include!(concat!(env!("OUT_DIR"), "/table.lzo.rs"));
+//! ```
+
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{CompressLevel, compress};
+
+/// Compress the given `data` with the given compression `level`, and write it to the file at the given `path`.
+///
+/// Additionally, a Rust source file containing an [`Embedded`](crate::Embedded) expression for the compressed data
+/// is written to the same path with `.rs` appended.
+///
+/// #### Errors
+///
+/// This function returns an error if writing either of the files fails.
+pub fn embed(data: &[u8], level: CompressLevel, path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+
+    fs::write(path, compress(data, level))?;
+
+    // Use an absolute path, as `include_bytes!` resolves relative paths from the including file.
+    let path = fs::canonicalize(path)?;
+
+    let source = format!(
+        "::lzo1x::Embedded::new(include_bytes!({:?}), {})\n",
+        path.to_str().ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "path is not valid UTF-8"
+        ))?,
+        data.len()
+    );
+
+    fs::write(source_path(&path), source)
+}
+
+/// Compress the file at the given `src_path` with the given compression `level`,
+/// and write it to the file at the given `dst_path`.
+///
+/// See [`embed`] for the files that are written.
+/// The build script is rerun when the file at the given `src_path` changes.
+///
+/// #### Errors
+///
+/// This function returns an error if reading the file at the given `src_path` fails,
+/// or if writing either of the files fails.
+pub fn embed_file(
+    src_path: impl AsRef<Path>,
+    level: CompressLevel,
+    dst_path: impl AsRef<Path>,
+) -> io::Result<()> {
+    let src_path = src_path.as_ref();
+
+    println!("cargo::rerun-if-changed={}", src_path.display());
+
+    embed(&fs::read(src_path)?, level, dst_path)
+}
+
+fn source_path(path: &Path) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(".rs");
+
+    path.into()
+}
@@ -0,0 +1,22 @@
+const ADLER32_BASE: u32 = 65521;
+const ADLER32_NMAX: usize = 5552;
+
+/// Update the given Adler-32 checksum `adler` with the given `buf`.
+///
+/// The initial checksum value is 1.
+pub(crate) fn adler32(adler: u32, buf: &[u8]) -> u32 {
+    let mut s1 = adler & 0xffff;
+    let mut s2 = adler >> 16;
+
+    for chunk in buf.chunks(ADLER32_NMAX) {
+        for &byte in chunk {
+            s1 += byte as u32;
+            s2 += s1;
+        }
+
+        s1 %= ADLER32_BASE;
+        s2 %= ADLER32_BASE;
+    }
+
+    (s2 << 16) | s1
+}
@@ -1,241 +1,300 @@
-use alloc::{vec, vec::Vec};
+use core::ops::ControlFlow;
+
 use cfg_if::cfg_if;
 
-use crate::config::{
-    M2_MAX_LEN, M2_MAX_OFFSET, M3_MARKER, M3_MAX_LEN, M3_MAX_OFFSET, M4_MARKER, M4_MAX_LEN,
+use crate::{
+    CompressError,
+    config::M4_MARKER,
+    output::Output,
+    token::{store_match, store_run_len},
 };
 
-pub fn compress_1(src: &[u8], d_bits: u32) -> Vec<u8> {
-    let src_len = src.len();
+const BLOCK_LEN: usize = 49152;
+
+// Number of distinct offsets that can be stored in a dictionary entry.
+const DICT_OFFSETS: usize = 0x10000;
+
+/// Resumable LZO1X-1 compressor.
+///
+/// The input is given as a buffer which may grow between calls to [`Compressor::compress`].
+/// Every call to [`Compressor::compress`] with `finish` set ends the current block with an end marker.
+pub struct Compressor {
+    d_bits: u32,
+    max_offset: usize,
+    max_len: usize,
+    acceleration: usize,
+    src_pos: usize,
+    t: usize,
+    dict_base: Option<usize>,
+}
 
-    let mut dst = vec![0; src_len + (src_len / 16) + 64 + 3];
-    let mut work_mem = vec![0; 1 << d_bits];
+impl Compressor {
+    /// Create a new compressor, which emits matches with an offset of at most `max_offset`,
+    /// and a length of at most `max_len`.
+    ///
+    /// Positions without a match are skipped in steps of at least `acceleration` bytes.
+    pub fn new(d_bits: u32, max_offset: usize, max_len: usize, acceleration: usize) -> Self {
+        Self {
+            d_bits,
+            max_offset,
+            max_len,
+            acceleration,
+            src_pos: 0,
+            t: 0,
+            dict_base: None,
+        }
+    }
 
-    let mut src_pos = 0;
-    let mut dst_pos = 0;
-    let mut l = src_len;
-    let mut t = 0;
+    /// Start compressing a new input, which is given with the same dictionary as the previous input.
+    ///
+    /// The dictionary entries of the previous input are recognized as stale,
+    /// so the dictionary does not need to be cleared for every input.
+    #[cfg(feature = "alloc")]
+    pub fn reset(&mut self) {
+        self.src_pos = 0;
+        self.t = 0;
+    }
 
-    while l > 20 {
-        let ll = l.min(49152);
+    /// Required length of the dictionary in bytes.
+    pub fn dict_len(&self) -> usize {
+        2 << self.d_bits
+    }
 
-        if (t + ll) >> 5 == 0 {
-            break;
-        }
+    /// Index of the first byte in the input buffer that is still required.
+    #[cfg(feature = "alloc")]
+    pub fn retain_from(&self) -> usize {
+        self.src_pos - self.t
+    }
 
-        work_mem.fill(0);
+    /// Notify the compressor that the first `n` bytes of the input buffer were discarded.
+    #[cfg(feature = "alloc")]
+    pub fn shift(&mut self, n: usize) {
+        self.src_pos -= n;
+    }
 
-        let (new_t, out_len) = {
-            let src_start = src_pos;
-            let src_len = ll;
-            let src_pos_end = src_start + src_len - 20;
-            let dst_start = dst_pos;
-            let dict = &mut work_mem;
+    /// Compress the input given in `src` as far as possible, appending the compressed data to `dst`.
+    ///
+    /// If `finish` is set, all of the input is compressed and the current block is ended.
+    /// The given `dict` must be at least [`Compressor::dict_len`] bytes long.
+    pub fn compress(
+        &mut self,
+        src: &[u8],
+        dst: &mut impl Output,
+        finish: bool,
+        dict: &mut [u8],
+        callback: &mut dyn FnMut(usize, usize) -> ControlFlow<()>,
+    ) -> Result<(), CompressError> {
+        let src_len = src.len();
+        let dict = &mut dict[..self.dict_len()];
+
+        let mut src_pos = self.src_pos;
+        let mut dst_pos = dst.len();
+        let mut l = src_len - src_pos;
+        let mut t = self.t;
+
+        while l > 20 {
+            let ll = l.min(BLOCK_LEN);
+
+            if !finish && l <= BLOCK_LEN + 20 {
+                break;
+            }
 
-            let mut src_pos = src_start;
-            let mut dst_pos = dst_pos;
-            let mut ti = t;
-            let mut ii = src_pos;
+            if (t + ll) >> 5 == 0 {
+                break;
+            }
 
-            if ti < 4 {
-                src_pos += 4 - ti;
+            let len = dst_pos + t + ll + ((t + ll) / 16) + 64;
+
+            if dst.grow(len).is_err() {
+                dst.truncate(dst_pos);
+                return Err(CompressError::OutOfMemory);
             }
 
-            src_pos += 1 + ((src_pos - ii) >> 5);
+            // Entries of previous blocks are offset by less than the base of the current block,
+            // so the dictionary only needs to be cleared when the offsets of the block no longer fit.
+            let dict_base = match self.dict_base {
+                Some(dict_base) if dict_base + ll <= DICT_OFFSETS => dict_base,
+                _ => {
+                    dict.fill(0);
+                    0
+                }
+            };
+
+            let (new_t, out_len) = compress_block(
+                src,
+                src_pos,
+                ll,
+                t,
+                dst.buf(),
+                dst_pos,
+                dict,
+                dict_base,
+                self.d_bits,
+                self.max_offset,
+                self.max_len,
+                self.acceleration,
+            );
+
+            t = new_t;
+
+            src_pos += ll;
+            dst_pos += out_len;
+            l -= ll;
+
+            self.src_pos = src_pos;
+            self.t = t;
+            self.dict_base = Some(dict_base + ll);
+
+            if callback(src_pos, dst_pos).is_break() {
+                dst.truncate(dst_pos);
+                return Err(CompressError::Cancelled);
+            }
+        }
 
-            'main_loop: loop {
-                let mut match_pos;
+        if !finish {
+            dst.truncate(dst_pos);
+            return Ok(());
+        }
 
-                loop {
-                    if src_pos >= src_pos_end {
-                        break 'main_loop;
-                    }
+        t += l;
 
-                    let dv = get_u32_le(src, src_pos);
-                    let dindex = ((0x1824429du32.wrapping_mul(dv)) >> (32 - d_bits)) as usize;
-                    match_pos = src_start + dict[dindex] as usize;
-                    dict[dindex] = (src_pos - src_start) as u16;
+        let len = dst_pos + t + (t / 255) + 8;
 
-                    if dv == get_u32_le(src, match_pos) {
-                        break;
-                    }
+        if dst.grow(len).is_err() {
+            dst.truncate(dst_pos);
+            return Err(CompressError::OutOfMemory);
+        }
 
-                    src_pos += 1 + ((src_pos - ii) >> 5);
-                }
+        let buf = dst.buf();
 
-                ii -= ti;
-                ti = 0;
-                let t = src_pos - ii;
+        if t > 0 {
+            let ii = src_len - t;
 
-                match t {
-                    0 => {}
-                    1..=3 => {
-                        dst[dst_pos - 2] |= t as u8;
-                    }
-                    4..=18 => {
-                        dst[dst_pos] = t as u8 - 3;
-                        dst_pos += 1;
-                    }
-                    19.. => {
-                        let mut tt = t - 18;
-                        dst[dst_pos] = 0;
-                        dst_pos += 1;
-
-                        while tt > 255 {
-                            tt -= 255;
-                            dst[dst_pos] = 0;
-                            dst_pos += 1;
-                        }
-
-                        dst[dst_pos] = tt as u8;
-                        dst_pos += 1;
-                    }
-                }
+            if dst_pos == 0 && t <= 238 {
+                buf[dst_pos] = 17 + t as u8;
+                dst_pos += 1;
+            } else {
+                dst_pos = store_run_len(buf, dst_pos, t);
+            }
+
+            buf[dst_pos..dst_pos + t].copy_from_slice(&src[ii..ii + t]);
+            dst_pos += t;
+        }
 
-                dst[dst_pos..dst_pos + t].copy_from_slice(&src[ii..ii + t]);
-                dst_pos += t;
+        buf[dst_pos] = (M4_MARKER | 1) as u8;
+        dst_pos += 1;
+        buf[dst_pos] = 0;
+        dst_pos += 1;
+        buf[dst_pos] = 0;
+        dst_pos += 1;
 
-                let mut match_len = 4;
+        dst.truncate(dst_pos);
 
-                loop {
-                    let v = get_u64_ne(src, src_pos + match_len)
-                        ^ get_u64_ne(src, match_pos + match_len);
+        self.src_pos = src_len;
+        self.t = 0;
 
-                    if v != 0 {
-                        cfg_if! {
-                            if #[cfg(target_endian = "little")] {
-                                match_len += v.trailing_zeros() as usize / 8;
-                            } else if #[cfg(target_endian = "big")] {
-                                match_len += v.leading_zeros() as usize / 8;
-                            }
-                        }
+        if callback(src_len, dst_pos).is_break() {
+            return Err(CompressError::Cancelled);
+        }
 
-                        break;
-                    }
+        Ok(())
+    }
+}
 
-                    match_len += 8;
+#[allow(clippy::too_many_arguments)]
+fn compress_block(
+    src: &[u8],
+    src_start: usize,
+    src_len: usize,
+    t: usize,
+    dst: &mut [u8],
+    mut dst_pos: usize,
+    dict: &mut [u8],
+    dict_base: usize,
+    d_bits: u32,
+    max_offset: usize,
+    max_len: usize,
+    acceleration: usize,
+) -> (usize, usize) {
+    let src_pos_end = src_start + src_len - 20;
+    let dst_start = dst_pos;
+
+    let mut src_pos = src_start;
+    let mut ti = t;
+    let mut ii = src_pos;
+
+    if ti < 4 {
+        src_pos += 4 - ti;
+    }
 
-                    if src_pos + match_len >= src_pos_end {
-                        break;
-                    }
-                }
+    src_pos += acceleration + ((src_pos - ii) >> 5);
 
-                let mut match_off = src_pos - match_pos;
-                src_pos += match_len;
-                ii = src_pos;
-
-                if match_len <= M2_MAX_LEN && match_off <= M2_MAX_OFFSET {
-                    match_off -= 1;
-                    dst[dst_pos] = (((match_len - 1) << 5) | ((match_off & 7) << 2)) as u8;
-                    dst_pos += 1;
-                    dst[dst_pos] = (match_off >> 3) as u8;
-                    dst_pos += 1;
-                } else if match_off <= M3_MAX_OFFSET {
-                    match_off -= 1;
-
-                    if match_len <= M3_MAX_LEN {
-                        dst[dst_pos] = (M3_MARKER | (match_len - 2)) as u8;
-                        dst_pos += 1;
-                    } else {
-                        match_len -= M3_MAX_LEN;
-                        dst[dst_pos] = M3_MARKER as u8;
-                        dst_pos += 1;
-
-                        while match_len > 255 {
-                            match_len -= 255;
-                            dst[dst_pos] = 0;
-                            dst_pos += 1;
-                        }
-
-                        dst[dst_pos] = match_len as u8;
-                        dst_pos += 1;
-                    }
+    'main_loop: loop {
+        let mut match_pos;
 
-                    dst[dst_pos] = (match_off << 2) as u8;
-                    dst_pos += 1;
-                    dst[dst_pos] = (match_off >> 6) as u8;
-                    dst_pos += 1;
-                } else {
-                    match_off -= 0x4000;
-
-                    if match_len <= M4_MAX_LEN {
-                        dst[dst_pos] =
-                            (M4_MARKER | ((match_off >> 11) & 8) | (match_len - 2)) as u8;
-                        dst_pos += 1;
-                    } else {
-                        match_len -= M4_MAX_LEN;
-                        dst[dst_pos] = (M4_MARKER | ((match_off >> 11) & 8)) as u8;
-                        dst_pos += 1;
-
-                        while match_len > 255 {
-                            match_len -= 255;
-                            dst[dst_pos] = 0;
-                            dst_pos += 1;
-                        }
-
-                        dst[dst_pos] = match_len as u8;
-                        dst_pos += 1;
-                    }
+        loop {
+            if src_pos >= src_pos_end {
+                break 'main_loop;
+            }
 
-                    dst[dst_pos] = (match_off << 2) as u8;
-                    dst_pos += 1;
-                    dst[dst_pos] = (match_off >> 6) as u8;
-                    dst_pos += 1;
-                }
+            let dv = get_u32_le(src, src_pos);
+            let dindex = ((0x1824429du32.wrapping_mul(dv)) >> (32 - d_bits)) as usize;
+            let entry = &mut dict[dindex * 2..dindex * 2 + 2];
+            // Stale entries refer to the start of the block, like the entries of a cleared dictionary.
+            match_pos = src_start
+                + (u16::from_ne_bytes([entry[0], entry[1]]) as usize).saturating_sub(dict_base);
+            entry.copy_from_slice(&((src_pos - src_start + dict_base) as u16).to_ne_bytes());
+
+            if dv == get_u32_le(src, match_pos) && src_pos - match_pos <= max_offset {
+                break;
             }
 
-            ((src_start + src_len) - (ii - ti), dst_pos - dst_start)
-        };
+            src_pos += acceleration + ((src_pos - ii) >> 5);
+        }
 
-        t = new_t;
+        ii -= ti;
+        ti = 0;
+        let t = src_pos - ii;
 
-        src_pos += ll;
-        dst_pos += out_len;
-        l -= ll;
-    }
+        dst_pos = store_run_len(dst, dst_pos, t);
 
-    t += l;
+        dst[dst_pos..dst_pos + t].copy_from_slice(&src[ii..ii + t]);
+        dst_pos += t;
 
-    if t > 0 {
-        let ii = src_len - t;
+        let mut match_len = 4;
 
-        if dst_pos == 0 && t <= 238 {
-            dst[dst_pos] = 17 + t as u8;
-            dst_pos += 1;
-        } else if t <= 3 {
-            dst[dst_pos - 2] |= t as u8;
-        } else if t <= 18 {
-            dst[dst_pos] = t as u8 - 3;
-            dst_pos += 1;
-        } else {
-            let mut tt = t - 18;
+        loop {
+            let v = get_u64_ne(src, src_pos + match_len) ^ get_u64_ne(src, match_pos + match_len);
 
-            dst[dst_pos] = 0;
-            dst_pos += 1;
+            if v != 0 {
+                cfg_if! {
+                    if #[cfg(target_endian = "little")] {
+                        match_len += v.trailing_zeros() as usize / 8;
+                    } else if #[cfg(target_endian = "big")] {
+                        match_len += v.leading_zeros() as usize / 8;
+                    }
+                }
 
-            while tt > 255 {
-                tt -= 255;
-                dst[dst_pos] = 0;
-                dst_pos += 1;
+                break;
             }
 
-            dst[dst_pos] = tt as u8;
-            dst_pos += 1;
+            match_len += 8;
+
+            if src_pos + match_len >= src_pos_end {
+                break;
+            }
         }
 
-        dst[dst_pos..dst_pos + t].copy_from_slice(&src[ii..ii + t]);
-        dst_pos += t;
-    }
+        match_len = match_len.min(max_len);
+
+        let match_off = src_pos - match_pos;
+        src_pos += match_len;
+        ii = src_pos;
 
-    dst[dst_pos] = (M4_MARKER | 1) as u8;
-    dst_pos += 1;
-    dst[dst_pos] = 0;
-    dst_pos += 1;
-    dst[dst_pos] = 0;
-    dst_pos += 1;
+        dst_pos = store_match(dst, dst_pos, match_len, match_off, t);
+    }
 
-    dst.resize(dst_pos, 0);
-    dst
+    ((src_start + src_len) - (ii - ti), dst_pos - dst_start)
 }
 
 fn get_u32_le(src: &[u8], src_pos: usize) -> u32 {
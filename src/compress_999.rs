@@ -1,353 +1,446 @@
-use alloc::{vec, vec::Vec};
+use core::ops::{ControlFlow, DerefMut};
 
 use crate::{
+    CompressError,
     config::{
-        M1_MARKER, M1_MAX_OFFSET, M2_MAX_LEN, M2_MAX_OFFSET, M2_MIN_LEN, M3_MARKER, M3_MAX_LEN,
-        M3_MAX_OFFSET, M4_MARKER, M4_MAX_LEN, M4_MAX_OFFSET, MX_MAX_OFFSET,
+        M1_MAX_OFFSET, M2_MAX_LEN, M2_MAX_OFFSET, M2_MIN_LEN, M3_MAX_LEN, M3_MAX_OFFSET, M4_MARKER,
+        M4_MAX_LEN, M4_MAX_OFFSET, MX_MAX_OFFSET,
     },
-    swd::{Swd, SWD_MAX_CHAIN, SWD_THRESHOLD},
+    output::Output,
+    swd::{SWD_F, SWD_MAX_CHAIN, SWD_N, SWD_THRESHOLD, Swd, Workspace999},
+    token::{store_match, store_run_len},
 };
 
-pub fn compress_999(
-    src: &[u8],
+const PROGRESS_INTERVAL: usize = 1024;
+
+// Number of bytes that should be available after the current position before continuing,
+// such that the compressor never observes the end of an input that is still being written.
+const MIN_LOOKAHEAD: usize = SWD_F + 16;
+
+pub struct Params {
     try_lazy_parm: i32,
     good_length: usize,
     max_lazy: usize,
     nice_length: usize,
     max_chain: usize,
     flags: u32,
-) -> Vec<u8> {
-    let mut dst = vec![0; src.len() + (src.len() / 16) + 64 + 3];
-
-    let params = Params {
-        try_lazy_parm,
-        good_length,
-        max_lazy,
-        nice_length,
-        max_chain,
-        flags,
-    };
-
-    let dst_len = compress_internal(src, &mut dst, params);
-
-    dst.resize(dst_len, 0);
-    dst
+    tree_depth: usize,
 }
 
-struct Params {
-    try_lazy_parm: i32,
-    good_length: usize,
-    max_lazy: usize,
-    nice_length: usize,
-    max_chain: usize,
-    flags: u32,
+impl Params {
+    pub const fn new(
+        try_lazy_parm: i32,
+        good_length: usize,
+        max_lazy: usize,
+        nice_length: usize,
+        max_chain: usize,
+        flags: u32,
+    ) -> Self {
+        Self {
+            try_lazy_parm,
+            good_length,
+            max_lazy,
+            nice_length,
+            max_chain,
+            flags,
+            tree_depth: 0,
+        }
+    }
+
+    /// Find matches with binary trees that are searched up to the given `depth`, which replaces `max_chain`.
+    pub const fn tree_depth(mut self, depth: usize) -> Self {
+        self.tree_depth = depth;
+        self
+    }
+
+    /// Returns the parameters without the extensions that change the compressed data compared to liblzo.
+    pub const fn liblzo_compatible(mut self) -> Self {
+        self.tree_depth = 0;
+        self
+    }
 }
 
-pub struct Compress<'a> {
+pub struct Compress {
     look: usize,
     m_len: usize,
     m_off: usize,
     bp: usize,
     pub src_idx: usize,
-    pub src: &'a [u8],
     r1_lit: usize,
 }
 
-fn compress_internal(src: &[u8], dst: &mut [u8], params: Params) -> usize {
-    let try_lazy_parm = params.try_lazy_parm;
-    let mut good_length = params.good_length;
-    let mut max_lazy = params.max_lazy;
-    let mut nice_length = params.nice_length;
-    let mut max_chain = params.max_chain;
-    let flags = params.flags;
+impl Compress {
+    pub const fn new() -> Self {
+        Self {
+            look: 0,
+            m_len: 0,
+            m_off: 0,
+            bp: 0,
+            src_idx: 0,
+            r1_lit: 0,
+        }
+    }
+}
 
-    let mut try_lazy: usize = try_lazy_parm as usize;
+/// Resumable LZO1X-999 compressor.
+///
+/// The input is given as a buffer which may grow between calls to [`Compressor::compress`].
+/// Every call to [`Compressor::compress`] with `finish` set ends the current block with an end marker,
+/// the next block then uses the preceding input as its dictionary.
+///
+/// The sliding window dictionary is kept in a workspace of type `W`, which is obtained at the start of every block.
+pub struct Compressor<W> {
+    try_lazy: usize,
+    good_length: usize,
+    max_lazy: usize,
+    nice_length: usize,
+    max_chain: usize,
+    use_best_off: bool,
+    tree_depth: usize,
+    swd_n: usize,
+    swd_f: usize,
+    c: Compress,
+    swd: Option<Swd<W>>,
+    ii: usize,
+    lit: usize,
+    print_count: usize,
+}
 
-    if try_lazy_parm < 0 {
-        try_lazy = 1;
-    }
+impl<W: DerefMut<Target = Workspace999>> Compressor<W> {
+    /// Create a new compressor, which emits matches with an offset of at most `max_offset`,
+    /// and a length of at most `max_len`.
+    pub fn new(params: Params, max_offset: usize, max_len: usize) -> Self {
+        let try_lazy_parm = params.try_lazy_parm;
+        let mut good_length = params.good_length;
+        let mut max_lazy = params.max_lazy;
+        let mut nice_length = params.nice_length;
+        let mut max_chain = params.max_chain;
+        let flags = params.flags;
+
+        let mut try_lazy: usize = try_lazy_parm as usize;
+
+        if try_lazy_parm < 0 {
+            try_lazy = 1;
+        }
 
-    if good_length == 0 {
-        good_length = 32;
-    }
+        if good_length == 0 {
+            good_length = 32;
+        }
 
-    if max_lazy == 0 {
-        max_lazy = 32;
-    }
+        if max_lazy == 0 {
+            max_lazy = 32;
+        }
+
+        if nice_length == 0 {
+            nice_length = 0;
+        }
+
+        if max_chain == 0 {
+            max_chain = SWD_MAX_CHAIN;
+        }
 
-    if nice_length == 0 {
-        nice_length = 0;
+        if params.tree_depth > 0 {
+            max_chain = params.tree_depth;
+        }
+
+        Self {
+            try_lazy,
+            good_length,
+            max_lazy,
+            nice_length,
+            max_chain,
+            use_best_off: flags & 1 != 0,
+            tree_depth: params.tree_depth,
+            swd_n: max_offset.min(SWD_N),
+            swd_f: max_len.min(SWD_F),
+            c: Compress::new(),
+            swd: None,
+            ii: 0,
+            lit: 0,
+            print_count: 0,
+        }
     }
 
-    if max_chain == 0 {
-        max_chain = SWD_MAX_CHAIN;
+    /// Index of the first byte in the input buffer that is still required.
+    #[cfg(feature = "alloc")]
+    pub fn retain_from(&self) -> usize {
+        if self.lit > 0 { self.ii } else { self.c.bp }
     }
 
-    let c = &mut Compress {
-        look: 0,
-        m_len: 0,
-        m_off: 0,
-        bp: 0,
-        src_idx: 0,
-        src,
-        r1_lit: 0,
-    };
-
-    let mut dst_idx = 0;
-    let mut ii = 0;
-    let mut lit = 0;
-
-    let mut swd = Swd::new(c);
-    swd.use_best_off = flags & 1 != 0;
-
-    if max_chain > 0 {
-        swd.max_chain = max_chain;
+    /// Notify the compressor that the first `n` bytes of the input buffer were discarded.
+    #[cfg(feature = "alloc")]
+    pub fn shift(&mut self, n: usize) {
+        self.c.src_idx -= n;
+        self.c.bp -= n;
+        self.ii = self.ii.saturating_sub(n);
+        self.print_count = self.print_count.saturating_sub(n);
     }
 
-    if nice_length > 0 {
-        swd.nice_length = nice_length;
+    /// Compress the input given in `src` as far as possible, appending the compressed data to `dst`.
+    ///
+    /// If `finish` is set, all of the input is compressed and the current block is ended.
+    /// The `workspace` function is called when a new block is started.
+    pub fn compress(
+        &mut self,
+        src: &[u8],
+        dst: &mut impl Output,
+        finish: bool,
+        workspace: impl FnOnce() -> Result<W, CompressError>,
+        callback: &mut dyn FnMut(usize, usize) -> ControlFlow<()>,
+    ) -> Result<(), CompressError> {
+        let result = self.compress_internal(src, dst, finish, workspace, callback);
+
+        if result.is_err() {
+            self.swd = None;
+        }
+
+        result
     }
 
-    find_match(c, &mut swd, 0, 0);
+    fn compress_internal(
+        &mut self,
+        src: &[u8],
+        dst: &mut impl Output,
+        finish: bool,
+        workspace: impl FnOnce() -> Result<W, CompressError>,
+        callback: &mut dyn FnMut(usize, usize) -> ControlFlow<()>,
+    ) -> Result<(), CompressError> {
+        let try_lazy = self.try_lazy;
+        let good_length = self.good_length;
+        let max_lazy = self.max_lazy;
+        let max_chain = self.max_chain;
+
+        let c = &mut self.c;
+
+        let swd = match &mut self.swd {
+            Some(swd) => swd,
+            None => {
+                if !finish && src.len() - c.src_idx < MIN_LOOKAHEAD {
+                    return Ok(());
+                }
 
-    let mut m_len;
-    let mut m_off;
+                let dict_len = c.src_idx.min(self.swd_n);
+
+                let mut swd = Swd::new(
+                    c,
+                    src,
+                    dict_len,
+                    self.swd_n,
+                    self.swd_f,
+                    self.tree_depth,
+                    workspace()?,
+                );
+                swd.use_best_off = self.use_best_off;
+
+                if max_chain > 0 {
+                    swd.max_chain = max_chain;
+                }
 
-    while c.look > 0 {
-        m_len = c.m_len;
-        m_off = c.m_off;
+                if self.nice_length > 0 {
+                    swd.nice_length = self.nice_length;
+                }
 
-        if lit == 0 {
-            ii = c.bp;
-        }
+                find_match(c, &mut swd, src, 0, 0);
 
-        if m_len < 2
-            || (m_len == 2 && (m_off > M1_MAX_OFFSET || lit == 0 || lit >= 4))
-            || (m_len == 2 && dst_idx == 0)
-            || (dst_idx == 0 && lit == 0)
-            || (m_len == M2_MIN_LEN && m_off > MX_MAX_OFFSET && lit >= 4)
-        {
-            m_len = 0;
-        }
+                self.lit = 0;
+                self.swd.insert(swd)
+            }
+        };
 
-        if m_len == 0 {
-            lit += 1;
-            swd.max_chain = max_chain;
-            find_match(c, &mut swd, 1, 0);
-            continue;
-        }
+        let mut dst_idx = dst.len();
+        let mut ii = self.ii;
+        let mut lit = self.lit;
 
-        if swd.use_best_off {
-            better_match(&mut swd, &mut m_len, &mut m_off);
-        }
+        let mut m_len;
+        let mut m_off;
 
-        let mut ahead = 0usize;
-        let l1;
-        let max_ahead: usize;
+        while c.look > 0 {
+            if !finish && src.len() - c.src_idx < MIN_LOOKAHEAD {
+                break;
+            }
 
-        if try_lazy == 0 || m_len >= max_lazy {
-            l1 = 0;
-            max_ahead = 0;
-        } else {
-            l1 = len_of_coded_match(m_len, m_off, lit);
-            max_ahead = try_lazy.min(l1 - 1);
-        }
+            if c.bp > self.print_count {
+                if callback(c.bp, dst_idx).is_break() {
+                    return Err(CompressError::Cancelled);
+                }
 
-        let mut l2;
+                self.print_count += PROGRESS_INTERVAL;
+            }
 
-        let mut flag = true;
+            let len = dst_idx + lit + (lit / 255) + 32;
 
-        while ahead < max_ahead && c.look > m_len {
-            if m_len >= good_length {
-                swd.max_chain = max_chain >> 2;
-            } else {
-                swd.max_chain = max_chain;
+            if dst.grow(len).is_err() {
+                dst.truncate(dst_idx);
+                return Err(CompressError::OutOfMemory);
             }
 
-            find_match(c, &mut swd, 1, 0);
-            ahead += 1;
+            m_len = c.m_len;
+            m_off = c.m_off;
 
-            if c.m_len < m_len {
-                continue;
+            if lit == 0 {
+                ii = c.bp;
             }
 
-            if c.m_len == m_len && c.m_off >= m_off {
+            if m_len < 2
+                || (m_len == 2 && (m_off > M1_MAX_OFFSET || lit == 0 || lit >= 4))
+                || (m_len == 2 && dst_idx == 0)
+                || (dst_idx == 0 && lit == 0)
+                || (m_len == M2_MIN_LEN && m_off > MX_MAX_OFFSET && lit >= 4)
+            {
+                m_len = 0;
+            }
+
+            if m_len == 0 {
+                lit += 1;
+                swd.max_chain = max_chain;
+                find_match(c, swd, src, 1, 0);
                 continue;
             }
 
             if swd.use_best_off {
-                better_match(&mut swd, &mut c.m_len, &mut c.m_off);
+                better_match(swd, &mut m_len, &mut m_off);
             }
 
-            l2 = len_of_coded_match(c.m_len, c.m_off, lit + ahead);
+            let mut ahead = 0usize;
+            let l1;
+            let max_ahead: usize;
 
-            if l2 == 0 {
-                continue;
+            if try_lazy == 0 || m_len >= max_lazy {
+                l1 = 0;
+                max_ahead = 0;
+            } else {
+                l1 = len_of_coded_match(m_len, m_off, lit);
+                max_ahead = try_lazy.min(l1 - 1);
             }
 
-            let l3 = if dst_idx == 0 {
-                0
-            } else {
-                len_of_coded_match(ahead, m_off, lit)
-            };
+            let mut l2;
 
-            let lazy_match_min_gain = min_gain(ahead, lit, lit + ahead, l1, l2, l3);
+            let mut flag = true;
 
-            if c.m_len >= m_len + lazy_match_min_gain {
-                if l3 != 0 {
-                    dst_idx = code_run(c, dst, dst_idx, src, ii, lit);
-                    lit = 0;
-                    dst_idx = code_match(c, dst, dst_idx, ahead, m_off);
+            while ahead < max_ahead && c.look > m_len {
+                if m_len >= good_length {
+                    swd.max_chain = max_chain >> 2;
                 } else {
-                    lit += ahead;
+                    swd.max_chain = max_chain;
                 }
 
-                flag = false;
+                find_match(c, swd, src, 1, 0);
+                ahead += 1;
 
-                break;
-            }
-        }
+                if c.m_len < m_len {
+                    continue;
+                }
 
-        if flag {
-            dst_idx = code_run(c, dst, dst_idx, src, ii, lit);
-            lit = 0;
+                if c.m_len == m_len && c.m_off >= m_off {
+                    continue;
+                }
 
-            dst_idx = code_match(c, dst, dst_idx, m_len, m_off);
-            swd.max_chain = max_chain;
-            find_match(c, &mut swd, m_len, 1 + ahead);
-        }
-    }
+                if swd.use_best_off {
+                    better_match(swd, &mut c.m_len, &mut c.m_off);
+                }
 
-    if lit > 0 {
-        dst_idx = store_run(dst, dst_idx, src, ii, lit);
-    }
+                l2 = len_of_coded_match(c.m_len, c.m_off, lit + ahead);
 
-    dst[dst_idx] = M4_MARKER as u8 | 1;
-    dst_idx += 1;
-    dst[dst_idx] = 0;
-    dst_idx += 1;
-    dst[dst_idx] = 0;
-    dst_idx += 1;
+                if l2 == 0 {
+                    continue;
+                }
 
-    dst_idx
-}
+                let l3 = if dst_idx == 0 {
+                    0
+                } else {
+                    len_of_coded_match(ahead, m_off, lit)
+                };
 
-fn store_run(dst: &mut [u8], mut dst_idx: usize, src: &[u8], mut ii: usize, mut t: usize) -> usize {
-    if dst_idx == 0 && t <= 238 {
-        dst[dst_idx] = (17 + t) as u8;
-        dst_idx += 1;
-    } else if t <= 3 {
-        dst[dst_idx - 2] |= t as u8;
-    } else if t <= 18 {
-        dst[dst_idx] = (t - 3) as u8;
-        dst_idx += 1;
-    } else {
-        let mut tt = t - 18;
+                let lazy_match_min_gain = min_gain(ahead, lit, lit + ahead, l1, l2, l3);
 
-        dst[dst_idx] = 0;
-        dst_idx += 1;
+                if c.m_len >= m_len + lazy_match_min_gain {
+                    if l3 != 0 {
+                        dst_idx = code_run(c, dst.buf(), dst_idx, src, ii, lit);
+                        lit = 0;
+                        dst_idx = store_match(dst.buf(), dst_idx, ahead, m_off, c.r1_lit);
+                    } else {
+                        lit += ahead;
+                    }
 
-        while tt > 255 {
-            tt -= 255;
-            dst[dst_idx] = 0;
-            dst_idx += 1;
+                    flag = false;
+
+                    break;
+                }
+            }
+
+            if flag {
+                dst_idx = code_run(c, dst.buf(), dst_idx, src, ii, lit);
+                lit = 0;
+
+                dst_idx = store_match(dst.buf(), dst_idx, m_len, m_off, c.r1_lit);
+                swd.max_chain = max_chain;
+                find_match(c, swd, src, m_len, 1 + ahead);
+            }
         }
 
-        dst[dst_idx] = tt as u8;
-        dst_idx += 1;
-    }
+        if c.look > 0 {
+            self.ii = ii;
+            self.lit = lit;
 
-    if t == 0 {
-        dst[dst_idx] = src[ii];
-        dst_idx += 1;
-    } else {
-        while t > 0 {
-            dst[dst_idx] = src[ii];
-            dst_idx += 1;
-            ii += 1;
-            t -= 1;
+            dst.truncate(dst_idx);
+
+            return Ok(());
         }
-    }
 
-    dst_idx
-}
+        let len = dst_idx + lit + (lit / 255) + 8;
 
-fn code_match(
-    c: &mut Compress,
-    dst: &mut [u8],
-    mut dst_idx: usize,
-    mut m_len: usize,
-    mut m_off: usize,
-) -> usize {
-    if m_len == 2 {
-        m_off -= 1;
+        if dst.grow(len).is_err() {
+            dst.truncate(dst_idx);
+            return Err(CompressError::OutOfMemory);
+        }
 
-        dst[dst_idx] = (M1_MARKER | ((m_off & 3) << 2)) as u8;
-        dst_idx += 1;
-        dst[dst_idx] = (m_off >> 2) as u8;
-        dst_idx += 1;
-    } else if m_len <= M2_MAX_LEN && m_off <= M2_MAX_OFFSET {
-        m_off -= 1;
-        dst[dst_idx] = (((m_len - 1) << 5) | ((m_off & 7) << 2)) as u8;
-        dst_idx += 1;
-        dst[dst_idx] = (m_off >> 3) as u8;
+        let buf = dst.buf();
+
+        if lit > 0 {
+            dst_idx = store_run(buf, dst_idx, src, ii, lit);
+        }
+
+        buf[dst_idx] = M4_MARKER as u8 | 1;
         dst_idx += 1;
-    } else if m_len == M2_MIN_LEN && m_off <= MX_MAX_OFFSET && c.r1_lit >= 4 {
-        m_off -= 1 + M2_MAX_OFFSET;
-        dst[dst_idx] = (M1_MARKER | ((m_off & 3) << 2)) as u8;
+        buf[dst_idx] = 0;
         dst_idx += 1;
-        dst[dst_idx] = (m_off >> 2) as u8;
+        buf[dst_idx] = 0;
         dst_idx += 1;
-    } else if m_off <= M3_MAX_OFFSET {
-        m_off -= 1;
 
-        if m_len <= M3_MAX_LEN {
-            dst[dst_idx] = (M3_MARKER | (m_len - 2)) as u8;
-            dst_idx += 1;
-        } else {
-            m_len -= M3_MAX_LEN;
-            dst[dst_idx] = M3_MARKER as u8;
-            dst_idx += 1;
+        dst.truncate(dst_idx);
 
-            while m_len > 255 {
-                m_len -= 255;
-                dst[dst_idx] = 0;
-                dst_idx += 1;
-            }
+        if let Some(swd) = self.swd.take() {
+            swd.finish();
+        }
 
-            dst[dst_idx] = m_len as u8;
-            dst_idx += 1;
+        self.lit = 0;
+
+        if callback(src.len(), dst_idx).is_break() {
+            return Err(CompressError::Cancelled);
         }
 
-        dst[dst_idx] = (m_off << 2) as u8;
-        dst_idx += 1;
-        dst[dst_idx] = (m_off >> 6) as u8;
+        Ok(())
+    }
+}
+
+fn store_run(dst: &mut [u8], mut dst_idx: usize, src: &[u8], mut ii: usize, mut t: usize) -> usize {
+    if dst_idx == 0 && t <= 238 {
+        dst[dst_idx] = (17 + t) as u8;
         dst_idx += 1;
     } else {
-        m_off -= 0x4000;
-        let k = (m_off & 0x4000) >> 11;
-        if m_len <= M4_MAX_LEN {
-            dst[dst_idx] = (M4_MARKER | k | (m_len - 2)) as u8;
-            dst_idx += 1;
-        } else {
-            m_len -= M4_MAX_LEN;
-            dst[dst_idx] = (M4_MARKER | k) as u8;
-            dst_idx += 1;
-
-            while m_len > 255 {
-                m_len -= 255;
-                dst[dst_idx] = 0;
-                dst_idx += 1;
-            }
+        dst_idx = store_run_len(dst, dst_idx, t);
+    }
 
-            dst[dst_idx] = m_len as u8;
+    if t == 0 {
+        dst[dst_idx] = src[ii];
+        dst_idx += 1;
+    } else {
+        while t > 0 {
+            dst[dst_idx] = src[ii];
             dst_idx += 1;
+            ii += 1;
+            t -= 1;
         }
-
-        dst[dst_idx] = (m_off << 2) as u8;
-        dst_idx += 1;
-        dst[dst_idx] = (m_off >> 6) as u8;
-        dst_idx += 1;
     }
 
     dst_idx
@@ -371,7 +464,11 @@ fn code_run(
     dst_idx
 }
 
-fn better_match(swd: &mut Swd, m_len: &mut usize, m_off: &mut usize) {
+fn better_match<W: DerefMut<Target = Workspace999>>(
+    swd: &mut Swd<W>,
+    m_len: &mut usize,
+    m_off: &mut usize,
+) {
     if *m_len <= M2_MIN_LEN {
         return;
     }
@@ -493,9 +590,15 @@ fn min_gain(ahead: usize, lit1: usize, lit2: usize, l1: usize, l2: usize, l3: us
     lazy_match_min_gain as usize
 }
 
-fn find_match(c: &mut Compress, s: &mut Swd, this_len: usize, skip: usize) {
+fn find_match<W: DerefMut<Target = Workspace999>>(
+    c: &mut Compress,
+    s: &mut Swd<W>,
+    src: &[u8],
+    this_len: usize,
+    skip: usize,
+) {
     if skip > 0 {
-        s.accept(c, this_len - skip);
+        s.accept(c, src, this_len - skip);
     }
 
     s.m_len = SWD_THRESHOLD;
@@ -509,7 +612,7 @@ fn find_match(c: &mut Compress, s: &mut Swd, this_len: usize, skip: usize) {
     c.m_len = s.m_len;
     c.m_off = s.m_off;
 
-    s.get_byte(c);
+    s.get_byte(c, src);
 
     if s.b_char < 0 {
         c.look = 0;
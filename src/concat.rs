@@ -0,0 +1,221 @@
+use alloc::vec::Vec;
+
+use crate::{
+    DecompressError,
+    decompress::{Insn, State, copy_match, decode_insn},
+    token::store_run_len,
+};
+
+/// Concatenate the compressed data given in `a` and `b` without recompressing,
+/// such that the returned data decompresses to the decompressed data of `a` followed by that of `b`.
+///
+/// Only the seam is rewritten: the end marker of `a` is dropped, and the literals at the start of `b`
+/// are merged with the literals at the end of `a`. All other instructions are copied unchanged,
+/// as matches in `b` never refer back before the start of `b`.
+///
+/// #### Errors
+///
+/// This function returns an error if `a` or `b` do not contain valid compressed data,
+/// each consisting of a single block.
+///
+/// # Examples
+///
+/// ```
+/// let a = lzo1x::compress(b"hello hello ", lzo1x::CompressLevel::default());
+/// let b = lzo1x::compress(b"world world", lzo1x::CompressLevel::default());
+///
+/// let joined = lzo1x::concat(&a, &b).unwrap();
+///
+/// let mut decompressed = [0; 23];
+/// lzo1x::decompress(&joined, &mut decompressed).unwrap();
+///
+/// assert_eq!(&decompressed, b"hello hello world world");
+/// ```
+pub fn concat(a: &[u8], b: &[u8]) -> Result<Vec<u8>, DecompressError> {
+    let block_a = scan(a)?;
+    let block_b = scan(b)?;
+
+    let dst = match block_a.last {
+        // Nothing to merge if either block is empty.
+        _ if block_b.len == 0 => a.to_vec(),
+        Last::None => b.to_vec(),
+        last => join(a, &block_a, last, b)?,
+    };
+
+    // Ensure the seam was rewritten correctly.
+    if scan(&dst)?.len != block_a.len + block_b.len {
+        return Err(DecompressError::InvalidInput);
+    }
+
+    Ok(dst)
+}
+
+/// Summary of a single valid block.
+struct Block {
+    /// Length of the decompressed data.
+    len: usize,
+    /// Position of the end marker.
+    end_pos: usize,
+    last: Last,
+}
+
+/// Last instruction before the end marker.
+#[derive(Clone, Copy)]
+enum Last {
+    /// The block is empty.
+    None,
+    /// A literal run at `pos`, whose literals start at `lit_pos`.
+    Literal { pos: usize, lit_pos: usize },
+    /// A match, whose second to last byte at `litp` holds the length of its trailing literals.
+    Match { litp: usize },
+}
+
+/// Validate the single block given in `src`, without decompressing it.
+fn scan(src: &[u8]) -> Result<Block, DecompressError> {
+    let mut len = 0;
+    let mut last = Last::None;
+
+    let mut src_pos = 0;
+    let mut state = State::Start;
+
+    loop {
+        let insn_pos = src_pos;
+        let insn =
+            decode_insn(src, &mut src_pos, state).map_err(|_| DecompressError::InvalidInput)?;
+
+        let lit = match insn {
+            Insn::Literal { len } => {
+                last = Last::Literal {
+                    pos: insn_pos,
+                    lit_pos: src_pos,
+                };
+
+                len
+            }
+            Insn::Match {
+                len: match_len,
+                dist,
+                lit,
+            } => {
+                if dist > len {
+                    return Err(DecompressError::InvalidInput);
+                }
+
+                len += match_len;
+                last = Last::Match { litp: src_pos - 2 };
+
+                lit
+            }
+            Insn::End => {
+                if src_pos != src.len() {
+                    return Err(DecompressError::InvalidInput);
+                }
+
+                return Ok(Block {
+                    len,
+                    end_pos: insn_pos,
+                    last,
+                });
+            }
+        };
+
+        if src_pos + lit > src.len() {
+            return Err(DecompressError::InvalidInput);
+        }
+
+        len += lit;
+        src_pos += lit;
+        state = state.next(&insn);
+    }
+}
+
+/// Join the non-empty blocks `a` and `b`, where `last` is the last instruction of `a`.
+fn join(a: &[u8], block_a: &Block, last: Last, b: &[u8]) -> Result<Vec<u8>, DecompressError> {
+    // The literals at the end of `a`, and the position of the instruction which holds their length.
+    let (header_pos, lit_pos) = match last {
+        Last::None => unreachable!(),
+        Last::Literal { pos, lit_pos } => (pos, lit_pos),
+        Last::Match { litp } => (litp + 2, litp + 2),
+    };
+
+    let mut lits = a[lit_pos..block_a.end_pos].to_vec();
+
+    // The first instruction of a non-empty block is a literal run.
+    let mut b_pos = 0;
+    let first =
+        decode_insn(b, &mut b_pos, State::Start).map_err(|_| DecompressError::InvalidInput)?;
+
+    let Insn::Literal { len } = first else {
+        return Err(DecompressError::InvalidInput);
+    };
+
+    lits.extend_from_slice(&b[b_pos..b_pos + len]);
+    b_pos += len;
+
+    // Decompressed data at the start of `b`, needed to turn its first matches into literals.
+    let mut hist = b[b_pos - len..b_pos].to_vec();
+    let mut b_state = State::Start.next(&first);
+
+    loop {
+        // An instruction 0-15 following the merged literals is interpreted as in state B or C,
+        // depending on their length. Instructions which would change their meaning are turned into literals.
+        let state = if lits.len() < 4 { State::B } else { State::C };
+
+        if state == b_state || b[b_pos] >= 16 {
+            break;
+        }
+
+        let insn =
+            decode_insn(b, &mut b_pos, b_state).map_err(|_| DecompressError::InvalidInput)?;
+
+        let lit = match insn {
+            Insn::Literal { len } => len,
+            Insn::Match { len, dist, lit } => {
+                let hist_len = hist.len();
+
+                hist.resize(hist_len + len, 0);
+                copy_match(&mut hist, hist_len, dist, len);
+                lits.extend_from_slice(&hist[hist_len..]);
+
+                lit
+            }
+            Insn::End => unreachable!(),
+        };
+
+        hist.extend_from_slice(&b[b_pos..b_pos + lit]);
+        lits.extend_from_slice(&b[b_pos..b_pos + lit]);
+
+        b_pos += lit;
+        b_state = b_state.next(&insn);
+    }
+
+    let mut dst = Vec::with_capacity(a.len() + b.len());
+    dst.extend_from_slice(&a[..header_pos]);
+
+    let t = lits.len();
+
+    match last {
+        Last::Literal { pos: 0, .. } if a[0] > 17 && t <= 238 => {
+            dst.push((17 + t) as u8);
+        }
+        Last::Match { litp } if t <= 3 => {
+            dst[litp] = (dst[litp] & !3) | t as u8;
+        }
+        _ => {
+            if let Last::Match { litp } = last {
+                dst[litp] &= !3;
+            }
+
+            let dst_pos = dst.len();
+            dst.resize(dst_pos + t / 255 + 3, 0);
+
+            let dst_pos = store_run_len(&mut dst, dst_pos, t);
+            dst.truncate(dst_pos);
+        }
+    }
+
+    dst.extend_from_slice(&lits);
+    dst.extend_from_slice(&b[b_pos..]);
+
+    Ok(dst)
+}
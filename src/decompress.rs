@@ -1,6 +1,21 @@
 // Decompression is based on the following description: https://docs.kernel.org/staging/lzo.html.
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
 use crate::DecompressError;
+#[cfg(feature = "alloc")]
+use crate::{TryDecompressError, try_alloc::try_resize};
+
+/// The `?` operator, which can not be used in const functions yet.
+macro_rules! const_try {
+    ($expr:expr) => {
+        match $expr {
+            Ok(value) => value,
+            Err(err) => return Err(err),
+        }
+    };
+}
 
 /// Decompress the given `src` into the given `dst`.
 ///
@@ -21,300 +36,470 @@ use crate::DecompressError;
 /// assert_eq!(decompressed, data);
 /// ```
 pub fn decompress(src: &[u8], dst: &mut [u8]) -> Result<(), DecompressError> {
-    enum State {
-        A,
-        B,
-        C,
-    }
+    let dst_len = decompress_into(src, dst)?;
 
-    let mut src_pos = 0;
-    let mut dst_pos = 0;
-
-    if src_pos + 1 > src.len() {
-        return Err(DecompressError::InvalidInput);
+    // Ensure the destination buffer was completely filled.
+    if dst_len != dst.len() {
+        return Err(DecompressError::OutputLength);
     }
 
-    let insn = src[src_pos];
+    Ok(())
+}
 
-    let mut state = if insn <= 17 {
-        State::A
-    } else {
-        src_pos += 1;
+/// Decompress the given `src` into the start of the given `dst`, returning the length of the decompressed data.
+///
+/// Unlike [`decompress`], the given `dst` only needs to be large enough to hold the decompressed data.
+///
+/// #### Errors
+///
+/// This function returns an error if the given `src` does not contain valid compressed data,
+/// or if the given `dst` is too small to hold the decompressed data.
+///
+/// # Examples
+///
+/// ```
+/// let data = &[0xaa; 100];
+/// let compressed = lzo1x::compress(data, lzo1x::CompressLevel::default());
+///
+/// let mut decompressed = vec![0; 1000];
+/// let len = lzo1x::decompress_into(&compressed, &mut decompressed).unwrap();
+///
+/// assert_eq!(&decompressed[..len], data);
+/// ```
+pub fn decompress_into(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let mut src_pos = 0;
+    let mut dst_pos = 0;
+    let mut state = State::Start;
 
-        let lit_len = (insn as usize) - 17;
+    loop {
+        let insn = match decode_insn(src, &mut src_pos, state) {
+            Ok(insn) => insn,
+            Err(Truncated) => return Err(DecompressError::InvalidInput),
+        };
 
-        // Copy literal with length in the range 0-238.
+        match insn {
+            Insn::Literal { len } => {
+                copy_literal(src, &mut src_pos, dst, &mut dst_pos, len)?;
+            }
+            Insn::Match { len, dist, lit } => {
+                if dist > dst_pos {
+                    return Err(DecompressError::InvalidInput);
+                }
 
-        if src_pos + lit_len > src.len() {
-            return Err(DecompressError::InvalidInput);
-        }
+                if dst_pos + len > dst.len() {
+                    return Err(DecompressError::OutputLength);
+                }
 
-        if dst_pos + lit_len > dst.len() {
-            return Err(DecompressError::OutputLength);
-        }
+                copy_match(dst, dst_pos, dist, len);
+                dst_pos += len;
 
-        for _ in 0..lit_len {
-            dst[dst_pos] = src[src_pos];
-            src_pos += 1;
-            dst_pos += 1;
+                copy_literal(src, &mut src_pos, dst, &mut dst_pos, lit)?;
+            }
+            Insn::End => break,
         }
 
-        if insn <= 20 { State::B } else { State::C }
-    };
+        state = state.next(&insn);
+    }
 
-    loop {
-        // Decode instruction.
+    // Ensure the source buffer was completely consumed.
+    if src_pos != src.len() {
+        return Err(DecompressError::InvalidInput);
+    }
 
-        if src_pos + 1 > src.len() {
-            return Err(DecompressError::InvalidInput);
-        }
+    Ok(dst_pos)
+}
 
-        let insn = src[src_pos];
-        src_pos += 1;
+/// Decompress the given `src` into a new vector of `dst_len` bytes, returning an error if memory allocation fails.
+///
+/// #### Errors
+///
+/// This function returns an error if the given `src` does not contain valid compressed data,
+/// if `dst_len` does not exactly match the length of the decompressed data,
+/// or if memory allocation fails.
+///
+/// # Examples
+///
+/// ```
+/// let data = &[0xaa; 100];
+/// let compressed = lzo1x::compress(data, lzo1x::CompressLevel::default());
+///
+/// let decompressed = lzo1x::try_decompress_to_vec(&compressed, data.len()).unwrap();
+///
+/// assert_eq!(decompressed, data);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn try_decompress_to_vec(src: &[u8], dst_len: usize) -> Result<Vec<u8>, TryDecompressError> {
+    let mut dst = Vec::new();
+    try_resize(&mut dst, dst_len, 0).map_err(|_| TryDecompressError::OutOfMemory)?;
 
-        let (match_len, match_dist, lit_insn) = match insn {
-            0..=15 => {
-                let (match_len, match_dist_offset) = match state {
-                    State::A => {
-                        let lit_len = if insn == 0 {
-                            let start_src_pos = src_pos;
+    decompress(src, &mut dst)?;
 
-                            loop {
-                                if src_pos + 1 > src.len() {
-                                    return Err(DecompressError::InvalidInput);
-                                }
+    Ok(dst)
+}
 
-                                if src[src_pos] != 0 {
-                                    break;
-                                }
+/// Decompress the given `src` into an array of length `N` in a const context.
+///
+/// This is intended for small tables which are decompressed at compile time,
+/// so that only the compressed data has to be stored in the source code.
+///
+/// #### Panics
+///
+/// Panics if the given `src` does not contain valid compressed data,
+/// or if `N` does not exactly match the length of the decompressed data.
+/// When evaluated at compile time, this results in a compilation error.
+///
+/// # Examples
+///
+/// ```
+/// const DATA: [u8; 100] = lzo1x::decompress_const(&[18, 0xaa, 32, 66, 0, 0, 17, 0, 0]);
+///
+/// assert_eq!(DATA, [0xaa; 100]);
+/// ```
+pub const fn decompress_const<const N: usize>(src: &[u8]) -> [u8; N] {
+    let mut dst = [0; N];
 
-                                src_pos += 1;
-                            }
+    let mut src_pos = 0;
+    let mut dst_pos = 0;
+    let mut state = State::Start;
 
-                            let count = src_pos - start_src_pos;
+    loop {
+        let insn = match decode_insn(src, &mut src_pos, state) {
+            Ok(insn) => insn,
+            Err(Truncated) => panic!("invalid input"),
+        };
 
-                            let lit_len = (count * 255) + (src[src_pos] as usize) + 18;
-                            src_pos += 1;
+        let lit_len = match insn {
+            Insn::Literal { len } => len,
+            Insn::Match { len, dist, lit } => {
+                if dist > dst_pos {
+                    panic!("invalid input");
+                }
 
-                            lit_len
-                        } else {
-                            (insn as usize) + 3
-                        };
+                if dst_pos + len > N {
+                    panic!("output length does not match");
+                }
 
-                        // Copy literal with length 4 or greater.
+                // Copy byte by byte, as the match may overlap.
+                let mut i = 0;
 
-                        if src_pos + lit_len > src.len() {
-                            return Err(DecompressError::InvalidInput);
-                        }
+                while i < len {
+                    dst[dst_pos + i] = dst[dst_pos + i - dist];
+                    i += 1;
+                }
 
-                        if dst_pos + lit_len > dst.len() {
-                            return Err(DecompressError::OutputLength);
-                        }
+                dst_pos += len;
 
-                        dst[dst_pos..dst_pos + lit_len]
-                            .copy_from_slice(&src[src_pos..src_pos + lit_len]);
-                        src_pos += lit_len;
-                        dst_pos += lit_len;
+                lit
+            }
+            Insn::End => break,
+        };
 
-                        state = State::C;
+        if src_pos + lit_len > src.len() {
+            panic!("invalid input");
+        }
 
-                        continue;
-                    }
-                    State::B => (2, 1),
-                    State::C => (3, 2049),
-                };
+        if dst_pos + lit_len > N {
+            panic!("output length does not match");
+        }
 
-                if src_pos + 1 > src.len() {
-                    return Err(DecompressError::InvalidInput);
-                }
+        let mut i = 0;
 
-                let match_dist =
-                    ((src[src_pos] as usize) << 2) + ((insn >> 2) as usize) + match_dist_offset;
-                src_pos += 1;
+        while i < lit_len {
+            dst[dst_pos + i] = src[src_pos + i];
+            i += 1;
+        }
 
-                (match_len, match_dist, insn)
-            }
-            16..=31 => {
-                let match_len = if (insn & 0b00000111) == 0 {
-                    let src_pos_start = src_pos;
+        src_pos += lit_len;
+        dst_pos += lit_len;
 
-                    loop {
-                        if src_pos + 1 > src.len() {
-                            return Err(DecompressError::InvalidInput);
-                        }
+        state = state.next(&insn);
+    }
 
-                        if src[src_pos] != 0 {
-                            break;
-                        }
+    if src_pos != src.len() {
+        panic!("invalid input");
+    }
 
-                        src_pos += 1;
-                    }
+    if dst_pos != N {
+        panic!("output length does not match");
+    }
 
-                    let count = src_pos - src_pos_start;
+    dst
+}
 
-                    let match_len = (count * 255) + (src[src_pos] as usize) + 9;
-                    src_pos += 1;
+/// Decoder state, which determines the meaning of instructions 0-15.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum State {
+    /// At the start of a block, where the first instruction can also be a literal run of 1-238 bytes.
+    Start,
+    /// After a match without trailing literals.
+    A,
+    /// After a literal run of 1-3 bytes.
+    B,
+    /// After a literal run of 4 or more bytes.
+    C,
+}
 
-                    match_len
+impl State {
+    /// Returns the state after the given `insn`.
+    pub(crate) const fn next(self, insn: &Insn) -> Self {
+        match *insn {
+            Insn::Literal { len } => {
+                if len < 4 {
+                    State::B
                 } else {
-                    ((insn & 0b00000111) as usize) + 2
-                };
-
-                if src_pos + 2 > src.len() {
-                    return Err(DecompressError::InvalidInput);
+                    State::C
                 }
-
-                let match_dist = ((((insn & 0b00001000) >> 3) as usize) << 14)
-                    + ((src[src_pos + 1] as usize) << 6)
-                    + ((src[src_pos] >> 2) as usize)
-                    + 16384;
-                let sub_insn = src[src_pos];
-                src_pos += 2;
-
-                if match_dist == 16384 {
-                    break;
+            }
+            Insn::Match { lit, .. } => {
+                if lit == 0 {
+                    State::A
+                } else {
+                    State::B
                 }
-
-                (match_len, match_dist, sub_insn)
             }
-            32..=63 => {
-                let match_len = if (insn & 0b00011111) == 0 {
-                    let src_pos_start = src_pos;
+            Insn::End => State::Start,
+        }
+    }
+}
 
-                    loop {
-                        if src_pos + 1 > src.len() {
-                            return Err(DecompressError::InvalidInput);
-                        }
+/// A single decoded instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Insn {
+    /// Copy `len` literal bytes, which follow the instruction.
+    Literal { len: usize },
+    /// Copy `len` bytes from `dist` bytes back, followed by `lit` literal bytes.
+    Match { len: usize, dist: usize, lit: usize },
+    /// End of the block.
+    End,
+}
 
-                        if src[src_pos] != 0 {
-                            break;
-                        }
+/// The source ended in the middle of an instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct Truncated;
 
-                        src_pos += 1;
-                    }
+/// Decode the instruction at `src_pos` in the given `src`.
+///
+/// On success `src_pos` is advanced past the instruction, but not past any literal bytes it is followed by.
+/// On error `src_pos` is left unchanged.
+#[inline(always)]
+pub(crate) const fn decode_insn(
+    src: &[u8],
+    src_pos: &mut usize,
+    state: State,
+) -> Result<Insn, Truncated> {
+    let mut pos = *src_pos;
+
+    if pos + 1 > src.len() {
+        return Err(Truncated);
+    }
 
-                    let count = src_pos - src_pos_start;
+    let insn = src[pos];
+    pos += 1;
 
-                    let match_len = (count * 255) + (src[src_pos] as usize) + 33;
-                    src_pos += 1;
+    let state = match state {
+        State::Start => {
+            if insn > 17 {
+                *src_pos = pos;
 
-                    match_len
-                } else {
-                    ((insn & 0b00011111) as usize) + 2
-                };
+                return Ok(Insn::Literal {
+                    len: (insn as usize) - 17,
+                });
+            }
 
-                if src_pos + 2 > src.len() {
-                    return Err(DecompressError::InvalidInput);
-                }
+            State::A
+        }
+        state => state,
+    };
 
-                let match_dist =
-                    ((src[src_pos + 1] as usize) << 6) + ((src[src_pos] >> 2) as usize) + 1;
-                let sub_insn = src[src_pos];
-                src_pos += 2;
+    let (match_len, match_dist, lit_insn) = match insn {
+        0..=15 => {
+            let (match_len, match_dist_offset) = match state {
+                State::Start | State::A => {
+                    let lit_len = if insn == 0 {
+                        (const_try!(read_len(src, &mut pos)) * 255) + (src[pos - 1] as usize) + 18
+                    } else {
+                        (insn as usize) + 3
+                    };
 
-                (match_len, match_dist, sub_insn)
-            }
-            64..=255 => {
-                let match_len = match insn {
-                    0..=63 => unreachable!(),
-                    64..=127 => {
-                        if insn & 0b00100000 != 0 {
-                            4
-                        } else {
-                            3
-                        }
-                    }
-                    128..=255 => (((insn & 0b01100000) >> 5) as usize) + 5,
-                };
+                    *src_pos = pos;
 
-                if src_pos + 1 > src.len() {
-                    return Err(DecompressError::InvalidInput);
+                    return Ok(Insn::Literal { len: lit_len });
                 }
+                State::B => (2, 1),
+                State::C => (3, 2049),
+            };
 
-                let match_dist =
-                    ((src[src_pos] as usize) << 3) + (((insn & 0b00011100) >> 2) as usize) + 1;
-                src_pos += 1;
-
-                (match_len, match_dist, insn)
+            if pos + 1 > src.len() {
+                return Err(Truncated);
             }
-        };
-
-        // Copy match.
 
-        if match_dist > dst_pos {
-            return Err(DecompressError::InvalidInput);
-        }
+            let match_dist =
+                ((src[pos] as usize) << 2) + ((insn >> 2) as usize) + match_dist_offset;
+            pos += 1;
 
-        if dst_pos + match_len > dst.len() {
-            return Err(DecompressError::OutputLength);
+            (match_len, match_dist, insn)
         }
+        16..=31 => {
+            let match_len = if (insn & 0b00000111) == 0 {
+                (const_try!(read_len(src, &mut pos)) * 255) + (src[pos - 1] as usize) + 9
+            } else {
+                ((insn & 0b00000111) as usize) + 2
+            };
+
+            if pos + 2 > src.len() {
+                return Err(Truncated);
+            }
 
-        let match_pos = dst_pos - match_dist;
+            let match_dist = ((((insn & 0b00001000) >> 3) as usize) << 14)
+                + ((src[pos + 1] as usize) << 6)
+                + ((src[pos] >> 2) as usize)
+                + 16384;
+            let sub_insn = src[pos];
+            pos += 2;
 
-        if match_dist >= match_len {
-            // Match does not overlap.
+            if match_dist == 16384 {
+                *src_pos = pos;
 
-            let (a, b) = dst.split_at_mut(dst_pos);
-            b[..match_len].copy_from_slice(&a[match_pos..match_pos + match_len]);
-        } else {
-            // Match overlaps.
+                return Ok(Insn::End);
+            }
 
-            let (a, b) = dst.split_at_mut(dst_pos);
-            b[..match_dist].copy_from_slice(&a[match_pos..match_pos + match_dist]);
+            (match_len, match_dist, sub_insn)
+        }
+        32..=63 => {
+            let match_len = if (insn & 0b00011111) == 0 {
+                (const_try!(read_len(src, &mut pos)) * 255) + (src[pos - 1] as usize) + 33
+            } else {
+                ((insn & 0b00011111) as usize) + 2
+            };
+
+            if pos + 2 > src.len() {
+                return Err(Truncated);
+            }
 
-            let mut n = match_dist;
+            let match_dist = ((src[pos + 1] as usize) << 6) + ((src[pos] >> 2) as usize) + 1;
+            let sub_insn = src[pos];
+            pos += 2;
 
-            while n * 2 < match_len {
-                let (a, b) = b.split_at_mut(n);
-                b[..n].copy_from_slice(a);
+            (match_len, match_dist, sub_insn)
+        }
+        64..=255 => {
+            let match_len = match insn {
+                0..=63 => unreachable!(),
+                64..=127 => {
+                    if insn & 0b00100000 != 0 {
+                        4
+                    } else {
+                        3
+                    }
+                }
+                128..=255 => (((insn & 0b01100000) >> 5) as usize) + 5,
+            };
 
-                n *= 2;
+            if pos + 1 > src.len() {
+                return Err(Truncated);
             }
 
-            let (a, b) = b.split_at_mut(n);
-            b[..match_len - n].copy_from_slice(&a[..match_len - n]);
-        }
+            let match_dist = ((src[pos] as usize) << 3) + (((insn & 0b00011100) >> 2) as usize) + 1;
+            pos += 1;
 
-        dst_pos += match_len;
+            (match_len, match_dist, insn)
+        }
+    };
 
-        let lit_len = (lit_insn & 0b00000011) as usize;
+    *src_pos = pos;
 
-        state = if lit_len == 0 {
-            State::A
-        } else {
-            // Copy literal with length in the range 1-3.
+    Ok(Insn::Match {
+        len: match_len,
+        dist: match_dist,
+        lit: (lit_insn & 0b00000011) as usize,
+    })
+}
 
-            if src_pos + lit_len > src.len() {
-                return Err(DecompressError::InvalidInput);
-            }
+/// Read the zero bytes of an extended length, up to and including the first non-zero byte.
+///
+/// Returns the number of zero bytes.
+#[inline(always)]
+const fn read_len(src: &[u8], src_pos: &mut usize) -> Result<usize, Truncated> {
+    let start_src_pos = *src_pos;
 
-            if dst_pos + lit_len > dst.len() {
-                return Err(DecompressError::OutputLength);
-            }
+    loop {
+        if *src_pos + 1 > src.len() {
+            return Err(Truncated);
+        }
 
-            let src = &src[src_pos..src_pos + lit_len];
-            let dst = &mut dst[dst_pos..dst_pos + lit_len];
+        if src[*src_pos] != 0 {
+            break;
+        }
 
-            for (src, dst) in src.iter().zip(dst).take(3) {
-                *dst = *src;
-            }
+        *src_pos += 1;
+    }
 
-            src_pos += lit_len;
-            dst_pos += lit_len;
+    *src_pos += 1;
 
-            State::B
-        };
-    }
+    Ok(*src_pos - 1 - start_src_pos)
+}
 
-    // Ensure the source buffer was completely consumed.
-    if src_pos != src.len() {
+#[inline(always)]
+fn copy_literal(
+    src: &[u8],
+    src_pos: &mut usize,
+    dst: &mut [u8],
+    dst_pos: &mut usize,
+    len: usize,
+) -> Result<(), DecompressError> {
+    if *src_pos + len > src.len() {
         return Err(DecompressError::InvalidInput);
     }
 
-    // Ensure the destination buffer was completely filled.
-    if dst_pos != dst.len() {
+    if *dst_pos + len > dst.len() {
         return Err(DecompressError::OutputLength);
     }
 
+    let src = &src[*src_pos..*src_pos + len];
+    let dst = &mut dst[*dst_pos..*dst_pos + len];
+
+    if len <= 3 {
+        for (src, dst) in src.iter().zip(dst).take(3) {
+            *dst = *src;
+        }
+    } else {
+        dst.copy_from_slice(src);
+    }
+
+    *src_pos += len;
+    *dst_pos += len;
+
     Ok(())
 }
+
+/// Copy `len` bytes from `dist` bytes before `dst_pos` to `dst_pos`, where the source and destination may overlap.
+///
+/// The caller must ensure that `dist` is at most `dst_pos` and that `dst` is large enough.
+#[inline(always)]
+pub(crate) fn copy_match(dst: &mut [u8], dst_pos: usize, dist: usize, len: usize) {
+    let match_pos = dst_pos - dist;
+
+    if dist >= len {
+        // Match does not overlap.
+
+        let (a, b) = dst.split_at_mut(dst_pos);
+        b[..len].copy_from_slice(&a[match_pos..match_pos + len]);
+    } else {
+        // Match overlaps.
+
+        let (a, b) = dst.split_at_mut(dst_pos);
+        b[..dist].copy_from_slice(&a[match_pos..match_pos + dist]);
+
+        let mut n = dist;
+
+        while n * 2 < len {
+            let (a, b) = b.split_at_mut(n);
+            b[..n].copy_from_slice(a);
+
+            n *= 2;
+        }
+
+        let (a, b) = b.split_at_mut(n);
+        b[..len - n].copy_from_slice(&a[..len - n]);
+    }
+}
@@ -0,0 +1,89 @@
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+use crate::{DecompressError, decompress};
+
+/// Compressed data embedded in a binary, which is decompressed at runtime.
+///
+/// Instances are usually created at compile time with the `include_lzo!` macro of the `lzo1x-macros` crate,
+/// or with the [`build`](crate::build) helpers in a build script.
+///
+/// # Examples
+///
+/// ```
+/// static DATA: lzo1x::Embedded = lzo1x::Embedded::new(&[18, 0xaa, 17, 0, 0], 1);
+///
+/// assert_eq!(DATA.get(), [0xaa]);
+/// ```
+pub struct Embedded {
+    compressed: &'static [u8],
+    len: usize,
+    #[cfg(feature = "std")]
+    decompressed: OnceLock<Vec<u8>>,
+}
+
+impl Embedded {
+    /// Create a new `Embedded` instance from the given `compressed` data,
+    /// and the length of the decompressed data in `len`.
+    pub const fn new(compressed: &'static [u8], len: usize) -> Self {
+        Self {
+            compressed,
+            len,
+            #[cfg(feature = "std")]
+            decompressed: OnceLock::new(),
+        }
+    }
+
+    /// Returns the compressed data.
+    pub const fn compressed(&self) -> &'static [u8] {
+        self.compressed
+    }
+
+    /// Returns the length of the decompressed data.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the decompressed data is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Decompress the data into the given `dst`, without allocating.
+    ///
+    /// #### Errors
+    ///
+    /// This function returns an error if the compressed data is invalid,
+    /// or if the given `dst` does not exactly match the length of the decompressed data.
+    pub fn decompress(&self, dst: &mut [u8]) -> Result<(), DecompressError> {
+        decompress(self.compressed, dst)
+    }
+
+    /// Decompress the data into a new vector.
+    ///
+    /// #### Panics
+    ///
+    /// Panics if the compressed data is invalid.
+    #[cfg(feature = "alloc")]
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut dst = vec![0; self.len];
+
+        self.decompress(&mut dst)
+            .expect("invalid embedded compressed data");
+
+        dst
+    }
+
+    /// Returns the decompressed data, which is decompressed on the first call.
+    ///
+    /// #### Panics
+    ///
+    /// Panics if the compressed data is invalid.
+    #[cfg(feature = "std")]
+    pub fn get(&self) -> &[u8] {
+        self.decompressed.get_or_init(|| self.to_vec())
+    }
+}
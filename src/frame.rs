@@ -0,0 +1,759 @@
+// A frame consists of the following parts, where all integers are stored in little-endian byte order:
+//
+// - Header: the magic number "LZ1X" and the block size as u32.
+// - Blocks: each block starts with the stored length as u32, where the highest bit is set if the block
+//   is stored uncompressed, followed by the decompressed length as u32, the Adler-32 checksum of the
+//   decompressed data as u32, and the stored data. Every block is compressed independently.
+// - End mark: a stored length of 0 as u32.
+// - Seek table: for every block, the stored length and the decompressed length as u32.
+// - Footer: the number of blocks as u32, the content size as u64, and the magic number "LZ1S".
+
+use core::fmt::{self, Display, Formatter};
+
+use alloc::vec::Vec;
+
+use crate::{CompressLevel, checksum::adler32, compress, decompress};
+
+const MAGIC: [u8; 4] = *b"LZ1X";
+const FOOTER_MAGIC: [u8; 4] = *b"LZ1S";
+
+pub(crate) const HEADER_LEN: usize = 8;
+pub(crate) const BLOCK_HEADER_LEN: usize = 12;
+pub(crate) const FOOTER_LEN: usize = 16;
+
+const STORED_FLAG: u32 = 1 << 31;
+
+// Maximum ratio of the decompressed length to the stored length of a compressed block,
+// as a single byte of compressed data extends the length of a match by at most 255 bytes.
+const MAX_RATIO: usize = 255;
+
+/// Default size of the blocks in a frame. (256 KiB)
+#[cfg(feature = "std")]
+pub(crate) const DEFAULT_BLOCK_SIZE: usize = 256 * 1024;
+
+/// Maximum size of the blocks in a frame. (1 GiB)
+pub(crate) const MAX_BLOCK_SIZE: usize = 1 << 30;
+
+/// Compress the given `src` into a frame, with the given compression `level` and `block_size`.
+///
+/// A frame consists of independently compressed blocks with checksums,
+/// and contains a seek table which allows decompressing arbitrary ranges of the data with a [`FrameReader`].
+/// Blocks which do not compress are stored uncompressed.
+///
+/// The given `block_size` is clamped to the range 1 B - 1 GiB.
+///
+/// # Examples
+///
+/// ```
+/// let data = &[0xaa; 100];
+/// let frame = lzo1x::compress_frame(data, lzo1x::CompressLevel::default(), 64);
+///
+/// assert_eq!(lzo1x::decompress_frame(&frame).unwrap(), data);
+/// ```
+pub fn compress_frame(src: &[u8], level: CompressLevel, block_size: usize) -> Vec<u8> {
+    let block_size = clamp_block_size(block_size);
+
+    let mut dst = Vec::new();
+    let mut blocks = Vec::new();
+
+    write_header(&mut dst, block_size);
+
+    for block in src.chunks(block_size) {
+        let stored_len = encode_block(block, level, &mut dst);
+        blocks.push((stored_len, block.len() as u32));
+    }
+
+    write_trailer(&mut dst, &blocks, src.len() as u64);
+
+    dst
+}
+
+/// Decompress the given frame in `src`.
+///
+/// #### Errors
+///
+/// This function returns an error if the given `src` does not contain a valid frame,
+/// or if the checksum of any of the blocks does not match.
+///
+/// # Examples
+///
+/// ```
+/// let data = &[0xaa; 100];
+/// let frame = lzo1x::compress_frame(data, lzo1x::CompressLevel::default(), 64);
+///
+/// assert_eq!(lzo1x::decompress_frame(&frame).unwrap(), data);
+/// ```
+pub fn decompress_frame(src: &[u8]) -> Result<Vec<u8>, FrameError> {
+    let blocks = read_blocks(src)?;
+
+    let mut dst = Vec::new();
+
+    for (header, data) in blocks {
+        let dst_pos = dst.len();
+        dst.resize(dst_pos + header.len, 0);
+        header.decode(data, &mut dst[dst_pos..])?;
+    }
+
+    Ok(dst)
+}
+
+/// Read the headers and data of all blocks of the frame in `src`, and validate the seek table and footer.
+///
+/// The data of the blocks is not decoded, so the checksums are not validated.
+pub(crate) fn read_blocks(src: &[u8]) -> Result<Vec<(BlockHeader, &[u8])>, FrameError> {
+    let block_size = read_header(src)?;
+
+    let mut blocks = Vec::new();
+    let mut src_pos = HEADER_LEN;
+
+    loop {
+        // The end mark is always followed by the footer, so a full block header can be read.
+        let header = src
+            .get(src_pos..src_pos + BLOCK_HEADER_LEN)
+            .ok_or(FrameError::InvalidInput)?;
+
+        let Some(header) = BlockHeader::parse(header.try_into().unwrap()) else {
+            src_pos += 4;
+
+            break;
+        };
+
+        src_pos += BLOCK_HEADER_LEN;
+
+        header.check(block_size)?;
+
+        let data = src
+            .get(src_pos..src_pos + header.stored_len)
+            .ok_or(FrameError::InvalidInput)?;
+        src_pos += header.stored_len;
+
+        blocks.push((header, data));
+    }
+
+    let entries: Vec<_> = blocks
+        .iter()
+        .map(|(header, _)| (header.raw_stored_len(), header.len as u32))
+        .collect();
+
+    check_trailer(&src[src_pos..], &entries)?;
+
+    Ok(blocks)
+}
+
+/// Check that the given `trailer`, which follows the end mark, contains the seek table for the given `blocks`.
+pub(crate) fn check_trailer(trailer: &[u8], blocks: &[(u32, u32)]) -> Result<(), FrameError> {
+    if trailer.len() != (blocks.len() * 8) + FOOTER_LEN {
+        return Err(FrameError::InvalidInput);
+    }
+
+    let (table, footer) = trailer.split_at(trailer.len() - FOOTER_LEN);
+    let footer = Footer::parse(footer.try_into().unwrap())?;
+
+    let mut content_size = 0;
+
+    for (&(stored_len, len), entry) in blocks.iter().zip(table.chunks_exact(8)) {
+        if entry[0..4] != stored_len.to_le_bytes() || entry[4..8] != len.to_le_bytes() {
+            return Err(FrameError::InvalidInput);
+        }
+
+        content_size += len as u64;
+    }
+
+    if footer.block_count as usize != blocks.len() || footer.content_size != content_size {
+        return Err(FrameError::InvalidInput);
+    }
+
+    Ok(())
+}
+
+pub(crate) fn clamp_block_size(block_size: usize) -> usize {
+    block_size.clamp(1, MAX_BLOCK_SIZE)
+}
+
+pub(crate) fn write_header(dst: &mut Vec<u8>, block_size: usize) {
+    dst.extend_from_slice(&MAGIC);
+    dst.extend_from_slice(&(block_size as u32).to_le_bytes());
+}
+
+/// Parse the frame header at the start of `src`, returning the block size.
+pub(crate) fn read_header(src: &[u8]) -> Result<usize, FrameError> {
+    if src.len() < HEADER_LEN || src[..4] != MAGIC {
+        return Err(FrameError::InvalidInput);
+    }
+
+    let block_size = u32::from_le_bytes(src[4..8].try_into().unwrap()) as usize;
+
+    if block_size == 0 || block_size > MAX_BLOCK_SIZE {
+        return Err(FrameError::InvalidInput);
+    }
+
+    Ok(block_size)
+}
+
+/// Compress the given `src` into a block, appending it to `dst`.
+///
+/// Returns the stored length of the block, including the stored flag.
+pub(crate) fn encode_block(src: &[u8], level: CompressLevel, dst: &mut Vec<u8>) -> u32 {
+    let compressed = compress(src, level);
+
+    let (stored_len, data) = if compressed.len() < src.len() {
+        (compressed.len() as u32, compressed.as_slice())
+    } else {
+        (src.len() as u32 | STORED_FLAG, src)
+    };
+
+    dst.extend_from_slice(&stored_len.to_le_bytes());
+    dst.extend_from_slice(&(src.len() as u32).to_le_bytes());
+    dst.extend_from_slice(&adler32(1, src).to_le_bytes());
+    dst.extend_from_slice(data);
+
+    stored_len
+}
+
+/// Append the end mark, the seek table for the given `blocks` and the footer to `dst`.
+pub(crate) fn write_trailer(dst: &mut Vec<u8>, blocks: &[(u32, u32)], content_size: u64) {
+    dst.extend_from_slice(&0u32.to_le_bytes());
+
+    for &(stored_len, len) in blocks {
+        dst.extend_from_slice(&stored_len.to_le_bytes());
+        dst.extend_from_slice(&len.to_le_bytes());
+    }
+
+    dst.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+    dst.extend_from_slice(&content_size.to_le_bytes());
+    dst.extend_from_slice(&FOOTER_MAGIC);
+}
+
+/// Error that occured during decompression of a frame.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FrameError {
+    /// The input source does not contain a valid frame.
+    InvalidInput,
+    /// The checksum of the decompressed data of a block does not match.
+    Checksum,
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::InvalidInput => f.write_str("invalid frame"),
+            Self::Checksum => f.write_str("checksum does not match"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FrameError {}
+
+pub(crate) struct BlockHeader {
+    pub stored_len: usize,
+    pub stored: bool,
+    pub len: usize,
+    pub checksum: u32,
+}
+
+impl BlockHeader {
+    /// Parse the given block header, returning `None` for the end mark.
+    ///
+    /// Only the first 4 bytes are read in case of the end mark.
+    pub fn parse(bytes: &[u8; BLOCK_HEADER_LEN]) -> Option<Self> {
+        let stored_len = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+
+        if stored_len == 0 {
+            return None;
+        }
+
+        Some(Self::new(
+            stored_len,
+            u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+        ))
+    }
+
+    /// Create a block header from the given stored length including the stored flag, as written in the frame.
+    pub fn new(raw_stored_len: u32, len: u32, checksum: u32) -> Self {
+        Self {
+            stored_len: (raw_stored_len & !STORED_FLAG) as usize,
+            stored: raw_stored_len & STORED_FLAG != 0,
+            len: len as usize,
+            checksum,
+        }
+    }
+
+    /// Returns the stored length including the stored flag, as written in the frame.
+    pub fn raw_stored_len(&self) -> u32 {
+        if self.stored {
+            self.stored_len as u32 | STORED_FLAG
+        } else {
+            self.stored_len as u32
+        }
+    }
+
+    /// Check that the lengths of the block are valid for a frame with the given `block_size`.
+    ///
+    /// The decompressed length is bounded by the stored length, such that a corrupt block header
+    /// does not cause a large allocation.
+    pub fn check(&self, block_size: usize) -> Result<(), FrameError> {
+        let valid_len = if self.stored {
+            self.len == self.stored_len
+        } else {
+            self.len <= self.stored_len * MAX_RATIO
+        };
+
+        if !valid_len || self.len > block_size || self.stored_len > block_size {
+            return Err(FrameError::InvalidInput);
+        }
+
+        Ok(())
+    }
+
+    /// Decode the given block `data` into `dst`, which should have the decompressed length of the block.
+    pub fn decode(&self, data: &[u8], dst: &mut [u8]) -> Result<(), FrameError> {
+        if self.stored {
+            if data.len() != dst.len() {
+                return Err(FrameError::InvalidInput);
+            }
+
+            dst.copy_from_slice(data);
+        } else {
+            decompress(data, dst).map_err(|_| FrameError::InvalidInput)?;
+        }
+
+        if adler32(1, dst) != self.checksum {
+            return Err(FrameError::Checksum);
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) struct Footer {
+    pub block_count: u32,
+    pub content_size: u64,
+}
+
+impl Footer {
+    pub fn parse(bytes: &[u8; FOOTER_LEN]) -> Result<Self, FrameError> {
+        if bytes[12..16] != FOOTER_MAGIC {
+            return Err(FrameError::InvalidInput);
+        }
+
+        Ok(Self {
+            block_count: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            content_size: u64::from_le_bytes(bytes[4..12].try_into().unwrap()),
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+pub use io::{FrameReader, FrameWriter};
+
+#[cfg(feature = "std")]
+mod io {
+    use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+
+    use super::{
+        BLOCK_HEADER_LEN, BlockHeader, DEFAULT_BLOCK_SIZE, FOOTER_LEN, Footer, FrameError,
+        HEADER_LEN, clamp_block_size, encode_block, write_header, write_trailer,
+    };
+    use crate::CompressLevel;
+
+    /// Writer which compresses data written to it into a frame.
+    ///
+    /// See [`compress_frame`](crate::compress_frame) for a description of frames.
+    ///
+    /// The frame is finished when the writer is dropped, but any error is ignored in that case.
+    /// Call [`FrameWriter::finish`] to handle errors.
+    ///
+    /// Data is accepted once it is buffered, and a compressed block is written to the underlying writer
+    /// by the next call to [`Write::write`] or [`Write::flush`], which returns any error that occurs.
+    /// A failed call can be retried, as the written data is never written twice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// let mut writer = lzo1x::FrameWriter::new(Vec::new(), lzo1x::CompressLevel::default());
+    /// writer.write_all(&[0xaa; 100]).unwrap();
+    ///
+    /// let frame = writer.finish().unwrap();
+    ///
+    /// assert_eq!(lzo1x::decompress_frame(&frame).unwrap(), [0xaa; 100]);
+    /// ```
+    pub struct FrameWriter<W: Write> {
+        inner: Option<W>,
+        level: CompressLevel,
+        block_size: usize,
+        src: Vec<u8>,
+        dst: Vec<u8>,
+        dst_pos: usize,
+        blocks: Vec<(u32, u32)>,
+        content_size: u64,
+        finished: bool,
+    }
+
+    impl<W: Write> FrameWriter<W> {
+        /// Create a new `FrameWriter` that writes a frame with the given compression `level` to the given `inner` writer.
+        ///
+        /// The frame uses blocks of 256 KiB.
+        pub fn new(inner: W, level: CompressLevel) -> Self {
+            Self::with_block_size(inner, level, DEFAULT_BLOCK_SIZE)
+        }
+
+        /// Create a new `FrameWriter` that writes a frame with the given compression `level` and `block_size`
+        /// to the given `inner` writer.
+        ///
+        /// The given `block_size` is clamped to the range 1 B - 1 GiB.
+        pub fn with_block_size(inner: W, level: CompressLevel, block_size: usize) -> Self {
+            let block_size = clamp_block_size(block_size);
+
+            let mut dst = Vec::new();
+            write_header(&mut dst, block_size);
+
+            Self {
+                inner: Some(inner),
+                level,
+                block_size,
+                src: Vec::new(),
+                dst,
+                dst_pos: 0,
+                blocks: Vec::new(),
+                content_size: 0,
+                finished: false,
+            }
+        }
+
+        /// Returns a reference to the underlying writer.
+        pub fn get_ref(&self) -> &W {
+            self.inner.as_ref().unwrap()
+        }
+
+        /// Returns a mutable reference to the underlying writer.
+        pub fn get_mut(&mut self) -> &mut W {
+            self.inner.as_mut().unwrap()
+        }
+
+        /// Compress all remaining data, write the seek table, and return the underlying writer.
+        ///
+        /// #### Errors
+        ///
+        /// This function returns an error if writing to the underlying writer fails.
+        pub fn finish(mut self) -> io::Result<W> {
+            self.end()?;
+
+            Ok(self.inner.take().unwrap())
+        }
+
+        fn end(&mut self) -> io::Result<()> {
+            // The writer is finished before writing the trailer, such that a failed write is not repeated on drop.
+            self.finished = true;
+
+            self.encode_block();
+
+            write_trailer(&mut self.dst, &self.blocks, self.content_size);
+            self.write_dst()?;
+
+            self.inner.as_mut().unwrap().flush()
+        }
+
+        fn encode_block(&mut self) {
+            if self.src.is_empty() {
+                return;
+            }
+
+            let stored_len = encode_block(&self.src, self.level, &mut self.dst);
+            self.blocks.push((stored_len, self.src.len() as u32));
+            self.content_size += self.src.len() as u64;
+
+            self.src.clear();
+        }
+
+        /// Write all buffered compressed data to the underlying writer.
+        ///
+        /// The position is kept on error, such that a retry continues where the failed write stopped.
+        fn write_dst(&mut self) -> io::Result<()> {
+            let inner = self.inner.as_mut().unwrap();
+
+            while self.dst_pos < self.dst.len() {
+                match inner.write(&self.dst[self.dst_pos..]) {
+                    Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                    Ok(n) => self.dst_pos += n,
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+
+            self.dst.clear();
+            self.dst_pos = 0;
+
+            Ok(())
+        }
+    }
+
+    impl<W: Write> Write for FrameWriter<W> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            loop {
+                self.write_dst()?;
+
+                if self.src.len() < self.block_size {
+                    break;
+                }
+
+                self.encode_block();
+            }
+
+            let len = buf.len().min(self.block_size - self.src.len());
+            self.src.extend_from_slice(&buf[..len]);
+
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.encode_block();
+            self.write_dst()?;
+
+            self.inner.as_mut().unwrap().flush()
+        }
+    }
+
+    impl<W: Write> Drop for FrameWriter<W> {
+        fn drop(&mut self) {
+            if !self.finished {
+                let _ = self.end();
+            }
+        }
+    }
+
+    /// Reader which decompresses a frame, and supports seeking to arbitrary positions in the decompressed data.
+    ///
+    /// Only the blocks which contain the data that is read are decompressed.
+    ///
+    /// See [`compress_frame`](crate::compress_frame) for a description of frames.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Cursor, Read, Seek, SeekFrom};
+    ///
+    /// let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+    /// let frame = lzo1x::compress_frame(&data, lzo1x::CompressLevel::default(), 100);
+    ///
+    /// let mut reader = lzo1x::FrameReader::new(Cursor::new(frame)).unwrap();
+    /// reader.seek(SeekFrom::Start(550)).unwrap();
+    ///
+    /// let mut buf = [0; 100];
+    /// reader.read_exact(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf, data[550..650]);
+    /// ```
+    pub struct FrameReader<R: Read + Seek> {
+        inner: R,
+        blocks: Vec<Block>,
+        content_size: u64,
+        pos: u64,
+        block: Option<usize>,
+        src: Vec<u8>,
+        dst: Vec<u8>,
+    }
+
+    struct Block {
+        offset: u64,
+        start: u64,
+        stored_len: u32,
+        len: u32,
+    }
+
+    impl<R: Read + Seek> FrameReader<R> {
+        /// Create a new `FrameReader` that reads a frame from the given `inner` reader.
+        ///
+        /// The frame is expected to start at the current position of the given `inner` reader,
+        /// and to end at the end of the given `inner` reader.
+        ///
+        /// #### Errors
+        ///
+        /// This function returns an error if reading from the given `inner` reader fails,
+        /// or if the header, seek table or footer of the frame are invalid.
+        pub fn new(mut inner: R) -> io::Result<Self> {
+            let frame_start = inner.stream_position()?;
+
+            let mut header = [0; HEADER_LEN];
+            inner.read_exact(&mut header)?;
+            let block_size = super::read_header(&header).map_err(invalid_data)?;
+
+            let frame_end = inner.seek(SeekFrom::End(0))?;
+
+            if frame_end - frame_start < (HEADER_LEN + 4 + FOOTER_LEN) as u64 {
+                return Err(invalid_data(FrameError::InvalidInput));
+            }
+
+            let mut footer = [0; FOOTER_LEN];
+            inner.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+            inner.read_exact(&mut footer)?;
+            let footer = Footer::parse(&footer).map_err(invalid_data)?;
+
+            let table_len = footer.block_count as u64 * 8;
+
+            if frame_end - frame_start < (HEADER_LEN + 4 + FOOTER_LEN) as u64 + table_len {
+                return Err(invalid_data(FrameError::InvalidInput));
+            }
+
+            // The seek table is read without allocating its full length up front,
+            // as the block count of a corrupt footer can be very large.
+            let mut table = Vec::new();
+            inner.seek(SeekFrom::End(-((table_len + FOOTER_LEN as u64) as i64)))?;
+            (&mut inner).take(table_len).read_to_end(&mut table)?;
+
+            if table.len() as u64 != table_len {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+
+            let mut blocks = Vec::with_capacity(table.len() / 8);
+            let mut offset = frame_start + HEADER_LEN as u64;
+            let mut start = 0;
+
+            for entry in table.chunks_exact(8) {
+                let stored_len = u32::from_le_bytes(entry[0..4].try_into().unwrap());
+                let len = u32::from_le_bytes(entry[4..8].try_into().unwrap());
+
+                let header = BlockHeader::new(stored_len, len, 0);
+                header.check(block_size).map_err(invalid_data)?;
+
+                blocks.push(Block {
+                    offset,
+                    start,
+                    stored_len,
+                    len,
+                });
+
+                offset += (BLOCK_HEADER_LEN + header.stored_len) as u64;
+                start += len as u64;
+            }
+
+            if start != footer.content_size
+                || offset + 4 + table_len + FOOTER_LEN as u64 != frame_end
+            {
+                return Err(invalid_data(FrameError::InvalidInput));
+            }
+
+            // The end mark follows the last block.
+            let mut end_mark = [0; 4];
+            inner.seek(SeekFrom::Start(offset))?;
+            inner.read_exact(&mut end_mark)?;
+
+            if end_mark != [0; 4] {
+                return Err(invalid_data(FrameError::InvalidInput));
+            }
+
+            Ok(Self {
+                inner,
+                blocks,
+                content_size: footer.content_size,
+                pos: 0,
+                block: None,
+                src: Vec::new(),
+                dst: Vec::new(),
+            })
+        }
+
+        /// Returns the length of the decompressed data.
+        pub fn content_size(&self) -> u64 {
+            self.content_size
+        }
+
+        /// Returns the underlying reader.
+        pub fn into_inner(self) -> R {
+            self.inner
+        }
+
+        fn load_block(&mut self, index: usize) -> io::Result<()> {
+            if self.block == Some(index) {
+                return Ok(());
+            }
+
+            self.block = None;
+
+            let block = &self.blocks[index];
+
+            self.inner.seek(SeekFrom::Start(block.offset))?;
+
+            let mut header = [0; BLOCK_HEADER_LEN];
+            self.inner.read_exact(&mut header)?;
+
+            let header = match BlockHeader::parse(&header) {
+                Some(header)
+                    if header.raw_stored_len() == block.stored_len
+                        && header.len == block.len as usize =>
+                {
+                    header
+                }
+                _ => return Err(invalid_data(FrameError::InvalidInput)),
+            };
+
+            // The lengths were checked against the seek table, so the decompressed length is bounded
+            // by the stored length, and the stored data is read without allocating its full length up front.
+            self.src.clear();
+            (&mut self.inner)
+                .take(header.stored_len as u64)
+                .read_to_end(&mut self.src)?;
+
+            if self.src.len() != header.stored_len {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+
+            self.dst.resize(header.len, 0);
+            header
+                .decode(&self.src, &mut self.dst)
+                .map_err(invalid_data)?;
+
+            self.block = Some(index);
+
+            Ok(())
+        }
+    }
+
+    impl<R: Read + Seek> Read for FrameReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if buf.is_empty() || self.pos >= self.content_size {
+                return Ok(0);
+            }
+
+            let index = self.blocks.partition_point(|block| block.start <= self.pos) - 1;
+            self.load_block(index)?;
+
+            let block_pos = (self.pos - self.blocks[index].start) as usize;
+            let len = buf.len().min(self.dst.len() - block_pos);
+
+            buf[..len].copy_from_slice(&self.dst[block_pos..block_pos + len]);
+            self.pos += len as u64;
+
+            Ok(len)
+        }
+    }
+
+    impl<R: Read + Seek> Seek for FrameReader<R> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            let pos = match pos {
+                SeekFrom::Start(pos) => Some(pos),
+                SeekFrom::End(offset) => self.content_size.checked_add_signed(offset),
+                SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+            };
+
+            match pos {
+                Some(pos) => {
+                    self.pos = pos;
+
+                    Ok(pos)
+                }
+                None => Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "invalid seek to a negative or overflowing position",
+                )),
+            }
+        }
+    }
+
+    fn invalid_data(err: FrameError) -> io::Error {
+        io::Error::new(ErrorKind::InvalidData, err)
+    }
+}
@@ -0,0 +1,191 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{
+    TokenWriter,
+    config::{
+        M1_MAX_OFFSET, M2_MAX_LEN, M2_MAX_OFFSET, M3_MAX_OFFSET, M4_MAX_OFFSET, MX_MAX_OFFSET,
+    },
+    decompress::{Insn, State, decode_insn},
+};
+
+// Maximum length of the decompressed data of a generated stream.
+const MAX_LEN: usize = 0x40000;
+
+/// Valid compressed data together with its decompressed data, generated from unstructured fuzzer input.
+///
+/// Random bytes almost never form valid compressed data, so fuzzers of code which processes compressed data
+/// rarely get past the first instruction. Generated streams consist of a single block, and cover every
+/// instruction form: literal runs of any length, M1 matches after 1 to 3 literals and after 4 or more literals,
+/// M2, M3 and M4 matches with extended lengths, and distances up to 49151 bytes.
+///
+/// This type is only available with the `arbitrary` feature.
+///
+/// # Examples
+///
+/// ```
+/// use arbitrary::{Arbitrary, Unstructured};
+///
+/// let input: Vec<u8> = (0..1000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+/// let mut u = Unstructured::new(&input);
+///
+/// let stream = lzo1x::ArbitraryStream::arbitrary(&mut u).unwrap();
+///
+/// let mut decompressed = vec![0; stream.decompressed.len()];
+/// lzo1x::decompress(&stream.compressed, &mut decompressed).unwrap();
+///
+/// assert_eq!(decompressed, stream.decompressed);
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ArbitraryStream {
+    /// The compressed data.
+    pub compressed: Vec<u8>,
+    /// The decompressed data.
+    pub decompressed: Vec<u8>,
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryStream {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut writer = TokenWriter::new();
+        let mut dst = Vec::new();
+
+        // Number of literals since the last match.
+        let mut lit = 0;
+
+        while !u.is_empty() && dst.len() < MAX_LEN {
+            let pos = dst.len();
+
+            let (len, dist) = match u.int_in_range(0..=4)? {
+                1 if (1..=3).contains(&lit) => (2, u.int_in_range(1..=pos.min(M1_MAX_OFFSET))?),
+                1 if lit >= 4 && pos > M2_MAX_OFFSET => (
+                    3,
+                    u.int_in_range(M2_MAX_OFFSET + 1..=pos.min(MX_MAX_OFFSET))?,
+                ),
+                2 if pos > 0 => (
+                    u.int_in_range(3..=M2_MAX_LEN)?,
+                    u.int_in_range(1..=pos.min(M2_MAX_OFFSET))?,
+                ),
+                3 if pos > 0 => (match_len(u)?, u.int_in_range(1..=pos.min(M3_MAX_OFFSET))?),
+                4 if pos > M3_MAX_OFFSET => (
+                    match_len(u)?,
+                    u.int_in_range(M3_MAX_OFFSET + 1..=pos.min(M4_MAX_OFFSET))?,
+                ),
+                _ => {
+                    let len = if u.ratio(1, 4)? {
+                        u.int_in_range(1..=2000)?
+                    } else {
+                        u.int_in_range(1..=20)?
+                    };
+
+                    // Once the input runs out, the literals are zero.
+                    dst.resize(pos + len, 0);
+                    u.fill_buffer(&mut dst[pos..])?;
+
+                    writer.write_literals(&dst[pos..]);
+                    lit += len;
+
+                    continue;
+                }
+            };
+
+            // The generated matches are always valid.
+            if writer.write_match(len, dist).is_err() {
+                unreachable!();
+            }
+
+            for _ in 0..len {
+                dst.push(dst[dst.len() - dist]);
+            }
+
+            lit = 0;
+        }
+
+        Ok(Self {
+            compressed: writer.finish(),
+            decompressed: dst,
+        })
+    }
+}
+
+impl ArbitraryStream {
+    /// Returns a corrupt copy of the compressed data, which is usually invalid while still being close to valid data.
+    ///
+    /// The corruption is chosen from the given unstructured fuzzer input. It is either a flipped bit, a truncation,
+    /// trailing data, or a changed, inserted or removed byte at the start of an instruction. Distances are
+    /// corrupted by setting the bytes following the start of an instruction to their maximum value.
+    ///
+    /// #### Errors
+    ///
+    /// This function returns [`IncorrectFormat`](arbitrary::Error::IncorrectFormat)
+    /// if the compressed data of the stream is not valid.
+    pub fn mutate(&self, u: &mut Unstructured<'_>) -> Result<Vec<u8>> {
+        let mut src = self.compressed.clone();
+
+        let insns = insn_positions(&src).ok_or(arbitrary::Error::IncorrectFormat)?;
+        let pos = insns[u.choose_index(insns.len())?];
+
+        match u.int_in_range(0..=6)? {
+            0 => {
+                let i = u.choose_index(src.len())?;
+                src[i] ^= 1 << u.int_in_range(0..=7)?;
+            }
+            1 => {
+                src.truncate(u.choose_index(src.len())?);
+            }
+            2 => {
+                src.push(u.arbitrary()?);
+            }
+            3 => {
+                src[pos] = u.arbitrary()?;
+            }
+            4 => {
+                src.insert(pos, u.arbitrary()?);
+            }
+            5 => {
+                src.remove(pos);
+            }
+            _ => {
+                for b in src[pos + 1..].iter_mut().take(2) {
+                    *b = 0xff;
+                }
+            }
+        }
+
+        Ok(src)
+    }
+}
+
+/// Returns the start positions of the instructions of the compressed data given in `src`,
+/// including the end marker, or `None` if the data is not valid.
+fn insn_positions(src: &[u8]) -> Option<Vec<usize>> {
+    let mut positions = Vec::new();
+
+    let mut src_pos = 0;
+    let mut state = State::Start;
+
+    loop {
+        positions.push(src_pos);
+
+        let insn = decode_insn(src, &mut src_pos, state).ok()?;
+
+        match insn {
+            Insn::Literal { len } => src_pos += len,
+            Insn::Match { lit, .. } => src_pos += lit,
+            Insn::End => return Some(positions),
+        }
+
+        if src_pos > src.len() {
+            return None;
+        }
+
+        state = state.next(&insn);
+    }
+}
+
+fn match_len(u: &mut Unstructured<'_>) -> Result<usize> {
+    // Long matches have extended lengths of several bytes.
+    if u.ratio(1, 4)? {
+        u.int_in_range(3..=2000)
+    } else {
+        u.int_in_range(3..=40)
+    }
+}
@@ -31,24 +31,109 @@
 //! assert_eq!(compressed.len(), 9);
 //! ```
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+mod analyze;
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "std")]
+pub mod build;
+#[cfg(feature = "alloc")]
+mod checksum;
 mod compress_1;
 mod compress_999;
+#[cfg(feature = "alloc")]
+mod concat;
 mod config;
 mod decompress;
+mod embedded;
+#[cfg(feature = "alloc")]
+mod frame;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+#[cfg(feature = "alloc")]
+mod match_finder;
+#[cfg(feature = "alloc")]
 mod optimize;
+#[cfg(feature = "alloc")]
+mod optimizer;
+mod output;
+#[cfg(feature = "alloc")]
+mod pages;
+#[cfg(feature = "std")]
+mod parallel;
+#[cfg(feature = "alloc")]
+mod recompress;
+mod ring;
+#[cfg(feature = "std")]
+mod stream;
 mod swd;
+mod token;
+#[cfg(feature = "alloc")]
+mod try_alloc;
+#[cfg(feature = "alloc")]
+mod vectored;
+mod workspace;
 
-use core::fmt::{self, Display, Formatter};
+#[cfg(feature = "alloc")]
+use core::ops::ControlFlow;
+use core::{
+    fmt::{self, Display, Formatter},
+    ops::DerefMut,
+};
 
-pub use decompress::decompress;
-pub use optimize::optimize;
+#[cfg(feature = "alloc")]
+pub use analyze::analyze_regions;
+pub use analyze::{Analysis, InsnStats, Region, analyze};
+#[cfg(feature = "tokio")]
+pub use async_io::{AsyncFrameReader, AsyncFrameWriter};
+#[cfg(feature = "alloc")]
+pub use concat::concat;
+#[cfg(feature = "alloc")]
+pub use decompress::try_decompress_to_vec;
+pub use decompress::{decompress, decompress_const, decompress_into};
+pub use embedded::Embedded;
+#[cfg(feature = "alloc")]
+pub use frame::{FrameError, compress_frame, decompress_frame};
+#[cfg(feature = "std")]
+pub use frame::{FrameReader, FrameWriter};
+#[cfg(feature = "arbitrary")]
+pub use fuzz::ArbitraryStream;
+#[cfg(feature = "alloc")]
+pub use match_finder::{Match, MatchFinder};
+#[cfg(feature = "alloc")]
+pub use optimize::{optimize, try_optimize};
+#[cfg(feature = "std")]
+pub use optimizer::OptimizeWriter;
+#[cfg(feature = "alloc")]
+pub use optimizer::Optimizer;
+#[cfg(feature = "alloc")]
+pub use pages::{CompressedPages, PageCompressor, compress_pages};
+#[cfg(feature = "std")]
+pub use parallel::{compress_frame_parallel, decompress_frame_parallel};
+#[cfg(feature = "alloc")]
+pub use recompress::{recompress, recompress_optimized};
+pub use ring::{RingDecoder, RingError};
+#[cfg(feature = "std")]
+pub use stream::{Decoder, Encoder};
+pub use swd::Workspace999;
+pub use token::TokenError;
+#[cfg(feature = "alloc")]
+pub use token::TokenWriter;
+#[cfg(feature = "alloc")]
+pub use vectored::compress_vectored;
+pub use workspace::{
+    LZO1X_1_11_MEM_COMPRESS, LZO1X_1_12_MEM_COMPRESS, LZO1X_1_15_MEM_COMPRESS,
+    LZO1X_1_MEM_COMPRESS, LZO1X_999_MEM_COMPRESS, Workspace, compress_bound,
+    compress_with_workspace,
+};
 
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
-use compress_1::compress_1;
-use compress_999::compress_999;
+use compress_999::Params;
+use config::{M2_MIN_LEN, M4_MAX_OFFSET};
 
 /// Compress the given `src` with the given compression `level`.
 ///
@@ -62,25 +147,193 @@ use compress_999::compress_999;
 ///
 /// assert_eq!(compressed.len(), 34);
 /// ```
+#[cfg(feature = "alloc")]
 pub fn compress(src: &[u8], level: CompressLevel) -> Vec<u8> {
-    match level.0 {
-        1 => compress_1(src, 11),
-        2 => compress_1(src, 12),
-        3 => compress_1(src, 14),
-        4 => compress_1(src, 15),
-        5 => compress_999(src, 0, 0, 0, 8, 4, 0),
-        6 => compress_999(src, 0, 0, 0, 16, 8, 0),
-        7 => compress_999(src, 0, 0, 0, 32, 16, 0),
-        8 => compress_999(src, 1, 5, 5, 16, 16, 0),
-        9 => compress_999(src, 1, 8, 16, 32, 32, 0),
-        10 => compress_999(src, 1, 8, 16, 128, 128, 0),
-        11 => compress_999(src, 2, 8, 32, 128, 256, 0),
-        12 => compress_999(src, 2, 32, 128, 2048, 2048, 1),
-        13 => compress_999(src, 2, 2048, 2048, 2048, 4096, 1),
-        _ => unreachable!(),
+    match try_compress(src, level) {
+        Ok(dst) => dst,
+        Err(TryCompressError::OutOfMemory) => panic!("memory allocation failed"),
+    }
+}
+
+/// Compress the given `src` with the given compression `level`, returning an error if memory allocation fails.
+///
+/// Unlike [`compress`], this function does not abort when memory allocation fails,
+/// including for the internal dictionary of the compressor.
+/// The compressed data is identical to the data returned by [`compress`].
+///
+/// #### Errors
+///
+/// This function returns [`TryCompressError::OutOfMemory`] if memory allocation fails.
+///
+/// # Examples
+///
+/// ```
+/// let data = &[0xaa; 100];
+/// let compressed = lzo1x::try_compress(data, lzo1x::CompressLevel::default()).unwrap();
+///
+/// assert_eq!(compressed.len(), 34);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn try_compress(src: &[u8], level: CompressLevel) -> Result<Vec<u8>, TryCompressError> {
+    match compress_with_callback(src, level, |_, _| ControlFlow::Continue(())) {
+        Ok(dst) => Ok(dst),
+        Err(CompressError::Cancelled) => unreachable!(),
+        Err(CompressError::OutOfMemory) => Err(TryCompressError::OutOfMemory),
+    }
+}
+
+/// Compress the given `src` with the given compression `level`, regularly reporting progress to the given `callback`.
+///
+/// The `callback` is called with the current position in `src` and the current length of the compressed data.
+/// Returning [`ControlFlow::Break`] from the `callback` cancels the compression.
+///
+/// The compressed data is identical to the data returned by [`compress`].
+///
+/// #### Errors
+///
+/// This function returns an error if the compression was cancelled by the given `callback`,
+/// or if memory allocation fails.
+///
+/// # Examples
+///
+/// ```
+/// use std::ops::ControlFlow;
+///
+/// let data = &[0xaa; 100];
+///
+/// let compressed = lzo1x::compress_with_callback(data, lzo1x::CompressLevel::new(13), |src_pos, dst_len| {
+///     println!("{src_pos} bytes read, {dst_len} bytes written");
+///
+///     ControlFlow::Continue(())
+/// })
+/// .unwrap();
+///
+/// assert_eq!(compressed.len(), 9);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn compress_with_callback<F>(
+    src: &[u8],
+    level: CompressLevel,
+    mut callback: F,
+) -> Result<Vec<u8>, CompressError>
+where
+    F: FnMut(usize, usize) -> ControlFlow<()>,
+{
+    let mut dst = Vec::new();
+
+    dst.try_reserve_exact(compress_bound(src.len()))
+        .map_err(|_| CompressError::OutOfMemory)?;
+
+    let mut compressor = Compressor::new(level);
+    compressor.compress(src, &mut dst, true, &mut callback)?;
+
+    Ok(dst)
+}
+
+/// Compress the given `src` with the given compression `options`.
+///
+/// The compressed data is standard LZO1X data, which can be decompressed with [`decompress`].
+///
+/// # Examples
+///
+/// ```
+/// let data = &[0xaa; 100];
+/// let options = lzo1x::CompressOptions::new(lzo1x::CompressLevel::default()).max_offset(4096);
+///
+/// let compressed = lzo1x::compress_with_options(data, options);
+///
+/// let mut decompressed = vec![0; data.len()];
+/// lzo1x::decompress(&compressed, &mut decompressed).unwrap();
+///
+/// assert_eq!(decompressed, data);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn compress_with_options(src: &[u8], options: CompressOptions) -> Vec<u8> {
+    let mut dst = Vec::with_capacity(compress_bound(src.len()));
+
+    let mut compressor = Compressor::with_options(options);
+
+    match compressor.compress(src, &mut dst, true, &mut |_, _| ControlFlow::Continue(())) {
+        Ok(()) => dst,
+        Err(CompressError::Cancelled) => unreachable!(),
+        Err(CompressError::OutOfMemory) => panic!("memory allocation failed"),
     }
 }
 
+/// Resumable compressor for any compression level.
+#[cfg(feature = "alloc")]
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Compressor {
+    Lzo1x1(compress_1::Compressor, Vec<u8>),
+    Lzo1x999(compress_999::Compressor<swd::OwnedWorkspace>),
+}
+
+#[cfg(feature = "alloc")]
+impl Compressor {
+    pub(crate) fn new(level: CompressLevel) -> Self {
+        Self::with_options(CompressOptions::new(level))
+    }
+
+    pub(crate) fn with_options(options: CompressOptions) -> Self {
+        match options.algorithm() {
+            Algorithm::Lzo1x1(compressor) => Self::Lzo1x1(compressor, Vec::new()),
+            Algorithm::Lzo1x999(compressor) => Self::Lzo1x999(compressor),
+        }
+    }
+
+    /// Compress the input given in `src` as far as possible, appending the compressed data to `dst`.
+    ///
+    /// If `finish` is set, all of the input is compressed and the current block is ended with an end marker.
+    pub(crate) fn compress(
+        &mut self,
+        src: &[u8],
+        dst: &mut Vec<u8>,
+        finish: bool,
+        callback: &mut dyn FnMut(usize, usize) -> ControlFlow<()>,
+    ) -> Result<(), CompressError> {
+        match self {
+            Self::Lzo1x1(compressor, dict) => {
+                // The dictionary is allocated on first use, such that allocation failures can be reported.
+                if dict.is_empty() {
+                    try_alloc::try_resize(dict, compressor.dict_len(), 0)
+                        .map_err(|_| CompressError::OutOfMemory)?;
+                }
+
+                compressor.compress(src, dst, finish, dict, callback)
+            }
+            Self::Lzo1x999(compressor) => {
+                let workspace =
+                    || swd::OwnedWorkspace::new().map_err(|_| CompressError::OutOfMemory);
+
+                compressor.compress(src, dst, finish, workspace, callback)
+            }
+        }
+    }
+
+    /// Index of the first byte in the input buffer that is still required.
+    pub(crate) fn retain_from(&self) -> usize {
+        match self {
+            Self::Lzo1x1(compressor, _) => compressor.retain_from(),
+            Self::Lzo1x999(compressor) => compressor.retain_from(),
+        }
+    }
+
+    /// Notify the compressor that the first `n` bytes of the input buffer were discarded.
+    pub(crate) fn shift(&mut self, n: usize) {
+        match self {
+            Self::Lzo1x1(compressor, _) => compressor.shift(n),
+            Self::Lzo1x999(compressor) => compressor.shift(n),
+        }
+    }
+}
+
+/// Compressor of the algorithm used by a compression level, without its dictionary.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Algorithm<W> {
+    Lzo1x1(compress_1::Compressor),
+    Lzo1x999(compress_999::Compressor<W>),
+}
+
 /// Compression level.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct CompressLevel(u8);
@@ -125,6 +378,183 @@ impl From<u8> for CompressLevel {
     }
 }
 
+/// Compression options, which can restrict the matches in the compressed data for decoders with a small history buffer.
+///
+/// By default, matches can refer back up to 49151 bytes, and have an unlimited length.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CompressOptions {
+    level: CompressLevel,
+    max_offset: usize,
+    max_len: usize,
+    binary_trees: bool,
+    acceleration: usize,
+}
+
+impl CompressOptions {
+    /// Create a new `CompressOptions` instance with the given compression `level`, and without any restrictions.
+    pub const fn new(level: CompressLevel) -> Self {
+        Self {
+            level,
+            max_offset: M4_MAX_OFFSET,
+            max_len: usize::MAX,
+            binary_trees: false,
+            acceleration: 1,
+        }
+    }
+
+    /// Limit the distance that matches can refer back to the given `max_offset`,
+    /// such that the data can be decompressed with a history buffer of `max_offset` bytes.
+    ///
+    /// The given `max_offset` should be between 1 and 49151, otherwise it is clamped to the nearest valid value.
+    pub const fn max_offset(mut self, max_offset: usize) -> Self {
+        self.max_offset = if max_offset < 1 {
+            1
+        } else if max_offset > M4_MAX_OFFSET {
+            M4_MAX_OFFSET
+        } else {
+            max_offset
+        };
+
+        self
+    }
+
+    /// Limit the length of matches to the given `max_len`.
+    ///
+    /// The given `max_len` should be at least 3, otherwise it is clamped to 3.
+    /// Compression levels 5 and higher limit matches to at most 2048 bytes regardless.
+    pub const fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = if max_len < M2_MIN_LEN {
+            M2_MIN_LEN
+        } else {
+            max_len
+        };
+
+        self
+    }
+
+    /// Find matches with binary trees instead of hash chains at compression levels 12 and 13 if `binary_trees` is set.
+    ///
+    /// This is several times faster for data in which many positions start with the same bytes, such as text
+    /// with a small alphabet, but can be somewhat slower for other data. The compressed data differs slightly
+    /// from the data produced by liblzo, which is always produced by default.
+    /// Other compression levels are not affected.
+    pub const fn binary_trees(mut self, binary_trees: bool) -> Self {
+        self.binary_trees = binary_trees;
+        self
+    }
+
+    /// Trade compression ratio for speed with the given `acceleration` factor, similar to the acceleration of LZ4.
+    ///
+    /// Compression levels 1 to 4 skip ahead through data without matches in steps of at least `acceleration` bytes
+    /// instead of 1 byte, and use a hash table that is smaller by a factor of up to `acceleration`, but at least
+    /// 2048 bytes. The default of 1 produces the same compressed data as liblzo.
+    /// Compression levels 5 and higher are not affected.
+    ///
+    /// The given `acceleration` should be between 1 and 64, otherwise it is clamped to the nearest valid value.
+    pub const fn acceleration(mut self, acceleration: usize) -> Self {
+        self.acceleration = if acceleration < 1 {
+            1
+        } else if acceleration > 64 {
+            64
+        } else {
+            acceleration
+        };
+
+        self
+    }
+}
+
+impl CompressOptions {
+    pub(crate) fn algorithm<W: DerefMut<Target = Workspace999>>(&self) -> Algorithm<W> {
+        let lzo1x_1 = |d_bits: u32| {
+            let d_bits = (d_bits - self.acceleration.ilog2()).max(10);
+
+            Algorithm::Lzo1x1(compress_1::Compressor::new(
+                d_bits,
+                self.max_offset,
+                self.max_len,
+                self.acceleration,
+            ))
+        };
+
+        let lzo1x_999 = |params: Params| {
+            let params = if self.binary_trees {
+                params
+            } else {
+                params.liblzo_compatible()
+            };
+
+            Algorithm::Lzo1x999(compress_999::Compressor::new(
+                params,
+                self.max_offset,
+                self.max_len,
+            ))
+        };
+
+        match self.level.0 {
+            1 => lzo1x_1(11),
+            2 => lzo1x_1(12),
+            3 => lzo1x_1(14),
+            4 => lzo1x_1(15),
+            5 => lzo1x_999(Params::new(0, 0, 0, 8, 4, 0)),
+            6 => lzo1x_999(Params::new(0, 0, 0, 16, 8, 0)),
+            7 => lzo1x_999(Params::new(0, 0, 0, 32, 16, 0)),
+            8 => lzo1x_999(Params::new(1, 5, 5, 16, 16, 0)),
+            9 => lzo1x_999(Params::new(1, 8, 16, 32, 32, 0)),
+            10 => lzo1x_999(Params::new(1, 8, 16, 128, 128, 0)),
+            11 => lzo1x_999(Params::new(2, 8, 32, 128, 256, 0)),
+            12 => lzo1x_999(Params::new(2, 32, 128, 2048, 2048, 1).tree_depth(64)),
+            13 => lzo1x_999(Params::new(2, 2048, 2048, 2048, 4096, 1).tree_depth(128)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl From<CompressLevel> for CompressOptions {
+    fn from(level: CompressLevel) -> Self {
+        Self::new(level)
+    }
+}
+
+/// Error that occured during compression.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CompressError {
+    /// The compression was cancelled by the progress callback.
+    Cancelled,
+    /// Memory allocation failed.
+    OutOfMemory,
+}
+
+impl Display for CompressError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Cancelled => f.write_str("compression cancelled"),
+            Self::OutOfMemory => f.write_str("memory allocation failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompressError {}
+
+/// Error that occured during compression with fallible memory allocation.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TryCompressError {
+    /// Memory allocation failed.
+    OutOfMemory,
+}
+
+impl Display for TryCompressError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::OutOfMemory => f.write_str("memory allocation failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TryCompressError {}
+
 /// Error that occured during decompression.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum DecompressError {
@@ -145,3 +575,37 @@ impl Display for DecompressError {
 
 #[cfg(feature = "std")]
 impl std::error::Error for DecompressError {}
+
+/// Error that occured during decompression with fallible memory allocation.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TryDecompressError {
+    /// The decompression failed.
+    Decompress(DecompressError),
+    /// Memory allocation failed.
+    OutOfMemory,
+}
+
+impl From<DecompressError> for TryDecompressError {
+    fn from(err: DecompressError) -> Self {
+        Self::Decompress(err)
+    }
+}
+
+impl Display for TryDecompressError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Decompress(err) => err.fmt(f),
+            Self::OutOfMemory => f.write_str("memory allocation failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TryDecompressError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decompress(err) => Some(err),
+            Self::OutOfMemory => None,
+        }
+    }
+}
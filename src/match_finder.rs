@@ -0,0 +1,175 @@
+use crate::{
+    compress_999::Compress,
+    config::M2_MIN_LEN,
+    swd::{OwnedWorkspace, SWD_F, SWD_N, SWD_THRESHOLD, Swd},
+};
+
+/// A match of the input at the current position with earlier input.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Match {
+    /// Length of the match, which is at least 2.
+    pub len: usize,
+    /// Distance back from the current position to the start of the earlier input, which is at least 1.
+    pub dist: usize,
+}
+
+/// Finder of matches in the input, using the hash chains of the LZO1X-999 compressor.
+///
+/// The finder walks through the input from start to end. At every position, [`MatchFinder::find_best`] finds
+/// the longest match with the preceding 49151 bytes, and [`MatchFinder::candidates`] enumerates all earlier
+/// positions with the same next 3 bytes. [`MatchFinder::accept`] then moves the finder forward, for example
+/// past the match which was chosen. Matches are at most 2048 bytes long.
+///
+/// # Examples
+///
+/// ```
+/// let data = b"abcdefgh abcdefgh abcd";
+///
+/// let mut finder = lzo1x::MatchFinder::new(data);
+/// finder.accept(9);
+///
+/// let best = finder.find_best().unwrap();
+///
+/// assert_eq!((best.len, best.dist), (13, 9));
+///
+/// finder.accept(best.len);
+///
+/// assert_eq!(finder.position(), 22);
+/// assert_eq!(finder.find_best(), None);
+/// ```
+pub struct MatchFinder<'a> {
+    src: &'a [u8],
+    c: Compress,
+    swd: Swd<OwnedWorkspace>,
+    pos: usize,
+    /// Result of the search at the current position, if the current position is already searched.
+    best: Option<Option<Match>>,
+}
+
+impl<'a> MatchFinder<'a> {
+    /// Create a new `MatchFinder` instance for the given `src`, starting at its first byte.
+    ///
+    /// #### Panics
+    ///
+    /// Panics if memory allocation fails.
+    pub fn new(src: &'a [u8]) -> Self {
+        let Ok(workspace) = OwnedWorkspace::new() else {
+            panic!("memory allocation failed");
+        };
+
+        let mut c = Compress::new();
+        let swd = Swd::new(&mut c, src, 0, SWD_N, SWD_F, 0, workspace);
+
+        Self {
+            src,
+            c,
+            swd,
+            pos: 0,
+            best: None,
+        }
+    }
+
+    /// Limit the number of earlier positions that [`MatchFinder::find_best`] compares with.
+    ///
+    /// A smaller limit results in faster searches at the cost of possibly missing the longest match.
+    /// The default is 2048, and a `max_chain` of zero means that there is no limit.
+    pub fn max_chain(mut self, max_chain: usize) -> Self {
+        self.swd.max_chain = max_chain;
+
+        self
+    }
+
+    /// Stop the search of [`MatchFinder::find_best`] as soon as a match of at least `nice_length` bytes is found.
+    ///
+    /// The default is 2048, which is the maximum length of a match.
+    pub fn nice_length(mut self, nice_length: usize) -> Self {
+        self.swd.nice_length = nice_length.clamp(1, SWD_F);
+
+        self
+    }
+
+    /// Returns the current position in the input.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Find the longest match at the current position, without moving forward.
+    ///
+    /// Matches of 3 or more bytes are searched in the hash chain of the current position.
+    /// If there is no such match, the most recent match of 2 bytes is returned.
+    ///
+    /// The search stops early as described at [`MatchFinder::max_chain`] and [`MatchFinder::nice_length`],
+    /// so the returned match is not always the longest possible match.
+    pub fn find_best(&mut self) -> Option<Match> {
+        if let Some(best) = self.best {
+            return best;
+        }
+
+        self.swd.m_len = SWD_THRESHOLD;
+        self.swd.m_off = 0;
+        self.swd.find_best();
+
+        let best = (self.swd.m_off > 0).then_some(Match {
+            len: self.swd.m_len,
+            dist: self.swd.m_off,
+        });
+
+        self.best = Some(best);
+
+        best
+    }
+
+    /// Returns an iterator over all matches of at least 3 bytes at the current position, without moving forward.
+    ///
+    /// The matches are ordered from the nearest to the farthest earlier position.
+    /// Unlike [`MatchFinder::find_best`], the number of matches is not limited by [`MatchFinder::max_chain`].
+    pub fn candidates(&mut self) -> impl Iterator<Item = Match> + '_ {
+        // The current position is inserted into its hash chain by the search.
+        self.find_best();
+
+        let swd = &self.swd;
+        let (mut node, mut cnt) = swd.chain();
+
+        core::iter::from_fn(move || {
+            while cnt > 0 {
+                let (len, dist) = swd.match_at(node);
+
+                node = swd.next_node(node);
+                cnt -= 1;
+
+                // Positions in the same hash chain do not always start with the same bytes.
+                if len >= M2_MIN_LEN {
+                    return Some(Match { len, dist });
+                }
+            }
+
+            None
+        })
+    }
+
+    /// Move `n` bytes forward in the input, adding the skipped positions to the hash chains.
+    ///
+    /// #### Panics
+    ///
+    /// Panics if `n` is larger than the number of remaining bytes of the input.
+    pub fn accept(&mut self, mut n: usize) {
+        assert!(
+            n <= self.src.len() - self.pos,
+            "cannot move past the end of the input"
+        );
+
+        if n == 0 {
+            return;
+        }
+
+        self.pos += n;
+
+        // A searched position is already in its hash chain.
+        if self.best.take().is_some() {
+            self.swd.get_byte(&mut self.c, self.src);
+            n -= 1;
+        }
+
+        self.swd.accept(&mut self.c, self.src, n);
+    }
+}
@@ -1,4 +1,6 @@
-use alloc::vec;
+use alloc::{vec, vec::Vec};
+
+use crate::{DecompressError, decompress, try_alloc::try_resize};
 
 /// Optimize compressed data given in `src` in terms of decompression speed.
 ///
@@ -23,6 +25,70 @@ use alloc::vec;
 pub fn optimize(src: &mut [u8], decompressed_len: usize) {
     let mut dst = vec![0; decompressed_len];
 
+    if optimize_into(src, &mut dst).is_err() {
+        panic!("invalid input");
+    }
+}
+
+/// Optimize compressed data given in `src` in terms of decompression speed, returning an error if the data is invalid.
+///
+/// The length of the original decompressed data must be given in `decompressed_len`.
+///
+/// Unlike [`optimize`], this function never panics, and the given `src` is validated as thoroughly as by [`decompress`].
+/// The optimized data is identical to the data optimized by [`optimize`],
+/// and is verified to decompress to the same data before it is written to `src`.
+///
+/// #### Errors
+///
+/// This function returns an error if the given `src` does not contain valid compressed data,
+/// or if `decompressed_len` does not exactly match the length of the decompressed data.
+/// As the buffers used for optimizing are sized by `decompressed_len`,
+/// a failed memory allocation is reported as [`DecompressError::OutputLength`].
+/// The given `src` is left unchanged on error.
+///
+/// # Examples
+///
+/// ```
+/// let data = &[0xaa; 100];
+/// let mut compressed = lzo1x::compress(data, lzo1x::CompressLevel::default());
+///
+/// lzo1x::try_optimize(&mut compressed, data.len()).unwrap();
+///
+/// assert_eq!(
+///     lzo1x::try_optimize(&mut [0xff; 10], 100),
+///     Err(lzo1x::DecompressError::InvalidInput)
+/// );
+/// ```
+pub fn try_optimize(src: &mut [u8], decompressed_len: usize) -> Result<(), DecompressError> {
+    let mut decompressed = Vec::new();
+    try_resize(&mut decompressed, decompressed_len, 0)
+        .map_err(|_| DecompressError::OutputLength)?;
+
+    decompress(src, &mut decompressed)?;
+
+    let mut optimized = Vec::new();
+    try_resize(&mut optimized, src.len(), 0).map_err(|_| DecompressError::OutputLength)?;
+    optimized.copy_from_slice(src);
+
+    let mut dst = Vec::new();
+    try_resize(&mut dst, decompressed_len, 0).map_err(|_| DecompressError::OutputLength)?;
+
+    optimize_into(&mut optimized, &mut dst)?;
+
+    // Ensure the optimized data still decompresses to the original data.
+    decompress(&optimized, &mut dst)?;
+
+    if dst != decompressed {
+        return Err(DecompressError::InvalidInput);
+    }
+
+    src.copy_from_slice(&optimized);
+
+    Ok(())
+}
+
+/// Optimize the given `src` in place, using the given `dst` of at least the decompressed length as scratch buffer.
+fn optimize_into(src: &mut [u8], dst: &mut [u8]) -> Result<(), DecompressError> {
     let mut litp = None;
     let mut lit = 0;
     let mut next_lit = usize::MAX;
@@ -34,8 +100,8 @@ pub fn optimize(src: &mut [u8], decompressed_len: usize) {
 
     let mut state: u8;
 
-    if src[src_idx] > 17 {
-        t = src[src_idx] as usize - 17;
+    if get(src, src_idx)? > 17 {
+        t = get(src, src_idx)? as usize - 17;
         src_idx += 1;
 
         if t < 4 {
@@ -54,10 +120,10 @@ pub fn optimize(src: &mut [u8], decompressed_len: usize) {
         match state {
             0 => {
                 if src_idx >= src.len() || dst_idx > dst.len() {
-                    panic!();
+                    return Err(DecompressError::InvalidInput);
                 }
 
-                t = src[src_idx] as usize;
+                t = get(src, src_idx)? as usize;
                 src_idx += 1;
 
                 if t >= 16 {
@@ -68,12 +134,12 @@ pub fn optimize(src: &mut [u8], decompressed_len: usize) {
                     if t == 0 {
                         t = 15;
 
-                        while src[src_idx] == 0 {
+                        while get(src, src_idx)? == 0 {
                             t += 255;
                             src_idx += 1;
                         }
 
-                        t += src[src_idx] as usize;
+                        t += get(src, src_idx)? as usize;
                         src_idx += 1;
                     }
 
@@ -83,51 +149,35 @@ pub fn optimize(src: &mut [u8], decompressed_len: usize) {
                 }
             }
             1 => {
-                dst[dst_idx] = src[src_idx];
-                dst_idx += 1;
-                src_idx += 1;
-                dst[dst_idx] = src[src_idx];
-                dst_idx += 1;
-                src_idx += 1;
-                dst[dst_idx] = src[src_idx];
-                dst_idx += 1;
-                src_idx += 1;
+                copy_literal(src, dst, &mut src_idx, &mut dst_idx)?;
+                copy_literal(src, dst, &mut src_idx, &mut dst_idx)?;
+                copy_literal(src, dst, &mut src_idx, &mut dst_idx)?;
 
                 state = 2;
             }
             2 => {
-                dst[dst_idx] = src[src_idx];
-                dst_idx += 1;
-                src_idx += 1;
+                copy_literal(src, dst, &mut src_idx, &mut dst_idx)?;
                 t -= 1;
 
                 while t > 0 {
-                    dst[dst_idx] = src[src_idx];
-                    dst_idx += 1;
-                    src_idx += 1;
+                    copy_literal(src, dst, &mut src_idx, &mut dst_idx)?;
                     t -= 1;
                 }
 
-                t = src[src_idx] as usize;
+                t = get(src, src_idx)? as usize;
                 src_idx += 1;
 
                 if t >= 16 {
                     state = 5;
                 } else {
-                    m_pos = dst_idx - 1 - 0x800;
+                    m_pos = dst_idx.wrapping_sub(1 + 0x800);
 
-                    m_pos -= t >> 2;
-                    m_pos -= (src[src_idx] as usize) << 2;
+                    m_pos = m_pos.wrapping_sub(t >> 2);
+                    m_pos = m_pos.wrapping_sub((get(src, src_idx)? as usize) << 2);
                     src_idx += 1;
-                    dst[dst_idx] = dst[m_pos];
-                    dst_idx += 1;
-                    m_pos += 1;
-                    dst[dst_idx] = dst[m_pos];
-                    dst_idx += 1;
-                    m_pos += 1;
-                    dst[dst_idx] = dst[m_pos];
-                    dst_idx += 1;
-                    m_pos += 1;
+                    copy_match(dst, &mut m_pos, &mut dst_idx)?;
+                    copy_match(dst, &mut m_pos, &mut dst_idx)?;
+                    copy_match(dst, &mut m_pos, &mut dst_idx)?;
                     lit = 0;
 
                     state = 7;
@@ -135,45 +185,47 @@ pub fn optimize(src: &mut [u8], decompressed_len: usize) {
             }
             3 => {
                 if t < 16 {
-                    m_pos = dst_idx - 1;
-                    m_pos -= t >> 2;
-                    m_pos -= (src[src_idx] as usize) << 2;
+                    m_pos = dst_idx.wrapping_sub(1);
+                    m_pos = m_pos.wrapping_sub(t >> 2);
+                    m_pos = m_pos.wrapping_sub((get(src, src_idx)? as usize) << 2);
                     src_idx += 1;
 
                     if let Some(litp) = &mut litp {
-                        nl = src[src_idx - 2] as usize & 3;
+                        nl = get(src, src_idx - 2)? as usize & 3;
 
-                        if nl == 0 && lit == 1 && src[src_idx] >= 16 {
+                        if nl == 0 && lit == 1 && get(src, src_idx)? >= 16 {
                             next_lit = nl;
                             lit += 2;
-                            src[*litp] = ((src[*litp] as usize & !3) | lit) as u8;
-                            copy2(src, src_idx - 2, &mut dst, m_pos, dst_idx - m_pos);
+                            set(src, *litp, ((get(src, *litp)? as usize & !3) | lit) as u8)?;
+                            copy2(src, src_idx - 2, dst, m_pos, dst_idx.wrapping_sub(m_pos))?;
 
                             state = 4;
                         } else if nl == 0
-                            && src[src_idx] < 16
-                            && src[src_idx] != 0
-                            && ((lit + 2 + src[src_idx] as usize) < 16)
+                            && get(src, src_idx)? < 16
+                            && get(src, src_idx)? != 0
+                            && ((lit + 2 + get(src, src_idx)? as usize) < 16)
                         {
-                            t = src[src_idx] as usize;
+                            t = get(src, src_idx)? as usize;
                             src_idx += 1;
-                            src[*litp] &= !3;
-                            copy2(src, src_idx - 3 + 1, &mut dst, m_pos, dst_idx - m_pos);
+                            set(src, *litp, get(src, *litp)? & !3)?;
+                            copy2(
+                                src,
+                                src_idx - 3 + 1,
+                                dst,
+                                m_pos,
+                                dst_idx.wrapping_sub(m_pos),
+                            )?;
                             *litp += 2;
 
                             if lit > 0 {
-                                src.copy_within(*litp..*litp + lit, *litp + 1);
+                                copy_within(src, *litp, lit)?;
                             }
 
                             lit += 2 + t + 3;
-                            src[*litp] = (lit - 3) as u8;
+                            set(src, *litp, (lit - 3) as u8)?;
 
-                            dst[dst_idx] = dst[m_pos];
-                            dst_idx += 1;
-                            m_pos += 1;
-                            dst[dst_idx] = dst[m_pos];
-                            dst_idx += 1;
-                            m_pos += 1;
+                            copy_match(dst, &mut m_pos, &mut dst_idx)?;
+                            copy_match(dst, &mut m_pos, &mut dst_idx)?;
 
                             state = 1;
                         } else {
@@ -187,47 +239,43 @@ pub fn optimize(src: &mut [u8], decompressed_len: usize) {
                 }
             }
             4 => {
-                dst[dst_idx] = dst[m_pos];
-                dst_idx += 1;
-                m_pos += 1;
-                dst[dst_idx] = dst[m_pos];
-                dst_idx += 1;
-                m_pos += 1;
+                copy_match(dst, &mut m_pos, &mut dst_idx)?;
+                copy_match(dst, &mut m_pos, &mut dst_idx)?;
 
                 state = 7;
             }
             5 => {
                 if t >= 64 {
-                    m_pos = dst_idx - 1;
-                    m_pos -= (t >> 2) & 7;
-                    m_pos -= (src[src_idx] as usize) << 3;
+                    m_pos = dst_idx.wrapping_sub(1);
+                    m_pos = m_pos.wrapping_sub((t >> 2) & 7);
+                    m_pos = m_pos.wrapping_sub((get(src, src_idx)? as usize) << 3);
                     src_idx += 1;
                     t = (t >> 5) - 1;
 
                     if let Some(litp) = litp {
-                        nl = src[src_idx - 2] as usize & 3;
+                        nl = get(src, src_idx - 2)? as usize & 3;
                         if t == 1
                             && lit > 3
                             && nl == 0
-                            && src[src_idx] < 16
-                            && src[src_idx] != 0
-                            && ((lit + 3 + src[src_idx] as usize) < 16)
+                            && get(src, src_idx)? < 16
+                            && get(src, src_idx)? != 0
+                            && ((lit + 3 + get(src, src_idx)? as usize) < 16)
                         {
-                            t = src[src_idx] as usize;
+                            t = get(src, src_idx)? as usize;
                             src_idx += 1;
-                            copy3(src, src_idx - 1 - 2, &mut dst, m_pos, dst_idx - m_pos);
+                            copy3(
+                                src,
+                                src_idx - 1 - 2,
+                                dst,
+                                m_pos,
+                                dst_idx.wrapping_sub(m_pos),
+                            )?;
                             lit += 3 + t + 3;
-                            src[litp] = (lit - 3) as u8;
-
-                            dst[dst_idx] = dst[m_pos];
-                            dst_idx += 1;
-                            m_pos += 1;
-                            dst[dst_idx] = dst[m_pos];
-                            dst_idx += 1;
-                            m_pos += 1;
-                            dst[dst_idx] = dst[m_pos];
-                            dst_idx += 1;
-                            m_pos += 1;
+                            set(src, litp, (lit - 3) as u8)?;
+
+                            copy_match(dst, &mut m_pos, &mut dst_idx)?;
+                            copy_match(dst, &mut m_pos, &mut dst_idx)?;
+                            copy_match(dst, &mut m_pos, &mut dst_idx)?;
 
                             state = 1;
                         } else {
@@ -243,89 +291,91 @@ pub fn optimize(src: &mut [u8], decompressed_len: usize) {
                         if t == 0 {
                             t = 31;
 
-                            while src[src_idx] == 0 {
+                            while get(src, src_idx)? == 0 {
                                 t += 255;
                                 src_idx += 1;
                             }
 
-                            t += src[src_idx] as usize;
+                            t += get(src, src_idx)? as usize;
                             src_idx += 1;
                         }
 
-                        m_pos = dst_idx - 1;
-                        m_pos -= src[src_idx] as usize >> 2;
+                        m_pos = dst_idx.wrapping_sub(1);
+                        m_pos = m_pos.wrapping_sub(get(src, src_idx)? as usize >> 2);
                         src_idx += 1;
-                        m_pos -= (src[src_idx] as usize) << 6;
+                        m_pos = m_pos.wrapping_sub((get(src, src_idx)? as usize) << 6);
                         src_idx += 1;
                     } else {
                         m_pos = dst_idx;
-                        m_pos -= (t & 8) << 11;
+                        m_pos = m_pos.wrapping_sub((t & 8) << 11);
                         t &= 7;
 
                         if t == 0 {
                             t = 7;
 
-                            while src[src_idx] == 0 {
+                            while get(src, src_idx)? == 0 {
                                 t += 255;
                                 src_idx += 1;
                             }
 
-                            t += src[src_idx] as usize;
+                            t += get(src, src_idx)? as usize;
                             src_idx += 1;
                         }
 
-                        m_pos -= src[src_idx] as usize >> 2;
+                        m_pos = m_pos.wrapping_sub(get(src, src_idx)? as usize >> 2);
                         src_idx += 1;
-                        m_pos -= (src[src_idx] as usize) << 6;
+                        m_pos = m_pos.wrapping_sub((get(src, src_idx)? as usize) << 6);
                         src_idx += 1;
 
                         if m_pos == dst_idx {
                             if src_idx < src.len() {
-                                panic!();
+                                return Err(DecompressError::InvalidInput);
                             }
 
-                            return;
+                            return Ok(());
                         }
 
-                        m_pos -= 0x4000;
+                        m_pos = m_pos.wrapping_sub(0x4000);
                     }
 
                     if let Some(litp) = &mut litp {
-                        nl = src[src_idx - 2] as usize & 3;
-                        if t == 1 && lit == 0 && nl == 0 && src[src_idx] >= 16 {
+                        nl = get(src, src_idx - 2)? as usize & 3;
+                        if t == 1 && lit == 0 && nl == 0 && get(src, src_idx)? >= 16 {
                             next_lit = nl;
                             lit += 3;
-                            src[*litp] = ((src[*litp] as usize & !3) | lit) as u8;
-                            copy3(src, src_idx - 3, &mut dst, m_pos, dst_idx - m_pos);
+                            set(src, *litp, ((get(src, *litp)? as usize & !3) | lit) as u8)?;
+                            copy3(src, src_idx - 3, dst, m_pos, dst_idx.wrapping_sub(m_pos))?;
+
+                            state = 6;
                         } else if t == 1
                             && lit <= 3
                             && nl == 0
-                            && src[src_idx] < 16
-                            && src[src_idx] != 0
-                            && ((lit + 3 + src[src_idx] as usize) < 16)
+                            && get(src, src_idx)? < 16
+                            && get(src, src_idx)? != 0
+                            && ((lit + 3 + get(src, src_idx)? as usize) < 16)
                         {
-                            t = src[src_idx] as usize;
+                            t = get(src, src_idx)? as usize;
                             src_idx += 1;
-                            src[*litp] &= !3;
-                            copy3(src, src_idx - 4 + 1, &mut dst, m_pos, dst_idx - m_pos);
+                            set(src, *litp, get(src, *litp)? & !3)?;
+                            copy3(
+                                src,
+                                src_idx - 4 + 1,
+                                dst,
+                                m_pos,
+                                dst_idx.wrapping_sub(m_pos),
+                            )?;
                             *litp += 2;
 
                             if lit > 0 {
-                                src.copy_within(*litp..*litp + lit, *litp + 1);
+                                copy_within(src, *litp, lit)?;
                             }
 
                             lit += 3 + t + 3;
-                            src[*litp] = (lit - 3) as u8;
-
-                            dst[dst_idx] = dst[m_pos];
-                            dst_idx += 1;
-                            m_pos += 1;
-                            dst[dst_idx] = dst[m_pos];
-                            dst_idx += 1;
-                            m_pos += 1;
-                            dst[dst_idx] = dst[m_pos];
-                            dst_idx += 1;
-                            m_pos += 1;
+                            set(src, *litp, (lit - 3) as u8)?;
+
+                            copy_match(dst, &mut m_pos, &mut dst_idx)?;
+                            copy_match(dst, &mut m_pos, &mut dst_idx)?;
+                            copy_match(dst, &mut m_pos, &mut dst_idx)?;
 
                             state = 1;
                         } else {
@@ -337,21 +387,13 @@ pub fn optimize(src: &mut [u8], decompressed_len: usize) {
                 }
             }
             6 => {
-                dst[dst_idx] = dst[m_pos];
-                dst_idx += 1;
-                m_pos += 1;
-                dst[dst_idx] = dst[m_pos];
-                dst_idx += 1;
-                m_pos += 1;
-                dst[dst_idx] = dst[m_pos];
-                dst_idx += 1;
-                m_pos += 1;
+                copy_match(dst, &mut m_pos, &mut dst_idx)?;
+                copy_match(dst, &mut m_pos, &mut dst_idx)?;
+                copy_match(dst, &mut m_pos, &mut dst_idx)?;
                 t -= 1;
 
                 while t > 0 {
-                    dst[dst_idx] = dst[m_pos];
-                    dst_idx += 1;
-                    m_pos += 1;
+                    copy_match(dst, &mut m_pos, &mut dst_idx)?;
                     t -= 1;
                 }
 
@@ -359,7 +401,7 @@ pub fn optimize(src: &mut [u8], decompressed_len: usize) {
             }
             7 => {
                 if next_lit == usize::MAX {
-                    t = src[src_idx - 2] as usize & 3;
+                    t = get(src, src_idx - 2)? as usize & 3;
                     lit = t;
                     litp = Some(src_idx - 2);
                 } else {
@@ -375,19 +417,15 @@ pub fn optimize(src: &mut [u8], decompressed_len: usize) {
                 }
             }
             8 => {
-                dst[dst_idx] = src[src_idx];
-                dst_idx += 1;
-                src_idx += 1;
+                copy_literal(src, dst, &mut src_idx, &mut dst_idx)?;
                 t -= 1;
 
                 while t > 0 {
-                    dst[dst_idx] = src[src_idx];
-                    dst_idx += 1;
-                    src_idx += 1;
+                    copy_literal(src, dst, &mut src_idx, &mut dst_idx)?;
                     t -= 1;
                 }
 
-                t = src[src_idx] as usize;
+                t = get(src, src_idx)? as usize;
                 src_idx += 1;
 
                 if src_idx >= src.len() || dst_idx > dst.len() {
@@ -401,27 +439,91 @@ pub fn optimize(src: &mut [u8], decompressed_len: usize) {
     }
 }
 
-fn copy2(src: &mut [u8], src_idx: usize, dst: &mut [u8], m_pos: usize, off: usize) {
-    src[src_idx] = dst[m_pos];
+fn copy2(
+    src: &mut [u8],
+    src_idx: usize,
+    dst: &[u8],
+    m_pos: usize,
+    off: usize,
+) -> Result<(), DecompressError> {
+    set(src, src_idx, get(dst, m_pos)?)?;
 
     if off == 1 {
-        src[src_idx + 1] = dst[m_pos];
+        set(src, src_idx + 1, get(dst, m_pos)?)?;
     } else {
-        src[src_idx + 1] = dst[m_pos + 1];
+        set(src, src_idx + 1, get(dst, m_pos + 1)?)?;
     }
+
+    Ok(())
 }
 
-fn copy3(src: &mut [u8], src_idx: usize, dst: &mut [u8], m_pos: usize, off: usize) {
-    src[src_idx] = dst[m_pos];
+fn copy3(
+    src: &mut [u8],
+    src_idx: usize,
+    dst: &[u8],
+    m_pos: usize,
+    off: usize,
+) -> Result<(), DecompressError> {
+    set(src, src_idx, get(dst, m_pos)?)?;
 
     if off == 1 {
-        src[src_idx + 1] = dst[m_pos];
-        src[src_idx + 2] = dst[m_pos];
+        set(src, src_idx + 1, get(dst, m_pos)?)?;
+        set(src, src_idx + 2, get(dst, m_pos)?)?;
     } else if off == 2 {
-        src[src_idx + 1] = dst[m_pos + 1];
-        src[src_idx + 2] = dst[m_pos];
+        set(src, src_idx + 1, get(dst, m_pos + 1)?)?;
+        set(src, src_idx + 2, get(dst, m_pos)?)?;
     } else {
-        src[src_idx + 1] = dst[m_pos + 1];
-        src[src_idx + 2] = dst[m_pos + 2];
+        set(src, src_idx + 1, get(dst, m_pos + 1)?)?;
+        set(src, src_idx + 2, get(dst, m_pos + 2)?)?;
     }
+
+    Ok(())
+}
+
+/// Copy a literal byte from `src` to `dst`.
+fn copy_literal(
+    src: &[u8],
+    dst: &mut [u8],
+    src_idx: &mut usize,
+    dst_idx: &mut usize,
+) -> Result<(), DecompressError> {
+    set(dst, *dst_idx, get(src, *src_idx)?)?;
+    *src_idx += 1;
+    *dst_idx += 1;
+
+    Ok(())
+}
+
+/// Copy a match byte within `dst`.
+fn copy_match(
+    dst: &mut [u8],
+    m_pos: &mut usize,
+    dst_idx: &mut usize,
+) -> Result<(), DecompressError> {
+    set(dst, *dst_idx, get(dst, *m_pos)?)?;
+    *m_pos += 1;
+    *dst_idx += 1;
+
+    Ok(())
+}
+
+/// Move the `lit` literal bytes at `litp` one byte forward.
+fn copy_within(src: &mut [u8], litp: usize, lit: usize) -> Result<(), DecompressError> {
+    if litp + lit + 1 > src.len() {
+        return Err(DecompressError::InvalidInput);
+    }
+
+    src.copy_within(litp..litp + lit, litp + 1);
+
+    Ok(())
+}
+
+fn get(buf: &[u8], idx: usize) -> Result<u8, DecompressError> {
+    buf.get(idx).copied().ok_or(DecompressError::InvalidInput)
+}
+
+fn set(buf: &mut [u8], idx: usize, value: u8) -> Result<(), DecompressError> {
+    *buf.get_mut(idx).ok_or(DecompressError::InvalidInput)? = value;
+
+    Ok(())
 }
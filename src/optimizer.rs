@@ -0,0 +1,452 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, ErrorKind, Write};
+
+use crate::{
+    DecompressError,
+    config::M4_MAX_OFFSET,
+    decompress::{Insn, State, Truncated, copy_match, decode_insn},
+};
+
+// Number of discarded bytes at the start of a buffer before the buffer is compacted.
+const COMPACT_LEN: usize = 0x10000;
+
+/// Streaming optimizer, which optimizes compressed data in terms of decompression speed in a single pass.
+///
+/// Unlike [`optimize`](crate::optimize), the optimizer does not need to know the length of the decompressed data,
+/// and only keeps a window of the last 48 KiB of decompressed data.
+/// The compressed data can be given in pieces of any size, and may consist of multiple blocks,
+/// such as the data written by an [`Encoder`](crate::Encoder) with flushes.
+///
+/// The optimized data is identical to the data optimized by [`optimize`](crate::optimize),
+/// and has the same length as the original data.
+///
+/// # Examples
+///
+/// ```
+/// let data: Vec<u8> = (0..10_000u32).flat_map(|i| (i / 7).to_le_bytes()).collect();
+/// let compressed = lzo1x::compress(&data, lzo1x::CompressLevel::default());
+///
+/// let mut optimizer = lzo1x::Optimizer::new();
+/// let mut optimized = Vec::new();
+///
+/// for piece in compressed.chunks(100) {
+///     optimizer.update(piece, &mut optimized).unwrap();
+/// }
+///
+/// optimizer.finish(&mut optimized).unwrap();
+///
+/// let mut expected = compressed.clone();
+/// lzo1x::optimize(&mut expected, data.len());
+///
+/// assert_eq!(optimized, expected);
+/// ```
+pub struct Optimizer {
+    /// Compressed data which is not yet processed.
+    src: Vec<u8>,
+    /// Recently decompressed data, which contains at least the last 48 KiB.
+    hist: Vec<u8>,
+    /// Optimized data, which can still be modified from `litp` onward.
+    out: Vec<u8>,
+    /// Position in `out` of the byte which holds the length of the last literal run.
+    litp: Option<usize>,
+    /// Length of the last literal run.
+    lit: usize,
+    state: State,
+}
+
+impl Optimizer {
+    /// Create a new `Optimizer` instance.
+    pub fn new() -> Self {
+        Self {
+            src: Vec::new(),
+            hist: Vec::new(),
+            out: Vec::new(),
+            litp: None,
+            lit: 0,
+            state: State::Start,
+        }
+    }
+
+    /// Optimize the compressed data given in `src`, which continues the data given in previous calls,
+    /// appending the optimized data to `dst` as soon as it can no longer change.
+    ///
+    /// #### Errors
+    ///
+    /// This function returns an error if the compressed data is invalid.
+    pub fn update(&mut self, src: &[u8], dst: &mut Vec<u8>) -> Result<(), DecompressError> {
+        self.src.extend_from_slice(src);
+
+        let mut src_pos = 0;
+
+        while self.step(&mut src_pos)? {
+            // The last 48 KiB of decompressed data are needed for the matches of the following instructions.
+            let discard = self.hist.len().saturating_sub(M4_MAX_OFFSET);
+
+            if discard >= COMPACT_LEN {
+                self.hist.drain(..discard);
+            }
+        }
+
+        self.src.drain(..src_pos);
+
+        // Output the data which can no longer be modified.
+        let len = self.litp.unwrap_or(self.out.len());
+
+        dst.extend_from_slice(&self.out[..len]);
+        self.out.drain(..len);
+
+        if let Some(litp) = &mut self.litp {
+            *litp = 0;
+        }
+
+        Ok(())
+    }
+
+    /// End the compressed data, appending the remaining optimized data to `dst`.
+    ///
+    /// #### Errors
+    ///
+    /// This function returns an error if the compressed data did not end with a complete block.
+    pub fn finish(&mut self, dst: &mut Vec<u8>) -> Result<(), DecompressError> {
+        if !self.src.is_empty() || self.state != State::Start {
+            return Err(DecompressError::InvalidInput);
+        }
+
+        dst.append(&mut self.out);
+        self.litp = None;
+
+        Ok(())
+    }
+
+    /// Process the instruction at `src_pos`, including its literals, and any literal run it is merged with.
+    ///
+    /// Returns `false` if more data is needed to process the instruction.
+    fn step(&mut self, src_pos: &mut usize) -> Result<bool, DecompressError> {
+        let src = &self.src[..];
+        let insn_pos = *src_pos;
+        let mut pos = *src_pos;
+
+        let insn = match decode_insn(src, &mut pos, self.state) {
+            Ok(insn) => insn,
+            Err(Truncated) => return Ok(false),
+        };
+
+        let (len, dist, lit) = match insn {
+            Insn::Literal { len } => {
+                if pos + len > src.len() {
+                    return Ok(false);
+                }
+
+                // The literal run at the start of a block can not be merged.
+                self.litp = if self.state == State::Start && src[insn_pos] > 17 {
+                    None
+                } else {
+                    Some(self.out.len())
+                };
+
+                self.lit = len;
+
+                self.out.extend_from_slice(&src[insn_pos..pos + len]);
+                self.hist.extend_from_slice(&src[pos..pos + len]);
+
+                *src_pos = pos + len;
+                self.state = self.state.next(&insn);
+
+                return Ok(true);
+            }
+            Insn::Match { len, dist, lit } => (len, dist, lit),
+            Insn::End => {
+                self.out.extend_from_slice(&src[insn_pos..pos]);
+                self.litp = None;
+
+                *src_pos = pos;
+                self.state = State::Start;
+
+                return Ok(true);
+            }
+        };
+
+        if dist > self.hist.len() {
+            return Err(DecompressError::InvalidInput);
+        }
+
+        if pos + lit > src.len() {
+            return Ok(false);
+        }
+
+        let insn_len = pos - insn_pos;
+        let op = self.rewrite(src, insn, src[insn_pos], insn_len, pos);
+
+        if op == Some(Rewrite::Pending) {
+            return Ok(false);
+        }
+
+        let hist_len = self.hist.len();
+        self.hist.resize(hist_len + len, 0);
+        copy_match(&mut self.hist, hist_len, dist, len);
+
+        let decoded = &self.hist[hist_len..];
+
+        match op {
+            None => {
+                self.out.extend_from_slice(&src[insn_pos..pos + lit]);
+                self.hist.extend_from_slice(&src[pos..pos + lit]);
+
+                // The length of the trailing literals is stored in the second to last byte of a match.
+                self.litp = Some(self.out.len() - lit - 2);
+                self.lit = lit;
+
+                pos += lit;
+            }
+            Some(Rewrite::Extend) => {
+                // Replace the match by literals which extend the trailing literals of the previous match.
+                let litp = self.litp.unwrap();
+
+                self.lit += len;
+                self.out[litp] = (self.out[litp] & !3) | self.lit as u8;
+                self.out.extend_from_slice(decoded);
+            }
+            Some(Rewrite::Merge { run_len }) => {
+                // Replace the trailing literals of the previous match, the match, and the following literal run,
+                // by a single literal run.
+                let litp = self.litp.unwrap();
+                let lits = self.out.split_off(litp + 2);
+
+                self.out[litp] &= !3;
+                self.out.push((self.lit + len + run_len - 3) as u8);
+                self.out.extend_from_slice(&lits);
+                self.out.extend_from_slice(decoded);
+
+                self.litp = Some(litp + 2);
+                self.lit += len + run_len;
+
+                pos = self.copy_run(pos, run_len);
+            }
+            Some(Rewrite::MergeRun { run_len }) => {
+                // Replace the match and the following literal run by literals of the previous literal run.
+                let litp = self.litp.unwrap();
+
+                self.lit += len + run_len;
+                self.out[litp] = (self.lit - 3) as u8;
+                self.out.extend_from_slice(decoded);
+
+                pos = self.copy_run(pos, run_len);
+            }
+            Some(Rewrite::Pending) => unreachable!(),
+        }
+
+        *src_pos = pos;
+        self.state = match op {
+            Some(Rewrite::Merge { .. } | Rewrite::MergeRun { .. }) => State::C,
+            _ => self.state.next(&insn),
+        };
+
+        Ok(true)
+    }
+
+    /// Returns how the match at the end of `src[..pos]` is rewritten, if at all.
+    fn rewrite(
+        &self,
+        src: &[u8],
+        insn: Insn,
+        op: u8,
+        insn_len: usize,
+        pos: usize,
+    ) -> Option<Rewrite> {
+        let Insn::Match { len, lit: 0, .. } = insn else {
+            return None;
+        };
+
+        self.litp?;
+
+        let lit = self.lit;
+
+        let (extend, merge) = if op < 16 {
+            // Only a 2 byte match after 1-3 literals can be rewritten.
+            if self.state != State::B {
+                return None;
+            }
+
+            (lit == 1, true)
+        } else if op < 64 {
+            if len != 3 || insn_len != 3 {
+                return None;
+            }
+
+            (lit == 0, lit <= 3)
+        } else {
+            if len != 3 {
+                return None;
+            }
+
+            (false, lit > 3)
+        };
+
+        let Some(&next) = src.get(pos) else {
+            return Some(Rewrite::Pending);
+        };
+
+        if next >= 16 {
+            return extend.then_some(Rewrite::Extend);
+        }
+
+        let run_len = next as usize + 3;
+
+        if !merge || next == 0 || lit + len + run_len >= 19 {
+            return None;
+        }
+
+        if pos + 1 + run_len > src.len() {
+            return Some(Rewrite::Pending);
+        }
+
+        if op >= 64 {
+            Some(Rewrite::MergeRun { run_len })
+        } else {
+            Some(Rewrite::Merge { run_len })
+        }
+    }
+
+    /// Copy the literal run at `pos`, which is merged into the current literal run, and return the position after it.
+    fn copy_run(&mut self, pos: usize, run_len: usize) -> usize {
+        let run = &self.src[pos + 1..pos + 1 + run_len];
+
+        self.out.extend_from_slice(run);
+        self.hist.extend_from_slice(run);
+
+        pos + 1 + run_len
+    }
+}
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rewrite of a short match following a literal run.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Rewrite {
+    /// More data is needed to decide.
+    Pending,
+    /// Append the match as literals to the trailing literals of the previous match.
+    Extend,
+    /// Merge the trailing literals of the previous match, the match, and the following literal run.
+    Merge { run_len: usize },
+    /// Merge the match and the following literal run into the previous literal run.
+    MergeRun { run_len: usize },
+}
+
+/// Streaming optimizer stage, which optimizes compressed data written to it with an [`Optimizer`],
+/// and writes the optimized data to the underlying writer.
+///
+/// Can be used as the underlying writer of an [`Encoder`](crate::Encoder),
+/// to optimize the compressed data while it is written.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Read, Write};
+///
+/// let writer = lzo1x::OptimizeWriter::new(Vec::new());
+/// let mut encoder = lzo1x::Encoder::new(writer, lzo1x::CompressLevel::default());
+///
+/// for _ in 0..100 {
+///     encoder.write_all(b"hello world").unwrap();
+/// }
+///
+/// let optimized = encoder.finish().unwrap().finish().unwrap();
+///
+/// let mut decompressed = Vec::new();
+/// lzo1x::Decoder::new(optimized.as_slice()).read_to_end(&mut decompressed).unwrap();
+///
+/// assert_eq!(decompressed, b"hello world".repeat(100));
+/// ```
+#[cfg(feature = "std")]
+pub struct OptimizeWriter<W: Write> {
+    inner: Option<W>,
+    optimizer: Optimizer,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> OptimizeWriter<W> {
+    /// Create a new `OptimizeWriter` that writes optimized data to the given `inner` writer.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner: Some(inner),
+            optimizer: Optimizer::new(),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().unwrap()
+    }
+
+    /// Write the remaining optimized data, and return the underlying writer.
+    ///
+    /// #### Errors
+    ///
+    /// This function returns an error if the written data did not end with a complete block,
+    /// or if writing to the underlying writer fails.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.end()?;
+
+        Ok(self.inner.take().unwrap())
+    }
+
+    fn end(&mut self) -> io::Result<()> {
+        self.optimizer
+            .finish(&mut self.buf)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+        self.write_buf()?;
+
+        self.inner.as_mut().unwrap().flush()
+    }
+
+    fn write_buf(&mut self) -> io::Result<()> {
+        self.inner.as_mut().unwrap().write_all(&self.buf)?;
+        self.buf.clear();
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for OptimizeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.optimizer
+            .update(buf, &mut self.buf)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+        self.write_buf()?;
+
+        Ok(buf.len())
+    }
+
+    /// Flush the underlying writer.
+    ///
+    /// The last literal run of the written data may be held back until more data is written,
+    /// or until [`finish`](OptimizeWriter::finish) is called.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Drop for OptimizeWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.end();
+        }
+    }
+}
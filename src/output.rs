@@ -0,0 +1,70 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::try_alloc::try_resize;
+
+/// Buffer to which a compressor appends the compressed data.
+pub trait Output {
+    /// Returns the length of the buffer.
+    fn len(&self) -> usize;
+
+    /// Ensure the buffer is at least `len` bytes long.
+    ///
+    /// Returns an error if memory allocation fails.
+    fn grow(&mut self, len: usize) -> Result<(), ()>;
+
+    /// Returns the whole buffer.
+    fn buf(&mut self) -> &mut [u8];
+
+    /// Shorten the buffer to `len` bytes.
+    fn truncate(&mut self, len: usize);
+}
+
+#[cfg(feature = "alloc")]
+impl Output for Vec<u8> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn grow(&mut self, len: usize) -> Result<(), ()> {
+        if self.len() < len {
+            try_resize(self, len, 0).map_err(|_| ())?;
+        }
+
+        Ok(())
+    }
+
+    fn buf(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len);
+    }
+}
+
+/// Fixed size buffer, which must be large enough for the worst case compressed length.
+pub struct SliceOutput<'a> {
+    pub buf: &'a mut [u8],
+    pub len: usize,
+}
+
+impl Output for SliceOutput<'_> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn grow(&mut self, _len: usize) -> Result<(), ()> {
+        // Writing past the end of the buffer panics.
+        Ok(())
+    }
+
+    fn buf(&mut self) -> &mut [u8] {
+        self.buf
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.len = len;
+    }
+}
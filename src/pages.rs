@@ -0,0 +1,264 @@
+use core::ops::ControlFlow;
+
+use alloc::vec::Vec;
+
+use crate::{
+    Algorithm, CompressError, CompressLevel, CompressOptions, DecompressError, compress_1,
+    decompress,
+    swd::{OwnedWorkspace, Workspace999},
+    try_alloc::try_resize,
+};
+
+/// Compress the given `src`, which consists of independent pages of `page_len` bytes,
+/// with the given compression `level`.
+///
+/// This is a shorthand for compressing with a new [`PageCompressor`].
+///
+/// #### Panics
+///
+/// Panics if `page_len` is zero, or if the length of `src` is not a multiple of `page_len`.
+///
+/// # Examples
+///
+/// ```
+/// let data = [0xaa; 4096 * 4];
+/// let pages = lzo1x::compress_pages(&data, 4096, lzo1x::CompressLevel::default());
+///
+/// assert_eq!(pages.len(), 4);
+///
+/// let mut page = [0; 4096];
+/// pages.decompress_page(2, &mut page).unwrap();
+///
+/// assert_eq!(page, data[..4096]);
+/// ```
+pub fn compress_pages(src: &[u8], page_len: usize, level: CompressLevel) -> CompressedPages {
+    PageCompressor::new(page_len, level).compress_pages(src)
+}
+
+/// Compressor for many small, independent pages of equal length, such as 4 KiB memory pages.
+///
+/// The dictionary of the compressor is allocated once and reused for every page,
+/// which avoids the setup cost of [`compress`](crate::compress) for every page.
+/// The compressed data of every page is identical to the data returned by [`compress`](crate::compress),
+/// except for pages which do not compress, which are stored uncompressed instead.
+///
+/// # Examples
+///
+/// ```
+/// let data: Vec<u8> = (0..4096u32).flat_map(|i| (i / 7).to_le_bytes()).collect();
+///
+/// let mut compressor = lzo1x::PageCompressor::new(4096, lzo1x::CompressLevel::default());
+/// let pages = compressor.compress_pages(&data);
+///
+/// assert_eq!(pages.len(), 4);
+/// assert!(!pages.is_stored(0));
+///
+/// let mut decompressed = vec![0; data.len()];
+///
+/// for (i, page) in decompressed.chunks_mut(4096).enumerate() {
+///     pages.decompress_page(i, page).unwrap();
+/// }
+///
+/// assert_eq!(decompressed, data);
+/// ```
+pub struct PageCompressor {
+    page_len: usize,
+    options: CompressOptions,
+    lzo1x_1: Option<compress_1::Compressor>,
+    dict: Vec<u8>,
+    workspace: Option<OwnedWorkspace>,
+    buf: Vec<u8>,
+}
+
+impl PageCompressor {
+    /// Create a new `PageCompressor` for pages of `page_len` bytes, with the given compression `level`.
+    ///
+    /// #### Panics
+    ///
+    /// Panics if `page_len` is zero.
+    pub fn new(page_len: usize, level: CompressLevel) -> Self {
+        assert!(page_len > 0, "page length must not be zero");
+
+        Self {
+            page_len,
+            options: CompressOptions::new(level),
+            lzo1x_1: None,
+            dict: Vec::new(),
+            workspace: None,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Returns the length of the pages.
+    pub fn page_len(&self) -> usize {
+        self.page_len
+    }
+
+    /// Compress the given `src`, which consists of independent pages.
+    ///
+    /// #### Panics
+    ///
+    /// Panics if the length of `src` is not a multiple of the page length, or if memory allocation fails.
+    pub fn compress_pages(&mut self, src: &[u8]) -> CompressedPages {
+        assert!(
+            src.len() % self.page_len == 0,
+            "source length must be a multiple of the page length"
+        );
+
+        let mut pages = CompressedPages {
+            page_len: self.page_len,
+            data: Vec::new(),
+            offsets: Vec::with_capacity(src.len() / self.page_len + 1),
+        };
+
+        pages.offsets.push(0);
+
+        for page in src.chunks(self.page_len) {
+            match self.compress_page(page, &mut pages.data) {
+                Ok(()) => {}
+                Err(CompressError::Cancelled) => unreachable!(),
+                Err(CompressError::OutOfMemory) => panic!("memory allocation failed"),
+            }
+
+            pages.offsets.push(pages.data.len());
+        }
+
+        pages
+    }
+
+    /// Compress the given `page`, appending the compressed data to `dst`,
+    /// or the page itself if it does not compress.
+    fn compress_page(&mut self, page: &[u8], dst: &mut Vec<u8>) -> Result<(), CompressError> {
+        // The compressors expect to start at the beginning of their output.
+        let buf = &mut self.buf;
+        buf.clear();
+
+        let mut callback = |_, _| ControlFlow::Continue(());
+
+        match self.options.algorithm() {
+            Algorithm::Lzo1x1(compressor) => {
+                if self.dict.is_empty() {
+                    try_resize(&mut self.dict, compressor.dict_len(), 0)
+                        .map_err(|_| CompressError::OutOfMemory)?;
+                }
+
+                // The compressor is kept between pages, such that the dictionary is not cleared for every page.
+                let compressor = self.lzo1x_1.get_or_insert(compressor);
+                compressor.reset();
+
+                compressor.compress(page, buf, true, &mut self.dict, &mut callback)?;
+            }
+            Algorithm::Lzo1x999(mut compressor) => {
+                if self.workspace.is_none() {
+                    let workspace =
+                        OwnedWorkspace::new().map_err(|_| CompressError::OutOfMemory)?;
+
+                    self.workspace = Some(workspace);
+                }
+
+                let workspace: &mut Workspace999 = self.workspace.as_mut().unwrap();
+
+                compressor.compress(page, buf, true, || Ok(workspace), &mut callback)?;
+            }
+        }
+
+        let stored = if buf.len() < page.len() {
+            &buf[..]
+        } else {
+            page
+        };
+
+        dst.try_reserve(stored.len())
+            .map_err(|_| CompressError::OutOfMemory)?;
+        dst.extend_from_slice(stored);
+
+        Ok(())
+    }
+}
+
+/// Independently compressed pages of equal length, packed into a single buffer with an offset table.
+///
+/// Every page is either compressed, or stored uncompressed if compression did not reduce its length.
+/// A stored page is recognized by its length, which is exactly the page length.
+pub struct CompressedPages {
+    page_len: usize,
+    data: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl CompressedPages {
+    /// Returns the number of pages.
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Returns `true` if there are no pages.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the length of the pages.
+    pub fn page_len(&self) -> usize {
+        self.page_len
+    }
+
+    /// Returns the packed data of all pages.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the offset table, which contains the start offset of every page in the packed data,
+    /// followed by the total length of the packed data.
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    /// Returns the stored data of the page at the given `index`.
+    ///
+    /// #### Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn page(&self, index: usize) -> &[u8] {
+        &self.data[self.offsets[index]..self.offsets[index + 1]]
+    }
+
+    /// Returns `true` if the page at the given `index` is stored uncompressed.
+    ///
+    /// #### Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn is_stored(&self, index: usize) -> bool {
+        self.page(index).len() == self.page_len
+    }
+
+    /// Decompress the page at the given `index` into the given `dst`.
+    ///
+    /// #### Errors
+    ///
+    /// This function returns an error if the page does not contain valid compressed data,
+    /// or if the given `dst` is not exactly as long as a page.
+    ///
+    /// #### Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn decompress_page(&self, index: usize, dst: &mut [u8]) -> Result<(), DecompressError> {
+        if dst.len() != self.page_len {
+            return Err(DecompressError::OutputLength);
+        }
+
+        let page = self.page(index);
+
+        if page.len() == self.page_len {
+            dst.copy_from_slice(page);
+
+            Ok(())
+        } else {
+            decompress(page, dst)
+        }
+    }
+
+    /// Returns the packed data and the offset table.
+    pub fn into_parts(self) -> (Vec<u8>, Vec<usize>) {
+        (self.data, self.offsets)
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use crate::{
+    CompressLevel, FrameError,
+    frame::{clamp_block_size, encode_block, read_blocks, write_header, write_trailer},
+};
+
+/// Compress the given `src` into a frame on multiple threads, with the given compression `level` and `block_size`.
+///
+/// The blocks of the frame are compressed independently on `threads` threads,
+/// or on as many threads as available if `threads` is 0.
+/// The result is identical to the result of [`compress_frame`](crate::compress_frame), independent of the number of threads.
+///
+/// The given `block_size` is clamped to the range 1 B - 1 GiB.
+///
+/// # Examples
+///
+/// ```
+/// let data = &[0xaa; 100_000];
+/// let frame = lzo1x::compress_frame_parallel(data, lzo1x::CompressLevel::default(), 10_000, 4);
+///
+/// assert_eq!(frame, lzo1x::compress_frame(data, lzo1x::CompressLevel::default(), 10_000));
+/// ```
+pub fn compress_frame_parallel(
+    src: &[u8],
+    level: CompressLevel,
+    block_size: usize,
+    threads: usize,
+) -> Vec<u8> {
+    let block_size = clamp_block_size(block_size);
+
+    let blocks: Vec<&[u8]> = src.chunks(block_size).collect();
+    let threads = thread_count(threads).min(blocks.len()).max(1);
+
+    // Blocks are handed out to the threads one by one, so that slower blocks do not stall other threads.
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, u32, Vec<u8>)> = thread::scope(|s| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut results = Vec::new();
+
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+
+                        let Some(block) = blocks.get(index) else {
+                            break results;
+                        };
+
+                        let mut dst = Vec::new();
+                        let stored_len = encode_block(block, level, &mut dst);
+
+                        results.push((index, stored_len, dst));
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    results.sort_unstable_by_key(|&(index, _, _)| index);
+
+    let mut dst = Vec::with_capacity(results.iter().map(|(_, _, block)| block.len()).sum());
+    let mut table = Vec::with_capacity(results.len());
+
+    write_header(&mut dst, block_size);
+
+    for ((_, stored_len, block), src) in results.into_iter().zip(&blocks) {
+        dst.extend_from_slice(&block);
+        table.push((stored_len, src.len() as u32));
+    }
+
+    write_trailer(&mut dst, &table, src.len() as u64);
+
+    dst
+}
+
+/// Decompress the given frame in `src` on multiple threads.
+///
+/// The blocks of the frame are decompressed independently on `threads` threads,
+/// or on as many threads as available if `threads` is 0.
+///
+/// #### Errors
+///
+/// This function returns an error if the given `src` does not contain a valid frame,
+/// or if the checksum of any of the blocks does not match.
+/// If multiple blocks are invalid, the error of the first invalid block is returned.
+///
+/// # Examples
+///
+/// ```
+/// let data = &[0xaa; 100_000];
+/// let frame = lzo1x::compress_frame(data, lzo1x::CompressLevel::default(), 10_000);
+///
+/// assert_eq!(lzo1x::decompress_frame_parallel(&frame, 4).unwrap(), data);
+/// ```
+pub fn decompress_frame_parallel(src: &[u8], threads: usize) -> Result<Vec<u8>, FrameError> {
+    let blocks = read_blocks(src)?;
+
+    // The decompressed lengths of the blocks are checked against their stored lengths,
+    // so the decompressed length is bounded by the length of the frame.
+    let mut dst = vec![0; blocks.iter().map(|(header, _)| header.len).sum()];
+
+    let mut jobs = Vec::with_capacity(blocks.len());
+    let mut rest = dst.as_mut_slice();
+
+    for (header, data) in &blocks {
+        let (block_dst, next) = rest.split_at_mut(header.len);
+
+        jobs.push((header, *data, block_dst));
+        rest = next;
+    }
+
+    if jobs.is_empty() {
+        return Ok(dst);
+    }
+
+    // Every thread decompresses a contiguous range of blocks, so the errors are returned in block order.
+    let threads = thread_count(threads).min(jobs.len());
+    let jobs_per_thread = jobs.len().div_ceil(threads);
+
+    thread::scope(|s| {
+        let workers: Vec<_> = jobs
+            .chunks_mut(jobs_per_thread)
+            .map(|jobs| {
+                s.spawn(move || {
+                    for (header, data, dst) in jobs {
+                        header.decode(data, dst)?;
+                    }
+
+                    Ok(())
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().unwrap())
+    })?;
+
+    Ok(dst)
+}
+
+fn thread_count(threads: usize) -> usize {
+    if threads == 0 {
+        thread::available_parallelism().map_or(1, |threads| threads.get())
+    } else {
+        threads
+    }
+}
@@ -0,0 +1,203 @@
+use core::ops::ControlFlow;
+
+use alloc::vec::Vec;
+
+use crate::{
+    CompressError, CompressLevel, Compressor, DecompressError, Optimizer,
+    config::M4_MAX_OFFSET,
+    decompress::{Insn, State, copy_match, decode_insn},
+};
+
+// Number of discarded bytes at the start of a buffer before the buffer is compacted.
+const COMPACT_LEN: usize = 0x10000;
+
+// Number of decompressed bytes passed to the compressor at once.
+const CHUNK_LEN: usize = 0x10000;
+
+/// Recompress the compressed data given in `src` with the given compression `level`.
+///
+/// The data is decompressed and compressed again in chunks, such that only a window of the decompressed data
+/// is held in memory at any time. The given `src` may consist of multiple blocks, such as the data written by an
+/// [`Encoder`](crate::Encoder) with flushes.
+///
+/// The recompressed data is identical to the data returned by [`compress`](crate::compress) for the decompressed data.
+///
+/// #### Errors
+///
+/// This function returns an error if the given `src` does not contain valid compressed data.
+///
+/// #### Panics
+///
+/// Panics if memory allocation fails.
+///
+/// # Examples
+///
+/// ```
+/// let data: Vec<u8> = (0..10_000u32).flat_map(|i| (i / 7).to_le_bytes()).collect();
+/// let compressed = lzo1x::compress(&data, lzo1x::CompressLevel::new(1));
+///
+/// let recompressed = lzo1x::recompress(&compressed, lzo1x::CompressLevel::new(12)).unwrap();
+///
+/// assert_eq!(recompressed, lzo1x::compress(&data, lzo1x::CompressLevel::new(12)));
+/// ```
+pub fn recompress(src: &[u8], level: CompressLevel) -> Result<Vec<u8>, DecompressError> {
+    recompress_impl(src, level, None)
+}
+
+/// Recompress the compressed data given in `src` with the given compression `level`,
+/// and optimize the recompressed data in terms of decompression speed.
+///
+/// This is equivalent to calling [`optimize`](crate::optimize) on the data returned by [`recompress`],
+/// but the recompressed data is optimized while it is produced, with an [`Optimizer`].
+///
+/// #### Errors
+///
+/// This function returns an error if the given `src` does not contain valid compressed data.
+///
+/// #### Panics
+///
+/// Panics if memory allocation fails.
+///
+/// # Examples
+///
+/// ```
+/// let data: Vec<u8> = (0..10_000u32).flat_map(|i| (i / 7).to_le_bytes()).collect();
+/// let compressed = lzo1x::compress(&data, lzo1x::CompressLevel::new(1));
+///
+/// let recompressed = lzo1x::recompress_optimized(&compressed, lzo1x::CompressLevel::new(12)).unwrap();
+///
+/// let mut expected = lzo1x::compress(&data, lzo1x::CompressLevel::new(12));
+/// lzo1x::optimize(&mut expected, data.len());
+///
+/// assert_eq!(recompressed, expected);
+/// ```
+pub fn recompress_optimized(src: &[u8], level: CompressLevel) -> Result<Vec<u8>, DecompressError> {
+    recompress_impl(src, level, Some(Optimizer::new()))
+}
+
+fn recompress_impl(
+    src: &[u8],
+    level: CompressLevel,
+    mut optimizer: Option<Optimizer>,
+) -> Result<Vec<u8>, DecompressError> {
+    let mut compressor = Compressor::new(level);
+
+    // Decompressed data, which is both the history of the decoder and the input of the compressor.
+    let mut buf = Vec::new();
+    let mut buf_compressed = 0;
+
+    let mut compressed = Vec::new();
+    let mut dst = Vec::new();
+
+    let mut src_pos = 0;
+    let mut state = State::Start;
+    let mut ended = false;
+
+    while src_pos < src.len() || state != State::Start {
+        let insn =
+            decode_insn(src, &mut src_pos, state).map_err(|_| DecompressError::InvalidInput)?;
+
+        let lit = match insn {
+            Insn::Literal { len } => len,
+            Insn::Match { len, dist, lit } => {
+                let buf_len = buf.len();
+
+                if dist > buf_len {
+                    return Err(DecompressError::InvalidInput);
+                }
+
+                buf.resize(buf_len + len, 0);
+                copy_match(&mut buf, buf_len, dist, len);
+
+                lit
+            }
+            Insn::End => {
+                ended = true;
+
+                0
+            }
+        };
+
+        let literals = src
+            .get(src_pos..src_pos + lit)
+            .ok_or(DecompressError::InvalidInput)?;
+
+        buf.extend_from_slice(literals);
+        src_pos += lit;
+
+        state = state.next(&insn);
+
+        if buf.len() - buf_compressed >= CHUNK_LEN {
+            compress_part(&mut compressor, &buf, &mut compressed, false);
+            output(&mut compressed, &mut dst, optimizer.as_mut(), false);
+
+            buf_compressed = buf.len();
+
+            // Keep enough history for the compressor, and for matches of the following instructions.
+            let retain_from = compressor
+                .retain_from()
+                .min(buf.len().saturating_sub(M4_MAX_OFFSET));
+
+            if retain_from >= COMPACT_LEN {
+                buf.drain(..retain_from);
+                compressor.shift(retain_from);
+                buf_compressed -= retain_from;
+            }
+        }
+    }
+
+    // Empty input does not contain an end of stream.
+    if !ended {
+        return Err(DecompressError::InvalidInput);
+    }
+
+    compress_part(&mut compressor, &buf, &mut compressed, true);
+    output(&mut compressed, &mut dst, optimizer.as_mut(), true);
+
+    Ok(dst)
+}
+
+fn compress_part(compressor: &mut Compressor, src: &[u8], dst: &mut Vec<u8>, finish: bool) {
+    match compressor.compress(src, dst, finish, &mut |_, _| ControlFlow::Continue(())) {
+        Ok(()) => {}
+        Err(CompressError::Cancelled) => unreachable!(),
+        Err(CompressError::OutOfMemory) => panic!("memory allocation failed"),
+    }
+}
+
+/// Move the `compressed` data which can no longer change to `dst`, optimizing it if an `optimizer` is given.
+fn output(
+    compressed: &mut Vec<u8>,
+    dst: &mut Vec<u8>,
+    optimizer: Option<&mut Optimizer>,
+    finish: bool,
+) {
+    // The last 2 bytes of an open block can still be modified by the compressor.
+    let len = if finish {
+        compressed.len()
+    } else {
+        compressed.len().saturating_sub(2)
+    };
+
+    let Some(optimizer) = optimizer else {
+        dst.extend_from_slice(&compressed[..len]);
+        compressed.drain(..len);
+
+        return;
+    };
+
+    let result = optimizer.update(&compressed[..len], dst).and_then(|()| {
+        if finish {
+            optimizer.finish(dst)
+        } else {
+            Ok(())
+        }
+    });
+
+    // The data written by the compressor is always valid.
+    if result.is_err() {
+        unreachable!();
+    }
+
+    compressed.drain(..len);
+}
@@ -0,0 +1,263 @@
+use core::fmt::{self, Display, Formatter};
+
+use crate::{
+    DecompressError,
+    config::M4_MAX_OFFSET,
+    decompress::{Insn, State, Truncated, decode_insn},
+};
+
+/// Decompressor with bounded memory usage, which keeps the decompressed data in a caller-provided ring buffer.
+///
+/// The decompressed data is passed to an output callback in pieces of a fixed page length,
+/// which makes it suitable for writing directly to flash memory on targets without an allocator.
+/// Apart from the ring buffer, the decompressor only uses a small, fixed amount of stack space.
+///
+/// The decompressed data is identical to the data returned by [`decompress`](crate::decompress).
+///
+/// # Examples
+///
+/// ```
+/// let data: Vec<u8> = (0..100_000u32).flat_map(|i| (i / 7).to_le_bytes()).collect();
+/// let compressed = lzo1x::compress(&data, lzo1x::CompressLevel::default());
+///
+/// let mut ring = [0; 48 * 1024];
+/// let mut decoder = lzo1x::RingDecoder::new(&mut ring, 4096);
+///
+/// let mut decompressed = Vec::new();
+///
+/// let len = decoder
+///     .decompress(&compressed, |page| {
+///         decompressed.extend_from_slice(page);
+///
+///         Ok::<_, ()>(())
+///     })
+///     .unwrap();
+///
+/// assert_eq!(len, data.len());
+/// assert_eq!(decompressed, data);
+/// ```
+pub struct RingDecoder<'a> {
+    ring: &'a mut [u8],
+    page_len: usize,
+}
+
+impl<'a> RingDecoder<'a> {
+    /// Minimum length of the ring buffer, which is the maximum match distance. (49151)
+    pub const MIN_RING_LEN: usize = M4_MAX_OFFSET;
+
+    /// Create a new `RingDecoder` instance, which uses the given `ring` buffer,
+    /// and which outputs the decompressed data in pieces of `page_len` bytes.
+    ///
+    /// A ring buffer of 48 KiB supports any power of two page length up to 16 KiB.
+    ///
+    /// #### Panics
+    ///
+    /// Panics if the length of the given `ring` buffer is smaller than [`MIN_RING_LEN`](Self::MIN_RING_LEN),
+    /// or if it is not a multiple of the given `page_len`.
+    pub fn new(ring: &'a mut [u8], page_len: usize) -> Self {
+        assert!(
+            ring.len() >= Self::MIN_RING_LEN,
+            "ring buffer must be at least {} bytes",
+            Self::MIN_RING_LEN
+        );
+
+        assert!(
+            page_len > 0 && ring.len() % page_len == 0,
+            "ring buffer length must be a multiple of the page length"
+        );
+
+        Self { ring, page_len }
+    }
+
+    /// Decompress the given `src`, passing the decompressed data to the given `output` callback,
+    /// and returning the length of the decompressed data.
+    ///
+    /// The `output` callback is called with pieces of exactly the page length, except for the last piece,
+    /// which can be shorter. Returning an error from the `output` callback aborts the decompression.
+    ///
+    /// #### Errors
+    ///
+    /// This function returns an error if the given `src` does not contain valid compressed data,
+    /// or if the `output` callback returned an error. Any data passed to the `output` callback
+    /// before the error occurred should be discarded.
+    pub fn decompress<E, F>(&mut self, src: &[u8], output: F) -> Result<usize, RingError<E>>
+    where
+        F: FnMut(&[u8]) -> Result<(), E>,
+    {
+        let mut ring = Ring {
+            buf: self.ring,
+            page_len: self.page_len,
+            pos: 0,
+            len: 0,
+            output,
+        };
+
+        let mut src_pos = 0;
+        let mut state = State::Start;
+
+        loop {
+            let insn = match decode_insn(src, &mut src_pos, state) {
+                Ok(insn) => insn,
+                Err(Truncated) => return Err(RingError::Decompress(DecompressError::InvalidInput)),
+            };
+
+            let lit_len = match insn {
+                Insn::Literal { len } => len,
+                Insn::Match { len, dist, lit } => {
+                    if dist > ring.len {
+                        return Err(RingError::Decompress(DecompressError::InvalidInput));
+                    }
+
+                    ring.copy_match(dist, len)?;
+
+                    lit
+                }
+                Insn::End => break,
+            };
+
+            if src_pos + lit_len > src.len() {
+                return Err(RingError::Decompress(DecompressError::InvalidInput));
+            }
+
+            ring.copy_literal(&src[src_pos..src_pos + lit_len])?;
+            src_pos += lit_len;
+
+            state = state.next(&insn);
+        }
+
+        // Ensure the source buffer was completely consumed.
+        if src_pos != src.len() {
+            return Err(RingError::Decompress(DecompressError::InvalidInput));
+        }
+
+        ring.finish()?;
+
+        Ok(ring.len)
+    }
+
+    /// Decompress the given `src`, writing the decompressed data to the given `writer`,
+    /// and returning the length of the decompressed data.
+    ///
+    /// The decompressed data is written in pieces of exactly the page length, except for the last piece,
+    /// which can be shorter.
+    ///
+    /// #### Errors
+    ///
+    /// This function returns an error if the given `src` does not contain valid compressed data,
+    /// or if writing to the given `writer` failed.
+    #[cfg(feature = "embedded-io")]
+    pub fn decompress_to<W: embedded_io::Write>(
+        &mut self,
+        src: &[u8],
+        writer: &mut W,
+    ) -> Result<usize, RingError<W::Error>> {
+        self.decompress(src, |page| writer.write_all(page))
+    }
+}
+
+/// Ring buffer state during a single decompression.
+struct Ring<'a, F> {
+    buf: &'a mut [u8],
+    page_len: usize,
+    /// Position in the ring buffer, which is always in the range `0..buf.len()`.
+    pos: usize,
+    /// Total length of the decompressed data.
+    len: usize,
+    output: F,
+}
+
+impl<E, F: FnMut(&[u8]) -> Result<(), E>> Ring<'_, F> {
+    fn copy_literal(&mut self, mut src: &[u8]) -> Result<(), RingError<E>> {
+        while !src.is_empty() {
+            let n = src.len().min(self.page_remaining());
+
+            self.buf[self.pos..self.pos + n].copy_from_slice(&src[..n]);
+            src = &src[n..];
+
+            self.advance(n)?;
+        }
+
+        Ok(())
+    }
+
+    /// Copy `len` bytes from `dist` bytes back, where `dist` is at most the length of the ring buffer.
+    fn copy_match(&mut self, dist: usize, mut len: usize) -> Result<(), RingError<E>> {
+        while len > 0 {
+            let match_pos = (self.pos + self.buf.len() - dist) % self.buf.len();
+
+            // Copying at most `dist` bytes at once ensures that overlapping matches repeat correctly.
+            let n = len
+                .min(dist)
+                .min(self.page_remaining())
+                .min(self.buf.len() - match_pos);
+
+            self.buf.copy_within(match_pos..match_pos + n, self.pos);
+            len -= n;
+
+            self.advance(n)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of bytes until the end of the current page.
+    fn page_remaining(&self) -> usize {
+        self.page_len - (self.pos % self.page_len)
+    }
+
+    /// Advance the position by `n` bytes, outputting the current page if it was completed.
+    fn advance(&mut self, n: usize) -> Result<(), RingError<E>> {
+        self.pos += n;
+        self.len += n;
+
+        if self.pos % self.page_len == 0 {
+            (self.output)(&self.buf[self.pos - self.page_len..self.pos])
+                .map_err(RingError::Output)?;
+
+            if self.pos == self.buf.len() {
+                self.pos = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Output the remaining partial page.
+    fn finish(&mut self) -> Result<(), RingError<E>> {
+        let page_pos = self.pos % self.page_len;
+
+        if page_pos > 0 {
+            (self.output)(&self.buf[self.pos - page_pos..self.pos]).map_err(RingError::Output)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Error that occured during decompression with a [`RingDecoder`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RingError<E> {
+    /// The decompression failed.
+    Decompress(DecompressError),
+    /// The output callback or writer returned an error.
+    Output(E),
+}
+
+impl<E: Display> Display for RingError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decompress(err) => Display::fmt(err, f),
+            Self::Output(err) => write!(f, "output error: {err}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for RingError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decompress(err) => Some(err),
+            Self::Output(err) => Some(err),
+        }
+    }
+}
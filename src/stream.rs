@@ -0,0 +1,431 @@
+use core::ops::ControlFlow;
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::{
+    CompressError, CompressLevel, CompressOptions, Compressor, DecompressError,
+    config::M4_MAX_OFFSET,
+    decompress::{Insn, State, copy_match, decode_insn},
+};
+
+// Number of discarded bytes at the start of a buffer before the buffer is compacted.
+const COMPACT_LEN: usize = 0x10000;
+
+// Number of bytes to read from the underlying reader at once.
+const READ_LEN: usize = 0x8000;
+
+/// Stateful compressor, which compresses data written to it in a streaming fashion.
+///
+/// Data can be written in many small calls to [`Write::write`],
+/// without losing the history needed to find matches in previously written data.
+///
+/// Without any call to [`Write::flush`], the written compressed data is identical to the data returned by [`compress`](crate::compress).
+/// Each call to [`Write::flush`] ends the current block, such that all data written so far can be decompressed.
+/// Matches in later blocks can still refer to data written before the flush,
+/// so the compressed data can only be decompressed with a [`Decoder`].
+///
+/// Data is accepted once it is buffered, and compressed data is written to the underlying writer
+/// by the next call to [`Write::write`] or [`Write::flush`], which returns any error that occurs.
+/// A failed call can be retried, as the written data is never written twice.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{Read, Write};
+///
+/// let mut encoder = lzo1x::Encoder::new(Vec::new(), lzo1x::CompressLevel::default());
+///
+/// for _ in 0..100 {
+///     encoder.write_all(b"hello world").unwrap();
+/// }
+///
+/// let compressed = encoder.finish().unwrap();
+///
+/// let mut decompressed = Vec::new();
+/// lzo1x::Decoder::new(compressed.as_slice()).read_to_end(&mut decompressed).unwrap();
+///
+/// assert_eq!(decompressed, b"hello world".repeat(100));
+/// ```
+pub struct Encoder<W: Write> {
+    inner: Option<W>,
+    compressor: Compressor,
+    src: Vec<u8>,
+    dst: Vec<u8>,
+    dst_pos: usize,
+    block_open: bool,
+    block_written: bool,
+}
+
+impl<W: Write> Encoder<W> {
+    /// Create a new `Encoder` that writes compressed data with the given compression `level` to the given `inner` writer.
+    pub fn new(inner: W, level: CompressLevel) -> Self {
+        Self::with_options(inner, CompressOptions::new(level))
+    }
+
+    /// Create a new `Encoder` that writes compressed data with the given compression `options` to the given `inner` writer.
+    ///
+    /// Matches can not refer back further than the maximum offset of the `options`, also across flushes.
+    pub fn with_options(inner: W, options: CompressOptions) -> Self {
+        Self {
+            inner: Some(inner),
+            compressor: Compressor::with_options(options),
+            src: Vec::new(),
+            dst: Vec::new(),
+            dst_pos: 0,
+            block_open: false,
+            block_written: false,
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().unwrap()
+    }
+
+    /// Compress all remaining data, end the stream, and return the underlying writer.
+    ///
+    /// #### Errors
+    ///
+    /// This function returns an error if writing to the underlying writer fails.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.end()?;
+
+        Ok(self.inner.take().unwrap())
+    }
+
+    fn end(&mut self) -> io::Result<()> {
+        if self.block_open || !self.block_written {
+            self.compress(true)?;
+        }
+
+        self.write_dst()?;
+
+        self.inner.as_mut().unwrap().flush()
+    }
+
+    fn compress(&mut self, finish: bool) -> io::Result<()> {
+        let mut callback = |_, _| ControlFlow::Continue(());
+
+        match self
+            .compressor
+            .compress(&self.src, &mut self.dst, finish, &mut callback)
+        {
+            Ok(()) => {}
+            Err(CompressError::Cancelled) => unreachable!(),
+            Err(CompressError::OutOfMemory) => return Err(ErrorKind::OutOfMemory.into()),
+        }
+
+        if finish {
+            self.block_open = false;
+            self.block_written = true;
+        }
+
+        // Keep enough history to be used as dictionary by the next block.
+        let retain_from = self
+            .compressor
+            .retain_from()
+            .min(self.src.len().saturating_sub(M4_MAX_OFFSET));
+
+        if retain_from >= COMPACT_LEN {
+            self.src.drain(..retain_from);
+            self.compressor.shift(retain_from);
+        }
+
+        Ok(())
+    }
+
+    /// Write the buffered compressed data to the underlying writer.
+    ///
+    /// The position is kept on error, such that a retry continues where the failed write stopped.
+    fn write_dst(&mut self) -> io::Result<()> {
+        // The last 2 bytes of an open block can still be modified by the compressor.
+        let len = if self.block_open {
+            self.dst.len().saturating_sub(2)
+        } else {
+            self.dst.len()
+        };
+
+        let inner = self.inner.as_mut().unwrap();
+
+        while self.dst_pos < len {
+            match inner.write(&self.dst[self.dst_pos..len]) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => self.dst_pos += n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        self.dst.drain(..len);
+        self.dst_pos = 0;
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        self.write_dst()?;
+
+        self.src.extend_from_slice(buf);
+        self.block_open = true;
+
+        self.compress(false)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.block_open {
+            self.compress(true)?;
+        }
+
+        self.write_dst()?;
+
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl<W: Write> Drop for Encoder<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.end();
+        }
+    }
+}
+
+/// Stateful decompressor, which decompresses data read from it in a streaming fashion.
+///
+/// Decompresses data written by an [`Encoder`], or by [`compress`](crate::compress).
+/// Blocks ended by [`Encoder`] flushes are decompressed using the history of the previous blocks.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+///
+/// let data = &[0xaa; 100];
+/// let compressed = lzo1x::compress(data, lzo1x::CompressLevel::default());
+///
+/// let mut decompressed = Vec::new();
+/// lzo1x::Decoder::new(compressed.as_slice()).read_to_end(&mut decompressed).unwrap();
+///
+/// assert_eq!(decompressed, data);
+/// ```
+pub struct Decoder<R: Read> {
+    inner: R,
+    src: Vec<u8>,
+    src_pos: usize,
+    dst: Vec<u8>,
+    dst_pos: usize,
+    state: State,
+    match_dist: usize,
+    match_len: usize,
+    lit: usize,
+    zeros: usize,
+    eof: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    /// Create a new `Decoder` that reads compressed data from the given `inner` reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            src: Vec::new(),
+            src_pos: 0,
+            dst: Vec::new(),
+            dst_pos: 0,
+            state: State::Start,
+            match_dist: 0,
+            match_len: 0,
+            lit: 0,
+            zeros: 0,
+            eof: false,
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Decompress more data into the destination buffer.
+    ///
+    /// Returns `false` if the end of the stream was reached.
+    fn decode(&mut self) -> io::Result<bool> {
+        // Keep enough history for matches of the following instructions.
+        let discard = self.dst_pos.saturating_sub(M4_MAX_OFFSET);
+
+        if discard >= COMPACT_LEN {
+            self.dst.drain(..discard);
+            self.dst_pos -= discard;
+        }
+
+        let dst_len = self.dst.len();
+
+        loop {
+            if self.dst.len() - dst_len >= READ_LEN {
+                return Ok(true);
+            }
+
+            if self.match_len > 0 {
+                // Long matches are copied in pieces, to bound the size of the destination buffer.
+                let len = self.match_len.min(READ_LEN);
+                let dst_pos = self.dst.len();
+
+                self.dst.resize(dst_pos + len, 0);
+                copy_match(&mut self.dst, dst_pos, self.match_dist, len);
+                self.match_len -= len;
+            } else if self.lit > 0 {
+                let len = self.lit.min(self.src.len() - self.src_pos);
+
+                self.dst
+                    .extend_from_slice(&self.src[self.src_pos..self.src_pos + len]);
+                self.src_pos += len;
+                self.lit -= len;
+
+                if self.lit == 0 {
+                    continue;
+                }
+            } else if let Ok(insn) = decode_insn(&self.src, &mut self.src_pos, self.state) {
+                // Add the zero bytes of an extended length which were removed from the source buffer.
+                let zeros = core::mem::take(&mut self.zeros);
+
+                match insn {
+                    Insn::Literal { len } => {
+                        self.lit = len + zeros * 255;
+                    }
+                    Insn::Match { len, dist, lit } => {
+                        if dist > self.dst.len() {
+                            return Err(io::Error::new(
+                                ErrorKind::InvalidData,
+                                DecompressError::InvalidInput,
+                            ));
+                        }
+
+                        self.match_dist = dist;
+                        self.match_len = len + zeros * 255;
+                        self.lit = lit;
+                    }
+                    Insn::End => {}
+                }
+
+                self.state = self.state.next(&insn);
+
+                continue;
+            }
+
+            // More compressed data is needed to continue.
+
+            if self.dst.len() > dst_len {
+                return Ok(true);
+            }
+
+            if !self.fill()? {
+                if self.lit == 0 && self.state == State::Start && self.src_pos == self.src.len() {
+                    return Ok(false);
+                }
+
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+
+    /// Read more compressed data into the source buffer.
+    ///
+    /// Returns `false` if the underlying reader reached its end.
+    fn fill(&mut self) -> io::Result<bool> {
+        if self.eof {
+            return Ok(false);
+        }
+
+        if self.src_pos > 0 {
+            self.src.drain(..self.src_pos);
+            self.src_pos = 0;
+        }
+
+        // An instruction can only be longer than a few bytes because of the zero bytes of an extended length.
+        // These are counted and removed, to bound the size of the source buffer.
+        if self.lit == 0 && self.extended_len() {
+            let zeros = self.src[1..].iter().take_while(|&&byte| byte == 0).count();
+
+            self.src.drain(1..1 + zeros);
+            self.zeros += zeros;
+        }
+
+        let len = self.src.len();
+        self.src.resize(len + READ_LEN, 0);
+
+        let n = loop {
+            match self.inner.read(&mut self.src[len..]) {
+                Ok(n) => break n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.src.truncate(len);
+
+                    return Err(err);
+                }
+            }
+        };
+
+        self.src.truncate(len + n);
+
+        if n == 0 {
+            self.eof = true;
+
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// Returns whether the source buffer starts with an instruction with an extended length.
+    fn extended_len(&self) -> bool {
+        match (self.state, self.src.first()) {
+            (State::Start | State::A, Some(0)) => true,
+            (_, Some(&insn)) => {
+                (16..=31).contains(&insn) && insn & 0b00000111 == 0
+                    || (32..=63).contains(&insn) && insn & 0b00011111 == 0
+            }
+            (_, None) => false,
+        }
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        while self.dst_pos == self.dst.len() {
+            if !self.decode()? {
+                return Ok(0);
+            }
+        }
+
+        let len = buf.len().min(self.dst.len() - self.dst_pos);
+        buf[..len].copy_from_slice(&self.dst[self.dst_pos..self.dst_pos + len]);
+        self.dst_pos += len;
+
+        Ok(len)
+    }
+}
@@ -1,9 +1,16 @@
+#[cfg(feature = "alloc")]
+use core::ops::Deref;
+use core::ops::DerefMut;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, collections::TryReserveError, vec::Vec};
+
 use crate::{
     compress_999::Compress,
     config::{M3_MAX_LEN, M4_MAX_OFFSET},
 };
 
-const SWD_N: usize = M4_MAX_OFFSET;
+pub const SWD_N: usize = M4_MAX_OFFSET;
 pub const SWD_THRESHOLD: usize = 1;
 pub const SWD_F: usize = 2048;
 const SWD_BEST_OFF: usize = M3_MAX_LEN + 1;
@@ -13,7 +20,79 @@ pub const SWD_MAX_CHAIN: usize = 2048;
 
 const NIL2: u16 = u16::MAX;
 
-pub struct Swd {
+/// Buffers of a sliding window dictionary, which can be provided by the caller to compress without allocating.
+///
+/// The size of the workspace is [`LZO1X_999_MEM_COMPRESS`](crate::LZO1X_999_MEM_COMPRESS) bytes.
+pub struct Workspace999 {
+    b: [u8; SWD_N + SWD_F + SWD_F],
+    head3: [u16; SWD_HSIZE],
+    /// Successors in the hash chains, or left children in the binary trees.
+    succ3: [u16; SWD_N + SWD_F],
+    /// Longest match lengths found in the hash chains, or right children in the binary trees.
+    best3: [u16; SWD_N + SWD_F],
+    llen3: [u16; SWD_HSIZE],
+    head2: [u16; 65536],
+    /// Whether the hash tables are known to be empty, such that they do not need to be cleared.
+    clean: bool,
+}
+
+impl Workspace999 {
+    /// Create a new `Workspace999` instance.
+    ///
+    /// This function is `const`, such that the workspace can be placed in a `static`
+    /// instead of on the stack.
+    pub const fn new() -> Self {
+        Self {
+            b: [0; SWD_N + SWD_F + SWD_F],
+            head3: [0; SWD_HSIZE],
+            succ3: [0; SWD_N + SWD_F],
+            best3: [0; SWD_N + SWD_F],
+            llen3: [0; SWD_HSIZE],
+            head2: [0; 65536],
+            clean: false,
+        }
+    }
+}
+
+impl Default for Workspace999 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Heap allocated workspace.
+#[cfg(feature = "alloc")]
+pub struct OwnedWorkspace(Box<[Workspace999]>);
+
+#[cfg(feature = "alloc")]
+impl OwnedWorkspace {
+    pub fn new() -> Result<Self, TryReserveError> {
+        let mut vec = Vec::new();
+        vec.try_reserve_exact(1)?;
+        vec.push(Workspace999::new());
+
+        Ok(Self(vec.into_boxed_slice()))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Deref for OwnedWorkspace {
+    type Target = Workspace999;
+
+    fn deref(&self) -> &Workspace999 {
+        &self.0[0]
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl DerefMut for OwnedWorkspace {
+    fn deref_mut(&mut self) -> &mut Workspace999 {
+        &mut self.0[0]
+    }
+}
+
+pub struct Swd<W> {
+    swd_n: usize,
     swd_f: usize,
     pub max_chain: usize,
     pub nice_length: usize,
@@ -31,54 +110,78 @@ pub struct Swd {
     b_size: usize,
     b_wrap: usize,
     node_count: usize,
-    b: [u8; SWD_N + SWD_F + SWD_F],
-    head3: [u16; SWD_HSIZE],
-    succ3: [u16; SWD_N + SWD_F],
-    best3: [u16; SWD_N + SWD_F],
-    llen3: [u16; SWD_HSIZE],
-    head2: [u16; 65536],
+    wrapped: bool,
+    tree: bool,
+    ws: W,
 }
 
-impl Swd {
-    pub fn new(c: &mut Compress) -> Self {
-        let mut ip = 0;
+impl<W: DerefMut<Target = Workspace999>> Swd<W> {
+    /// Create a new sliding window dictionary, using the `dict_len` bytes before the current position in `src` as dictionary.
+    ///
+    /// The window size `swd_n` limits the match offset, and the lookahead size `swd_f` limits the match length.
+    /// These are at most [`SWD_N`] and [`SWD_F`] respectively.
+    ///
+    /// If `tree_depth` is not zero, the positions of every hash bucket are kept in a binary tree instead of
+    /// a hash chain, which is searched up to the given depth instead of [`SWD_MAX_CHAIN`] positions.
+    /// The trees need far fewer comparisons when many positions share a hash, but find different matches than liblzo.
+    ///
+    /// The given workspace `ws` is reinitialized, so it does not need to be cleared.
+    /// This is cheap if the workspace was last used by a dictionary that ended with [`Swd::finish`].
+    pub fn new(
+        c: &mut Compress,
+        src: &[u8],
+        dict_len: usize,
+        swd_n: usize,
+        swd_f: usize,
+        tree_depth: usize,
+        mut ws: W,
+    ) -> Self {
+        if !ws.clean {
+            ws.head3.fill(0);
+            ws.succ3.fill(0);
+            ws.best3.fill(0);
+            ws.llen3.fill(0);
+            ws.head2.fill(NIL2);
+            ws.b.fill(0);
+        }
+
+        ws.clean = false;
 
-        let mut look = c.src.len() - c.src_idx;
+        let b = &mut ws.b;
 
-        let mut b = [0; SWD_N + SWD_F + SWD_F];
+        b[..dict_len].copy_from_slice(&src[c.src_idx - dict_len..c.src_idx]);
+
+        let mut ip = dict_len;
+        let bp = ip;
+        let mut first_rp = ip;
+
+        let mut look = src.len() - c.src_idx;
 
         if look > 0 {
-            if look > SWD_F {
-                look = SWD_F;
+            if look > swd_f {
+                look = swd_f;
             }
 
-            b[ip..ip + look].copy_from_slice(&c.src[c.src_idx..c.src_idx + look]);
+            b[ip..ip + look].copy_from_slice(&src[c.src_idx..c.src_idx + look]);
             c.src_idx += look;
             ip += look;
         }
 
-        if ip == SWD_N + SWD_F {
-            ip = 0;
-        }
-
-        let mut rp = 0;
-
-        if rp >= SWD_N {
-            rp -= SWD_N;
-        } else {
-            rp += (SWD_N + SWD_F) - SWD_N;
-        }
+        let wrapped = ip == swd_n + swd_f;
 
-        if look < 3 {
-            b[look] = 0;
-            b[look + 1] = 0;
-            b[look + 2] = 0;
+        if wrapped {
+            ip = 0;
         }
 
-        Self {
-            swd_f: SWD_F,
-            max_chain: SWD_MAX_CHAIN,
-            nice_length: SWD_F,
+        let mut swd = Self {
+            swd_n,
+            swd_f,
+            max_chain: if tree_depth > 0 {
+                tree_depth
+            } else {
+                SWD_MAX_CHAIN
+            },
+            nice_length: swd_f,
             use_best_off: false,
             m_len: 0,
             m_off: 0,
@@ -88,30 +191,82 @@ impl Swd {
             m_pos: 0,
             best_pos: [0; SWD_BEST_OFF],
             ip,
-            bp: 0,
-            rp,
-            b_size: SWD_N + SWD_F,
-            b_wrap: SWD_N + SWD_F,
-            node_count: SWD_N,
-            b,
-            head3: [0; SWD_HSIZE],
-            succ3: [0; SWD_N + SWD_F],
-            best3: [0; SWD_N + SWD_F],
-            llen3: [0; SWD_HSIZE],
-            head2: [0xffff; 65536],
+            bp,
+            rp: 0,
+            b_size: swd_n + swd_f,
+            b_wrap: swd_n + swd_f,
+            node_count: swd_n,
+            wrapped,
+            tree: tree_depth > 0,
+            ws,
+        };
+
+        if look >= 2 && dict_len > 0 {
+            swd.insert_dict(dict_len);
+            first_rp = 0;
+        }
+
+        let mut rp = first_rp;
+
+        if rp >= swd.node_count {
+            rp -= swd.node_count;
+        } else {
+            rp += swd.b_size - swd.node_count;
+        }
+
+        swd.rp = rp;
+
+        if look < 3 {
+            swd.ws.b[bp + look] = 0;
+            swd.ws.b[bp + look + 1] = 0;
+            swd.ws.b[bp + look + 2] = 0;
+        }
+
+        swd
+    }
+
+    /// End the use of the dictionary, clearing the used parts of the workspace if the window never wrapped around,
+    /// which is much faster than clearing the whole workspace for small inputs.
+    pub fn finish(mut self) {
+        if self.wrapped {
+            return;
+        }
+
+        for node in 0..=self.bp {
+            let key = head3(&self.ws.b, node);
+            self.ws.llen3[key] = 0;
+
+            let key = head2(&self.ws.b, node);
+            self.ws.head2[key] = NIL2;
+        }
+
+        // Clear the written part of the buffer, including its mirrored start.
+        self.ws.b[..self.ip + 3].fill(0);
+
+        let mirror_len = self.ip.min(self.swd_f);
+        self.ws.b[self.b_wrap..self.b_wrap + mirror_len].fill(0);
+
+        self.ws.clean = true;
+    }
+
+    fn insert_dict(&mut self, len: usize) {
+        self.node_count = self.swd_n - len;
+
+        for node in 0..len {
+            self.insert(node, len + self.look - node);
         }
     }
 
     fn remove_node(&mut self, node: usize) {
         if self.node_count == 0 {
-            let key = head3(&self.b, node);
+            let key = head3(&self.ws.b, node);
 
-            self.llen3[key] -= 1;
+            self.ws.llen3[key] -= 1;
 
-            let key = head2(&self.b, node);
+            let key = head2(&self.ws.b, node);
 
-            if self.head2[key] as usize == node {
-                self.head2[key] = NIL2;
+            if self.ws.head2[key] as usize == node {
+                self.ws.head2[key] = NIL2;
             }
         } else {
             self.node_count -= 1;
@@ -120,16 +275,17 @@ impl Swd {
 
     fn search(&mut self, mut node: usize, cnt: usize) {
         let mut m_len = self.m_len;
-        let b = &mut self.b;
+        let ws = &*self.ws;
+        let b = &ws.b;
         let bp = self.bp;
         let bx = self.bp + self.look;
-        let succ3 = &self.succ3;
+        let succ3 = &ws.succ3;
 
         let mut scan_end1 = b[bp + m_len - 1];
 
         for _ in 0..cnt {
-            let mut p1 = bp;
-            let mut p2 = node;
+            let p1 = bp;
+            let p2 = node;
             let px = bx;
 
             if b[p2 + m_len - 1] == scan_end1
@@ -137,24 +293,8 @@ impl Swd {
                 && b[p2] == b[p1]
                 && b[p2 + 1] == b[p1 + 1]
             {
-                p1 += 2;
-                p2 += 2;
-
-                loop {
-                    p1 += 1;
-
-                    if p1 >= px {
-                        break;
-                    }
-
-                    p2 += 1;
-
-                    if b[p1] != b[p2] {
-                        break;
-                    }
-                }
-
-                let i = p1 - bp;
+                // The third byte is implied by the hash, as in liblzo.
+                let i = 3 + common_len(&b[p1 + 3..px], &b[p2 + 3..]);
 
                 if i < SWD_BEST_OFF && self.best_pos[i] == 0 {
                     self.best_pos[i] = node + 1;
@@ -173,7 +313,7 @@ impl Swd {
                         return;
                     }
 
-                    if m_len > self.best3[node] as usize {
+                    if m_len > ws.best3[node] as usize {
                         return;
                     }
 
@@ -186,7 +326,7 @@ impl Swd {
     }
 
     fn search2(&mut self) -> bool {
-        let key = self.head2[head2(&self.b, self.bp)];
+        let key = self.ws.head2[head2(&self.ws.b, self.bp)];
 
         if key == NIL2 {
             return false;
@@ -205,21 +345,29 @@ impl Swd {
     }
 
     pub fn find_best(&mut self) {
-        let key = head3(&self.b, self.bp);
+        let bp = self.bp;
+        let len = self.m_len;
 
-        let node = s_get_head3(self, key);
-        self.succ3[self.bp] = node;
-        let mut cnt = self.llen3[key] as usize;
-        self.llen3[key] += 1;
+        let chain = if self.tree {
+            None
+        } else {
+            let key = head3(&self.ws.b, bp);
 
-        if cnt > self.max_chain && self.max_chain > 0 {
-            cnt = self.max_chain;
-        }
+            let node = s_get_head3(&self.ws, key);
+            self.ws.succ3[bp] = node;
+            let mut cnt = self.ws.llen3[key] as usize;
+            self.ws.llen3[key] += 1;
 
-        self.head3[key] = self.bp as u16;
+            if cnt > self.max_chain && self.max_chain > 0 {
+                cnt = self.max_chain;
+            }
 
-        self.b_char = self.b[self.bp] as i32;
-        let len = self.m_len;
+            self.ws.head3[key] = bp as u16;
+
+            Some((node as usize, cnt))
+        };
+
+        self.b_char = self.ws.b[bp] as i32;
 
         if self.m_len >= self.look {
             if self.look == 0 {
@@ -227,18 +375,30 @@ impl Swd {
             }
 
             self.m_off = 0;
-            self.best3[self.bp] = (self.swd_f + 1) as u16;
+
+            if self.tree {
+                self.tree_insert(bp, self.look, false);
+            } else {
+                self.ws.best3[bp] = (self.swd_f + 1) as u16;
+            }
         } else {
-            if self.search2() && self.look >= 3 {
-                self.search(node as usize, cnt);
+            let found = self.search2();
+
+            match chain {
+                Some((node, cnt)) => {
+                    if found && self.look >= 3 {
+                        self.search(node, cnt);
+                    }
+
+                    self.ws.best3[bp] = self.m_len as u16;
+                }
+                None => self.tree_insert(bp, self.look, self.look >= 3),
             }
 
             if self.m_len > len {
                 self.m_off = swd_pos2off(self, self.m_pos);
             }
 
-            self.best3[self.bp] = self.m_len as u16;
-
             if self.use_best_off {
                 for i in 2..SWD_BEST_OFF {
                     if self.best_pos[i] > 0 {
@@ -252,38 +412,248 @@ impl Swd {
 
         self.remove_node(self.rp);
 
-        let key = head2(&self.b, self.bp);
-        self.head2[key] = self.bp as u16;
+        let key = head2(&self.ws.b, bp);
+        self.ws.head2[key] = bp as u16;
     }
 
-    pub fn accept(&mut self, c: &mut Compress, mut n: usize) {
-        while n != 0 {
-            self.remove_node(self.rp);
+    /// Insert the given `node`, followed by `look` bytes, into the binary tree of its hash bucket,
+    /// searching for matches on the way if `search` is set.
+    ///
+    /// The nodes of a tree are ordered by their strings, and newer nodes are closer to the root. Nodes that left
+    /// the window are detected by their offset, which must be larger than the offset of their parent, as the
+    /// positions in the ring buffer are reused. A node that is equal to the inserted node up to the length limit
+    /// is replaced by it.
+    fn tree_insert(&mut self, node: usize, look: usize, search: bool) {
+        let ws = &mut *self.ws;
+        let key = head3(&ws.b, node);
+
+        let mut cur = s_get_head3(ws, key);
+        ws.head3[key] = node as u16;
+        ws.llen3[key] += 1;
+
+        let len_limit = look.min(self.nice_length);
+        let b = &ws.b;
+        let left = &mut ws.succ3;
+        let right = &mut ws.best3;
+
+        // Children of the tree which receive the next node that is smaller or larger than the inserted node.
+        let mut smaller = Child::Left(node);
+        let mut larger = Child::Right(node);
+        let mut smaller_len = 0;
+        let mut larger_len = 0;
+
+        // Longest match length for which the best positions are already known.
+        let mut best_len = 2;
+
+        let mut prev_off = 0;
+        // Positions that are skipped over are inserted with less effort.
+        let mut depth = if search {
+            self.max_chain
+        } else {
+            (self.max_chain / 4).max(1)
+        };
+
+        // Returns the offset of a child if it is still in the window, given the offset of its parent.
+        let (b_size, swd_n) = (self.b_size, self.swd_n);
+        let child_off = |child: u16, parent_off: usize| {
+            if child == NIL2 {
+                return None;
+            }
 
-            let key = head3(&self.b, self.bp);
+            let p = child as usize;
 
-            self.succ3[self.bp] = if self.llen3[key] == 0 {
-                u16::MAX
+            let off = if node > p {
+                node - p
             } else {
-                self.head3[key] as u16
+                b_size - (p - node)
+            };
+
+            (off > parent_off && off <= swd_n).then_some(off)
+        };
+
+        while len_limit >= 3 && depth > 0 {
+            let Some(off) = child_off(cur, prev_off) else {
+                break;
             };
 
-            self.head3[key] = self.bp as u16;
-            self.best3[self.bp] = (self.swd_f + 1) as u16;
-            self.llen3[key] += 1;
+            let p = cur as usize;
+            prev_off = off;
+            depth -= 1;
+
+            let mut len = smaller_len.min(larger_len);
+
+            if b[p + len] == b[node + len] {
+                len += 1 + common_len(&b[node + len + 1..node + len_limit], &b[p + len + 1..]);
+            }
+
+            if search {
+                let m_len = if len == len_limit {
+                    len + common_len(&b[node + len..node + look], &b[p + len..])
+                } else {
+                    len
+                };
+
+                // The nodes are visited from the nearest to the farthest.
+                if m_len > best_len && best_len < SWD_BEST_OFF - 1 {
+                    for pos in &mut self.best_pos[best_len + 1..=m_len.min(SWD_BEST_OFF - 1)] {
+                        if *pos == 0 {
+                            *pos = p + 1;
+                        }
+                    }
+
+                    best_len = m_len;
+                }
+
+                if m_len > self.m_len {
+                    self.m_len = m_len;
+                    self.m_pos = p;
+                }
+            }
+
+            // The children are only taken over while they are in the window, such that the offset of a node
+            // never differs by more than the window size from the offset of its parent.
+            if len == len_limit {
+                let (l, r) = (left[p], right[p]);
 
-            let key = head2(&self.b, self.bp);
-            self.head2[key] = self.bp as u16;
+                set_child(left, right, smaller, child_off(l, off).map_or(NIL2, |_| l));
+                set_child(left, right, larger, child_off(r, off).map_or(NIL2, |_| r));
 
-            self.get_byte(c);
+                return;
+            }
 
-            n -= 1;
+            if b[p + len] < b[node + len] {
+                set_child(left, right, smaller, cur);
+                smaller = Child::Right(p);
+                smaller_len = len;
+                cur = right[p];
+            } else {
+                set_child(left, right, larger, cur);
+                larger = Child::Left(p);
+                larger_len = len;
+                cur = left[p];
+            }
         }
+
+        set_child(left, right, smaller, NIL2);
+        set_child(left, right, larger, NIL2);
     }
 
-    pub fn get_byte(&mut self, c: &mut Compress) {
-        let ch = if c.src_idx < c.src.len() {
-            let ch = c.src[c.src_idx];
+    /// Returns the first node and the length of the hash chain of the current position,
+    /// excluding the current position itself, which must already be inserted by [`Swd::find_best`].
+    #[cfg(feature = "alloc")]
+    pub fn chain(&self) -> (usize, usize) {
+        let key = head3(&self.ws.b, self.bp);
+
+        (
+            self.ws.succ3[self.bp] as usize,
+            self.ws.llen3[key] as usize - 1,
+        )
+    }
+
+    /// Returns the node following the given `node` in its hash chain.
+    #[cfg(feature = "alloc")]
+    pub fn next_node(&self, node: usize) -> usize {
+        self.ws.succ3[node] as usize
+    }
+
+    /// Returns the length and offset of the match of the current position with the given `node`.
+    #[cfg(feature = "alloc")]
+    pub fn match_at(&self, node: usize) -> (usize, usize) {
+        let b = &self.ws.b;
+        let len = common_len(&b[self.bp..self.bp + self.look], &b[node..]);
+
+        (len, swd_pos2off(self, node))
+    }
+
+    pub fn accept(&mut self, c: &mut Compress, src: &[u8], mut n: usize) {
+        if self.tree || n > self.swd_n || src.len() - c.src_idx < n {
+            while n != 0 {
+                self.remove_node(self.rp);
+                self.insert(self.bp, self.look);
+                self.get_byte(c, src);
+
+                n -= 1;
+            }
+
+            return;
+        }
+
+        // Slide the window in bulk, which is equivalent to sliding it a byte at a time: the nodes which leave
+        // the window are removed before their bytes are overwritten, and the bytes of the inserted positions
+        // are not overwritten again, as long as the window is larger than the number of bytes.
+        for _ in 0..n {
+            self.remove_node(self.rp);
+
+            self.rp += 1;
+
+            if self.rp == self.b_size {
+                self.rp = 0;
+            }
+        }
+
+        let mut data = &src[c.src_idx..c.src_idx + n];
+        c.src_idx += n;
+
+        while !data.is_empty() {
+            let len = data.len().min(self.b_size - self.ip);
+
+            self.ws.b[self.ip..self.ip + len].copy_from_slice(&data[..len]);
+
+            // Mirror the start of the buffer after its end, such that matches can be compared without wrapping.
+            if self.ip < self.swd_f {
+                let mirror_len = len.min(self.swd_f - self.ip);
+                let mirror_pos = self.b_wrap + self.ip;
+
+                self.ws.b[mirror_pos..mirror_pos + mirror_len].copy_from_slice(&data[..mirror_len]);
+            }
+
+            self.ip += len;
+
+            if self.ip == self.b_size {
+                self.ip = 0;
+                self.wrapped = true;
+            }
+
+            data = &data[len..];
+        }
+
+        for _ in 0..n {
+            self.insert(self.bp, self.look);
+
+            self.bp += 1;
+
+            if self.bp == self.b_size {
+                self.bp = 0;
+            }
+        }
+    }
+
+    /// Insert the given `node`, followed by `look` bytes, without searching for matches.
+    fn insert(&mut self, node: usize, look: usize) {
+        if self.tree {
+            self.tree_insert(node, look, false);
+
+            let key = head2(&self.ws.b, node);
+            self.ws.head2[key] = node as u16;
+
+            return;
+        }
+
+        let key = head3(&self.ws.b, node);
+
+        self.ws.succ3[node] = s_get_head3(&self.ws, key);
+
+        self.ws.head3[key] = node as u16;
+        self.ws.best3[node] = (self.swd_f + 1) as u16;
+        self.ws.llen3[key] += 1;
+
+        let key = head2(&self.ws.b, node);
+        self.ws.head2[key] = node as u16;
+    }
+
+    pub fn get_byte(&mut self, c: &mut Compress, src: &[u8]) {
+        let ch = if c.src_idx < src.len() {
+            let ch = src[c.src_idx];
             c.src_idx += 1;
             ch as i16
         } else {
@@ -295,16 +665,16 @@ impl Swd {
                 self.look -= 1;
             }
 
-            self.b[self.ip] = 0;
+            self.ws.b[self.ip] = 0;
 
             if self.ip < self.swd_f {
-                self.b[self.b_wrap + self.ip] = 0;
+                self.ws.b[self.b_wrap + self.ip] = 0;
             }
         } else {
-            self.b[self.ip] = ch as u8;
+            self.ws.b[self.ip] = ch as u8;
 
             if self.ip < self.swd_f {
-                self.b[self.b_wrap + self.ip] = ch as u8;
+                self.ws.b[self.b_wrap + self.ip] = ch as u8;
             }
         }
 
@@ -312,6 +682,7 @@ impl Swd {
 
         if self.ip == self.b_size {
             self.ip = 0;
+            self.wrapped = true;
         }
 
         self.bp += 1;
@@ -328,6 +699,44 @@ impl Swd {
     }
 }
 
+/// Returns the length of the common prefix of `a` and `b`, which is at most the length of `a`.
+///
+/// The bytes are compared a word at a time, and `b` must be at least as long as `a`.
+fn common_len(a: &[u8], b: &[u8]) -> usize {
+    let mut len = 0;
+
+    for (a, b) in a.chunks_exact(8).zip(b.chunks_exact(8)) {
+        let x =
+            u64::from_le_bytes(a.try_into().unwrap()) ^ u64::from_le_bytes(b.try_into().unwrap());
+
+        if x != 0 {
+            return len + x.trailing_zeros() as usize / 8;
+        }
+
+        len += 8;
+    }
+
+    len + a[len..]
+        .iter()
+        .zip(&b[len..])
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Child of a node in a binary tree.
+#[derive(Clone, Copy)]
+enum Child {
+    Left(usize),
+    Right(usize),
+}
+
+fn set_child(left: &mut [u16], right: &mut [u16], child: Child, node: u16) {
+    match child {
+        Child::Left(parent) => left[parent] = node,
+        Child::Right(parent) => right[parent] = node,
+    }
+}
+
 fn head3(b: &[u8], p: usize) -> usize {
     ((0x9f5f * (((((b[p] as usize) << 5) ^ b[p + 1] as usize) << 5) ^ b[p + 2] as usize)) >> 5)
         & (SWD_HSIZE - 1)
@@ -337,7 +746,7 @@ fn head2(b: &[u8], p: usize) -> usize {
     b[p] as usize ^ ((b[(p) + 1] as usize) << 8)
 }
 
-fn swd_pos2off(s: &Swd, pos: usize) -> usize {
+fn swd_pos2off<W>(s: &Swd<W>, pos: usize) -> usize {
     if s.bp > (pos) {
         s.bp - (pos)
     } else {
@@ -345,7 +754,7 @@ fn swd_pos2off(s: &Swd, pos: usize) -> usize {
     }
 }
 
-fn s_get_head3(s: &Swd, key: usize) -> u16 {
+fn s_get_head3(s: &Workspace999, key: usize) -> u16 {
     if s.llen3[key] == 0 {
         u16::MAX
     } else {
@@ -0,0 +1,280 @@
+use core::fmt::{self, Display, Formatter};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::config::{
+    M1_MARKER, M2_MAX_LEN, M2_MAX_OFFSET, M2_MIN_LEN, M3_MARKER, M3_MAX_LEN, M3_MAX_OFFSET,
+    M4_MARKER, M4_MAX_LEN, MX_MAX_OFFSET,
+};
+#[cfg(feature = "alloc")]
+use crate::config::{M1_MAX_OFFSET, M4_MAX_OFFSET};
+
+/// Low-level writer of compressed data, which encodes literal runs and matches chosen by the caller.
+///
+/// Every match is encoded in the shortest form that is valid after the literals directly preceding it.
+/// The length of a literal run is written in front of its literals, so literals are held back until
+/// the next match, or until [`TokenWriter::finish`] writes the end marker.
+///
+/// # Examples
+///
+/// ```
+/// let mut writer = lzo1x::TokenWriter::new();
+///
+/// writer.write_literals(b"abc");
+/// writer.write_match(2, 3).unwrap();
+/// writer.write_literals(b"x");
+/// writer.write_match(20, 1).unwrap();
+///
+/// let compressed = writer.finish();
+///
+/// let mut decompressed = [0; 26];
+/// lzo1x::decompress(&compressed, &mut decompressed).unwrap();
+///
+/// assert_eq!(&decompressed, b"abcabxxxxxxxxxxxxxxxxxxxxx");
+/// ```
+#[cfg(feature = "alloc")]
+pub struct TokenWriter {
+    dst: Vec<u8>,
+    /// Literals following the last match, which are not yet written.
+    lits: Vec<u8>,
+    /// Length of the decompressed data, including the literals which are not yet written.
+    len: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl TokenWriter {
+    /// Create a new `TokenWriter` instance.
+    pub fn new() -> Self {
+        Self {
+            dst: Vec::new(),
+            lits: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Returns the length of the decompressed data written so far.
+    pub fn position(&self) -> usize {
+        self.len
+    }
+
+    /// Write the given literals, which are merged with any literals written directly before.
+    pub fn write_literals(&mut self, lits: &[u8]) {
+        self.lits.extend_from_slice(lits);
+        self.len += lits.len();
+    }
+
+    /// Write a match which copies `len` bytes starting `dist` bytes back in the decompressed data.
+    ///
+    /// #### Errors
+    ///
+    /// This function returns an error if the match cannot be encoded, in which case nothing is written.
+    /// Matches must be at least 3 bytes long, and can refer back up to 49151 bytes. A match of 2 bytes
+    /// can only directly follow 1 to 3 literals, and refer back up to 1024 bytes.
+    pub fn write_match(&mut self, len: usize, dist: usize) -> Result<(), TokenError> {
+        let lit = self.lits.len();
+
+        if len < 2 || (len == 2 && !(1..=3).contains(&lit)) {
+            return Err(TokenError::Length);
+        }
+
+        if dist == 0
+            || dist > self.len
+            || dist > M4_MAX_OFFSET
+            || (len == 2 && dist > M1_MAX_OFFSET)
+        {
+            return Err(TokenError::Distance);
+        }
+
+        self.write_run();
+
+        let dst_pos = self.dst.len();
+        self.dst.resize(dst_pos + len / 255 + 4, 0);
+
+        let dst_pos = store_match(&mut self.dst, dst_pos, len, dist, lit);
+        self.dst.truncate(dst_pos);
+
+        self.len += len;
+
+        Ok(())
+    }
+
+    /// Write the literals which are held back, and the end marker, returning the compressed data.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.write_run();
+        self.dst.extend_from_slice(&[M4_MARKER as u8 | 1, 0, 0]);
+
+        self.dst
+    }
+
+    fn write_run(&mut self) {
+        let t = self.lits.len();
+
+        if t == 0 {
+            return;
+        }
+
+        let dst_pos = self.dst.len();
+        self.dst.resize(dst_pos + t / 255 + 3, 0);
+
+        let dst_pos = if dst_pos == 0 && t <= 238 {
+            self.dst[0] = (17 + t) as u8;
+
+            1
+        } else {
+            store_run_len(&mut self.dst, dst_pos, t)
+        };
+
+        self.dst.truncate(dst_pos);
+        self.dst.append(&mut self.lits);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Default for TokenWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Error of a match which cannot be encoded, as returned by [`TokenWriter::write_match`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TokenError {
+    /// The length of the match is invalid after the literals directly preceding it.
+    Length,
+    /// The distance of the match is zero, too large for the length of the match,
+    /// or larger than the length of the decompressed data written so far.
+    Distance,
+}
+
+impl Display for TokenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Length => f.write_str("invalid match length"),
+            Self::Distance => f.write_str("invalid match distance"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TokenError {}
+
+/// Store the length `t` of a literal run at `dst_pos` in `dst`, returning the position after it.
+///
+/// A run of at most 3 literals is stored in the second to last byte of the preceding match.
+#[inline]
+pub(crate) fn store_run_len(dst: &mut [u8], mut dst_pos: usize, t: usize) -> usize {
+    match t {
+        0 => {}
+        1..=3 => {
+            dst[dst_pos - 2] |= t as u8;
+        }
+        4..=18 => {
+            dst[dst_pos] = t as u8 - 3;
+            dst_pos += 1;
+        }
+        19.. => {
+            let mut tt = t - 18;
+            dst[dst_pos] = 0;
+            dst_pos += 1;
+
+            while tt > 255 {
+                tt -= 255;
+                dst[dst_pos] = 0;
+                dst_pos += 1;
+            }
+
+            dst[dst_pos] = tt as u8;
+            dst_pos += 1;
+        }
+    }
+
+    dst_pos
+}
+
+/// Store a match of `m_len` bytes at offset `m_off` at `dst_pos` in `dst`, returning the position after it.
+///
+/// The shortest form is chosen, given the number of literals `lit` directly preceding the match.
+/// A match of 2 bytes is always stored as M1, so it must follow 1 to 3 literals.
+#[inline]
+pub(crate) fn store_match(
+    dst: &mut [u8],
+    mut dst_pos: usize,
+    mut m_len: usize,
+    mut m_off: usize,
+    lit: usize,
+) -> usize {
+    if m_len == 2 {
+        m_off -= 1;
+
+        dst[dst_pos] = (M1_MARKER | ((m_off & 3) << 2)) as u8;
+        dst_pos += 1;
+        dst[dst_pos] = (m_off >> 2) as u8;
+        dst_pos += 1;
+    } else if m_len <= M2_MAX_LEN && m_off <= M2_MAX_OFFSET {
+        m_off -= 1;
+        dst[dst_pos] = (((m_len - 1) << 5) | ((m_off & 7) << 2)) as u8;
+        dst_pos += 1;
+        dst[dst_pos] = (m_off >> 3) as u8;
+        dst_pos += 1;
+    } else if m_len == M2_MIN_LEN && m_off <= MX_MAX_OFFSET && lit >= 4 {
+        m_off -= 1 + M2_MAX_OFFSET;
+        dst[dst_pos] = (M1_MARKER | ((m_off & 3) << 2)) as u8;
+        dst_pos += 1;
+        dst[dst_pos] = (m_off >> 2) as u8;
+        dst_pos += 1;
+    } else if m_off <= M3_MAX_OFFSET {
+        m_off -= 1;
+
+        if m_len <= M3_MAX_LEN {
+            dst[dst_pos] = (M3_MARKER | (m_len - 2)) as u8;
+            dst_pos += 1;
+        } else {
+            m_len -= M3_MAX_LEN;
+            dst[dst_pos] = M3_MARKER as u8;
+            dst_pos += 1;
+
+            while m_len > 255 {
+                m_len -= 255;
+                dst[dst_pos] = 0;
+                dst_pos += 1;
+            }
+
+            dst[dst_pos] = m_len as u8;
+            dst_pos += 1;
+        }
+
+        dst[dst_pos] = (m_off << 2) as u8;
+        dst_pos += 1;
+        dst[dst_pos] = (m_off >> 6) as u8;
+        dst_pos += 1;
+    } else {
+        m_off -= 0x4000;
+        let k = (m_off & 0x4000) >> 11;
+
+        if m_len <= M4_MAX_LEN {
+            dst[dst_pos] = (M4_MARKER | k | (m_len - 2)) as u8;
+            dst_pos += 1;
+        } else {
+            m_len -= M4_MAX_LEN;
+            dst[dst_pos] = (M4_MARKER | k) as u8;
+            dst_pos += 1;
+
+            while m_len > 255 {
+                m_len -= 255;
+                dst[dst_pos] = 0;
+                dst_pos += 1;
+            }
+
+            dst[dst_pos] = m_len as u8;
+            dst_pos += 1;
+        }
+
+        dst[dst_pos] = (m_off << 2) as u8;
+        dst_pos += 1;
+        dst[dst_pos] = (m_off >> 6) as u8;
+        dst_pos += 1;
+    }
+
+    dst_pos
+}
@@ -0,0 +1,14 @@
+// Allocation helpers which return an error instead of aborting when memory allocation fails.
+
+use alloc::{collections::TryReserveError, vec::Vec};
+
+/// Resize the given `vec` to `len` elements, filling new elements with `value`.
+pub fn try_resize<T: Clone>(vec: &mut Vec<T>, len: usize, value: T) -> Result<(), TryReserveError> {
+    if len > vec.len() {
+        vec.try_reserve(len - vec.len())?;
+    }
+
+    vec.resize(len, value);
+
+    Ok(())
+}
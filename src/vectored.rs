@@ -0,0 +1,86 @@
+use core::ops::ControlFlow;
+
+use alloc::vec::Vec;
+
+use crate::{CompressError, CompressLevel, Compressor, compress_bound};
+
+// Number of bytes of a slice which are joined with the unconsumed input of the previous slices at once.
+const JOIN_LEN: usize = 0x10000;
+
+/// Compress the given `src` slices with the given compression `level`, as if they were a single contiguous input.
+///
+/// Matches can refer back across slice boundaries, and the compressed data is identical
+/// to the data returned by [`compress`](crate::compress) for the concatenation of the slices.
+/// Instead of concatenating all slices, only the input around the slice boundaries is copied.
+///
+/// # Examples
+///
+/// ```
+/// let header = b"hello ";
+/// let payload = b"hello hello world";
+///
+/// let compressed = lzo1x::compress_vectored(&[header, payload], lzo1x::CompressLevel::default());
+///
+/// assert_eq!(
+///     compressed,
+///     lzo1x::compress(b"hello hello hello world", lzo1x::CompressLevel::default())
+/// );
+/// ```
+pub fn compress_vectored(src: &[&[u8]], level: CompressLevel) -> Vec<u8> {
+    let src_len = src.iter().map(|slice| slice.len()).sum();
+
+    let mut dst = Vec::with_capacity(compress_bound(src_len));
+    let mut compressor = Compressor::new(level);
+
+    // Input which is not yet consumed by the compressor, followed by the first `joined` bytes of the current slice.
+    let mut buf = Vec::new();
+
+    for &slice in src {
+        let mut tail_len = buf.len();
+        let mut joined = 0;
+
+        // Join the start of the slice with the unconsumed input,
+        // until the compressor no longer requires the unconsumed input.
+        while !buf.is_empty() && joined < slice.len() {
+            let len = (slice.len() - joined).min(JOIN_LEN);
+
+            buf.extend_from_slice(&slice[joined..joined + len]);
+            joined += len;
+
+            compress_part(&mut compressor, &mut dst, &buf, false);
+
+            let retain_from = compressor.retain_from();
+
+            if retain_from >= tail_len {
+                // Continue with the slice itself.
+                compressor.shift(tail_len);
+                buf.clear();
+            } else {
+                buf.drain(..retain_from);
+                compressor.shift(retain_from);
+                tail_len -= retain_from;
+            }
+        }
+
+        if buf.is_empty() {
+            compress_part(&mut compressor, &mut dst, slice, false);
+
+            let retain_from = compressor.retain_from();
+
+            buf.extend_from_slice(&slice[retain_from..]);
+            compressor.shift(retain_from);
+        }
+    }
+
+    compress_part(&mut compressor, &mut dst, &buf, true);
+
+    dst
+}
+
+fn compress_part(compressor: &mut Compressor, dst: &mut Vec<u8>, src: &[u8], finish: bool) {
+    match compressor.compress(src, dst, finish, &mut |_, _| ControlFlow::Continue(())) {
+        Ok(()) => {}
+        Err(CompressError::Cancelled) => unreachable!(),
+        Err(CompressError::OutOfMemory) => panic!("memory allocation failed"),
+    }
+}
@@ -0,0 +1,147 @@
+use core::{mem, ops::ControlFlow};
+
+use crate::{
+    Algorithm, CompressError, CompressLevel, CompressOptions, output::SliceOutput,
+    swd::Workspace999,
+};
+
+/// Workspace size in bytes for compression level 1. (4096)
+pub const LZO1X_1_11_MEM_COMPRESS: usize = 2 << 11;
+
+/// Workspace size in bytes for compression level 2. (8192)
+pub const LZO1X_1_12_MEM_COMPRESS: usize = 2 << 12;
+
+/// Workspace size in bytes for compression level 3, the default level. (32768)
+pub const LZO1X_1_MEM_COMPRESS: usize = 2 << 14;
+
+/// Workspace size in bytes for compression level 4. (65536)
+///
+/// A byte workspace of this size can be used for all levels up to 4.
+pub const LZO1X_1_15_MEM_COMPRESS: usize = 2 << 15;
+
+/// Workspace size in bytes for compression levels 5 and higher, which is the size of a [`Workspace999`].
+pub const LZO1X_999_MEM_COMPRESS: usize = mem::size_of::<Workspace999>();
+
+/// Returns the maximum length of the compressed data for an input of `src_len` bytes.
+///
+/// # Examples
+///
+/// ```
+/// let data = &[0xaa; 100];
+/// let compressed = lzo1x::compress(data, lzo1x::CompressLevel::default());
+///
+/// assert!(compressed.len() <= lzo1x::compress_bound(data.len()));
+/// ```
+pub const fn compress_bound(src_len: usize) -> usize {
+    src_len + (src_len / 16) + 64 + 3
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Workspace used by [`compress_with_workspace`].
+///
+/// This trait is implemented for byte slices, which are used by compression levels 1 to 4,
+/// and for [`Workspace999`], which is used by compression levels 5 and higher.
+pub trait Workspace: private::Sealed {
+    #[doc(hidden)]
+    fn compress(&mut self, src: &[u8], dst: &mut SliceOutput, options: CompressOptions);
+}
+
+impl private::Sealed for [u8] {}
+
+impl Workspace for [u8] {
+    fn compress(&mut self, src: &[u8], dst: &mut SliceOutput, options: CompressOptions) {
+        let Algorithm::Lzo1x1(mut compressor) = options.algorithm::<&mut Workspace999>() else {
+            panic!(
+                "compression level {} requires a Workspace999",
+                options.level
+            );
+        };
+
+        assert!(
+            self.len() >= compressor.dict_len(),
+            "workspace must be at least {} bytes for compression level {}",
+            compressor.dict_len(),
+            options.level
+        );
+
+        let result =
+            compressor.compress(src, dst, true, self, &mut |_, _| ControlFlow::Continue(()));
+
+        finish(result);
+    }
+}
+
+impl private::Sealed for Workspace999 {}
+
+impl Workspace for Workspace999 {
+    fn compress(&mut self, src: &[u8], dst: &mut SliceOutput, options: CompressOptions) {
+        let Algorithm::Lzo1x999(mut compressor) = options.algorithm() else {
+            panic!(
+                "compression level {} requires a byte workspace",
+                options.level
+            );
+        };
+
+        let result = compressor.compress(src, dst, true, || Ok(self), &mut |_, _| {
+            ControlFlow::Continue(())
+        });
+
+        finish(result);
+    }
+}
+
+fn finish(result: Result<(), CompressError>) {
+    match result {
+        Ok(()) => {}
+        Err(CompressError::Cancelled | CompressError::OutOfMemory) => unreachable!(),
+    }
+}
+
+/// Compress the given `src` with the given compression `level` into the given `dst`,
+/// using the given `workspace` instead of allocating, and returning the length of the compressed data.
+///
+/// Compression levels 1 to 4 use a byte slice of at least [`LZO1X_1_11_MEM_COMPRESS`], [`LZO1X_1_12_MEM_COMPRESS`],
+/// [`LZO1X_1_MEM_COMPRESS`] or [`LZO1X_1_15_MEM_COMPRESS`] bytes respectively as workspace.
+/// Compression levels 5 and higher use a [`Workspace999`].
+/// The workspace does not need to be cleared between calls.
+///
+/// The compressed data is identical to the data returned by [`compress`](crate::compress).
+///
+/// #### Panics
+///
+/// Panics if the given `dst` is shorter than [`compress_bound`] of the length of `src`,
+/// or if the given `workspace` can not be used for the given `level`.
+///
+/// # Examples
+///
+/// ```
+/// let data = &[0xaa; 100];
+///
+/// let mut dst = [0; lzo1x::compress_bound(100)];
+/// let mut workspace = [0; lzo1x::LZO1X_1_MEM_COMPRESS];
+///
+/// let len = lzo1x::compress_with_workspace(data, &mut dst, lzo1x::CompressLevel::default(), &mut workspace[..]);
+///
+/// assert_eq!(&dst[..len], lzo1x::compress(data, lzo1x::CompressLevel::default()));
+/// ```
+pub fn compress_with_workspace<W: Workspace + ?Sized>(
+    src: &[u8],
+    dst: &mut [u8],
+    level: CompressLevel,
+    workspace: &mut W,
+) -> usize {
+    assert!(
+        dst.len() >= compress_bound(src.len()),
+        "destination buffer must be at least {} bytes",
+        compress_bound(src.len())
+    );
+
+    let mut output = SliceOutput { buf: dst, len: 0 };
+
+    workspace.compress(src, &mut output, CompressOptions::new(level));
+
+    output.len
+}
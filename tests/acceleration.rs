@@ -0,0 +1,59 @@
+mod common;
+
+use common::{check, mixed};
+use lzo1x::{CompressLevel, CompressOptions, compress, compress_with_options};
+
+#[test]
+fn acceleration() {
+    let data = mixed();
+
+    for level in 1..=4 {
+        let level = CompressLevel::new(level);
+        let options = CompressOptions::new(level);
+
+        let compressed = compress(&data, level);
+
+        assert_eq!(
+            compress_with_options(&data, options.acceleration(0)),
+            compressed
+        );
+        assert_eq!(
+            compress_with_options(&data, options.acceleration(1)),
+            compressed
+        );
+
+        let mut prev_len = compressed.len();
+
+        // Every increase of the acceleration trades some compression ratio for speed.
+        for acceleration in [2, 4, 8, 16, 64] {
+            let compressed = compress_with_options(&data, options.acceleration(acceleration));
+
+            check(&compressed, &data);
+
+            assert!(compressed.len() > prev_len, "level {level}");
+
+            prev_len = compressed.len();
+        }
+
+        assert_eq!(
+            compress_with_options(&data, options.acceleration(255)).len(),
+            prev_len
+        );
+    }
+}
+
+#[test]
+fn acceleration_999() {
+    let data = mixed();
+
+    // Compression levels 5 and higher are not affected.
+    for level in [5, 9] {
+        let level = CompressLevel::new(level);
+        let options = CompressOptions::new(level).acceleration(8);
+
+        assert_eq!(
+            compress_with_options(&data, options),
+            compress(&data, level)
+        );
+    }
+}
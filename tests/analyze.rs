@@ -0,0 +1,124 @@
+mod common;
+
+use std::io::Write;
+
+use common::{random, repeated_words};
+use lzo1x::{CompressLevel, DecompressError, Encoder, analyze, analyze_regions, compress};
+
+#[test]
+fn analyze_totals() {
+    let data = [repeated_words(100_000), random(10_000, 1)].concat();
+
+    for level in 1..=13 {
+        let compressed = compress(&data, CompressLevel::new(level));
+        let analysis = analyze(&compressed).unwrap();
+
+        let matches = analysis.matches();
+
+        assert_eq!(analysis.compressed_len, compressed.len());
+        assert_eq!(analysis.decompressed_len, data.len());
+        assert_eq!(analysis.blocks, 1);
+
+        assert_eq!(
+            analysis.literals.decompressed_len + matches.decompressed_len,
+            data.len()
+        );
+        assert_eq!(
+            analysis.literals.compressed_len + matches.compressed_len + 3,
+            compressed.len()
+        );
+
+        assert_eq!(
+            analysis.match_len_histogram.iter().sum::<usize>(),
+            matches.count
+        );
+        assert_eq!(
+            analysis.match_dist_histogram.iter().sum::<usize>(),
+            matches.count
+        );
+
+        assert!(analysis.match_ratio() > 0.5 && analysis.match_ratio() < 1.0);
+        assert!(analysis.decode_cost > data.len() as u64);
+        assert!(!analysis.to_string().is_empty());
+    }
+}
+
+#[test]
+fn analyze_literals() {
+    let data = random(1000, 1);
+
+    let analysis = analyze(&compress(&data, CompressLevel::default())).unwrap();
+
+    assert_eq!(analysis.literals.decompressed_len, data.len());
+    assert_eq!(analysis.matches().count, 0);
+
+    let analysis = analyze(&compress(&[], CompressLevel::default())).unwrap();
+
+    assert_eq!(analysis.decompressed_len, 0);
+    assert_eq!(analysis.blocks, 1);
+}
+
+#[test]
+fn analyze_blocks() {
+    let data = repeated_words(100_000);
+
+    let mut encoder = Encoder::new(Vec::new(), CompressLevel::default());
+
+    for chunk in data.chunks(30_000) {
+        encoder.write_all(chunk).unwrap();
+        encoder.flush().unwrap();
+    }
+
+    let analysis = analyze(&encoder.finish().unwrap()).unwrap();
+
+    assert_eq!(analysis.decompressed_len, data.len());
+    assert_eq!(analysis.blocks, 14);
+}
+
+#[test]
+fn analyze_regions_totals() {
+    let data = [repeated_words(50_000), random(10_000, 1)].concat();
+
+    let compressed = compress(&data, CompressLevel::default());
+    let regions = analyze_regions(&compressed, 4096).unwrap();
+
+    assert_eq!(regions.len(), data.len().div_ceil(4096));
+    assert_eq!(
+        regions
+            .iter()
+            .map(|region| region.decompressed_len)
+            .sum::<usize>(),
+        data.len()
+    );
+    assert_eq!(
+        regions
+            .iter()
+            .map(|region| region.compressed_len)
+            .sum::<usize>(),
+        compressed.len()
+    );
+
+    for (i, region) in regions.iter().enumerate() {
+        assert_eq!(region.offset, i * 4096);
+    }
+
+    // The random data at the end compresses badly.
+    let last = regions.last().unwrap();
+
+    assert!(last.compressed_len > last.decompressed_len);
+    assert!(regions[0].compressed_len < regions[0].decompressed_len / 2);
+    assert!(last.literal_len > last.decompressed_len / 2);
+}
+
+#[test]
+fn analyze_errors() {
+    let compressed = compress(&[0xaa; 1000], CompressLevel::default());
+
+    for src in [&[][..], &compressed[..compressed.len() - 1], &[0xff; 10]] {
+        assert_eq!(analyze(src), Err(DecompressError::InvalidInput));
+        assert_eq!(
+            analyze_regions(src, 100),
+            Err(DecompressError::InvalidInput)
+        );
+    }
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "arbitrary")]
+
+mod common;
+
+use arbitrary::{Arbitrary, Unstructured};
+use common::random;
+use lzo1x::{Analysis, ArbitraryStream, analyze, decompress};
+
+#[test]
+fn arbitrary_stream() {
+    let mut total = Analysis::default();
+    let mut max_literals = 0;
+
+    for seed in 1..200 {
+        let input = random(seed as usize * 100, seed);
+        let stream = ArbitraryStream::arbitrary(&mut Unstructured::new(&input)).unwrap();
+
+        let mut decompressed = vec![0; stream.decompressed.len()];
+        decompress(&stream.compressed, &mut decompressed).unwrap();
+
+        assert_eq!(decompressed, stream.decompressed);
+
+        let analysis = analyze(&stream.compressed).unwrap();
+
+        for (total, stats) in [
+            (&mut total.literals, analysis.literals),
+            (&mut total.m1, analysis.m1),
+            (&mut total.m2, analysis.m2),
+            (&mut total.m3, analysis.m3),
+            (&mut total.m4, analysis.m4),
+        ] {
+            total.count += stats.count;
+        }
+
+        max_literals = max_literals.max(analysis.literals.compressed_len);
+    }
+
+    // Every instruction form is generated.
+    assert!(total.literals.count > 1000);
+    assert!(total.m1.count > 100);
+    assert!(total.m2.count > 100);
+    assert!(total.m3.count > 100);
+    assert!(total.m4.count > 100);
+    assert!(max_literals > 1000);
+}
+
+#[test]
+fn arbitrary_stream_empty() {
+    let stream = ArbitraryStream::arbitrary(&mut Unstructured::new(&[])).unwrap();
+
+    assert_eq!(stream.compressed, [17, 0, 0]);
+    assert!(stream.decompressed.is_empty());
+}
+
+#[test]
+fn arbitrary_stream_mutate() {
+    let mut invalid = 0;
+
+    for seed in 1..1000 {
+        let input = random(1000, seed);
+        let mut u = Unstructured::new(&input);
+
+        let stream = ArbitraryStream::arbitrary(&mut u).unwrap();
+        let corrupt = stream
+            .mutate(&mut Unstructured::new(&random(16, !seed)))
+            .unwrap();
+
+        let mut decompressed = vec![0; stream.decompressed.len()];
+
+        if decompress(&corrupt, &mut decompressed).is_err() {
+            invalid += 1;
+        }
+    }
+
+    assert!(invalid > 500);
+}
+
+#[test]
+fn arbitrary_stream_mutate_invalid() {
+    for compressed in [vec![], vec![0xff; 10], vec![17, 0]] {
+        let stream = ArbitraryStream {
+            compressed,
+            decompressed: Vec::new(),
+        };
+
+        assert!(matches!(
+            stream.mutate(&mut Unstructured::new(&random(16, 1))),
+            Err(arbitrary::Error::IncorrectFormat)
+        ));
+    }
+}
@@ -0,0 +1,171 @@
+#![cfg(feature = "tokio")]
+
+mod common;
+
+use std::{
+    io::{self, ErrorKind},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use common::repeated_words;
+use lzo1x::{AsyncFrameReader, AsyncFrameWriter, CompressLevel, compress_frame};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+#[tokio::test]
+async fn writer() {
+    let data = repeated_words(50_000);
+
+    for level in [1, 5] {
+        let level = CompressLevel::new(level);
+
+        let mut writer = AsyncFrameWriter::with_block_size(Trickle::new(Vec::new()), level, 30_000);
+
+        for chunk in data.chunks(7000) {
+            writer.write_all(chunk).await.unwrap();
+        }
+
+        writer.shutdown().await.unwrap();
+
+        assert_eq!(
+            writer.into_inner().inner,
+            compress_frame(&data, level, 30_000)
+        );
+    }
+}
+
+#[tokio::test]
+async fn reader() {
+    let data = repeated_words(50_000);
+    let frame = compress_frame(&data, CompressLevel::default(), 30_000);
+
+    let mut reader = AsyncFrameReader::new(Trickle::new(frame.as_slice()));
+
+    let mut decompressed = Vec::new();
+    reader.read_to_end(&mut decompressed).await.unwrap();
+
+    assert_eq!(decompressed, data);
+
+    // The frame is followed by other data, which should not be read.
+    let mut src = frame.clone();
+    src.extend_from_slice(b"tail");
+
+    let mut reader = AsyncFrameReader::new(src.as_slice());
+
+    let mut decompressed = Vec::new();
+    reader.read_to_end(&mut decompressed).await.unwrap();
+
+    assert_eq!(decompressed, data);
+    assert_eq!(reader.into_inner(), b"tail");
+
+    let mut reader = AsyncFrameReader::new(&frame[..frame.len() - 1]);
+
+    let err = reader.read_to_end(&mut Vec::new()).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[tokio::test]
+async fn reader_error() {
+    let data = repeated_words(400);
+    let mut frame = compress_frame(&data, CompressLevel::default(), 100);
+
+    // Corrupt the data of the first block, such that its checksum does not match.
+    frame[8 + 12] ^= 1;
+
+    let mut reader = AsyncFrameReader::new(frame.as_slice());
+    let mut buf = [0; 100];
+
+    for _ in 0..3 {
+        let err = reader.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    let mut reader = AsyncFrameReader::new(&frame[..4]);
+
+    for _ in 0..3 {
+        let err = reader.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+}
+
+#[tokio::test]
+async fn write_after_shutdown() {
+    let mut writer = AsyncFrameWriter::new(Vec::new(), CompressLevel::default());
+    writer.write_all(b"data").await.unwrap();
+    writer.shutdown().await.unwrap();
+
+    let err = writer.write(b"more").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+}
+
+/// Wrapper which returns pending on every other call, and reads or writes at most 7 bytes at once.
+struct Trickle<T> {
+    inner: T,
+    pending: bool,
+}
+
+impl<T> Trickle<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            pending: false,
+        }
+    }
+
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.pending = !self.pending;
+
+        if self.pending {
+            cx.waker().wake_by_ref();
+
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Trickle<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.poll_pending(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        let mut small_buf = [0; 7];
+        let len = buf.remaining().min(7);
+        let mut small_buf = ReadBuf::new(&mut small_buf[..len]);
+
+        let poll = Pin::new(&mut self.inner).poll_read(cx, &mut small_buf);
+        buf.put_slice(small_buf.filled());
+
+        poll
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Trickle<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.poll_pending(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        let len = buf.len().min(7);
+
+        Pin::new(&mut self.inner).poll_write(cx, &buf[..len])
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use std::io::{Read, Write};
+
+use common::{check, mixed};
+use lzo1x::{CompressLevel, CompressOptions, Decoder, Encoder, compress, compress_with_options};
+
+#[test]
+fn binary_trees() {
+    let data = mixed();
+
+    for level in 1..=13 {
+        let level = CompressLevel::new(level);
+        let options = CompressOptions::new(level);
+
+        let compressed = compress(&data, level);
+        let trees = compress_with_options(&data, options.binary_trees(true));
+
+        check(&compressed, &data);
+        check(&trees, &data);
+
+        if level <= CompressLevel::new(11) {
+            assert_eq!(trees, compressed, "level {level}");
+        } else {
+            assert_ne!(trees, compressed, "level {level}");
+            assert!(trees.len() <= compressed.len() + compressed.len() / 100);
+        }
+
+        assert_eq!(
+            compress_with_options(&data, options.binary_trees(false)),
+            compressed
+        );
+    }
+}
+
+#[test]
+fn binary_trees_window() {
+    let data = mixed();
+
+    for level in [12, 13] {
+        for (max_offset, max_len) in [(100, 3), (2048, 300), (49151, 9)] {
+            let options = CompressOptions::new(CompressLevel::new(level))
+                .max_offset(max_offset)
+                .max_len(max_len)
+                .binary_trees(true);
+
+            check(&compress_with_options(&data, options), &data);
+        }
+    }
+}
+
+#[test]
+fn binary_trees_encoder() {
+    let data = mixed();
+
+    // Every block after the first uses the preceding data as its dictionary.
+    let options = CompressOptions::new(CompressLevel::MAX).binary_trees(true);
+    let mut encoder = Encoder::with_options(Vec::new(), options);
+
+    for chunk in data.chunks(30_000) {
+        encoder.write_all(chunk).unwrap();
+        encoder.flush().unwrap();
+    }
+
+    let compressed = encoder.finish().unwrap();
+
+    let mut decompressed = Vec::new();
+    Decoder::new(compressed.as_slice())
+        .read_to_end(&mut decompressed)
+        .unwrap();
+
+    assert!(decompressed == data);
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use std::{env, fs};
+
+use common::repeated_words;
+use lzo1x::CompressLevel;
+
+#[test]
+fn embed() {
+    let dir = env::temp_dir().join(format!("lzo1x-build-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let data = repeated_words(100_000);
+
+    fs::write(dir.join("data.bin"), &data).unwrap();
+
+    lzo1x::build::embed_file(
+        dir.join("data.bin"),
+        CompressLevel::new(5),
+        dir.join("data.lzo"),
+    )
+    .unwrap();
+
+    let compressed = fs::read(dir.join("data.lzo")).unwrap();
+    let source = fs::read_to_string(dir.join("data.lzo.rs")).unwrap();
+
+    assert_eq!(compressed, lzo1x::compress(&data, CompressLevel::new(5)));
+    assert!(source.starts_with("::lzo1x::Embedded::new(include_bytes!("));
+    assert!(source.contains(&format!(", {})", data.len())));
+
+    let embedded = lzo1x::Embedded::new(compressed.leak(), data.len());
+
+    assert_eq!(embedded.get(), data);
+
+    fs::remove_dir_all(dir).unwrap();
+}
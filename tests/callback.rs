@@ -0,0 +1,54 @@
+mod common;
+
+use std::ops::ControlFlow;
+
+use common::repeated_words;
+use lzo1x::{CompressError, CompressLevel, compress, compress_with_callback};
+
+#[test]
+fn progress() {
+    let data = repeated_words(200_000);
+
+    for level in 1..=13 {
+        let level = CompressLevel::new(level);
+
+        let mut last_src_pos = 0;
+        let mut last_dst_len = 0;
+
+        let compressed = compress_with_callback(&data, level, |src_pos, dst_len| {
+            assert!(src_pos >= last_src_pos && src_pos <= data.len());
+            assert!(dst_len >= last_dst_len);
+
+            last_src_pos = src_pos;
+            last_dst_len = dst_len;
+
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+        assert_eq!(last_src_pos, data.len());
+        assert_eq!(last_dst_len, compressed.len());
+        assert_eq!(compressed, compress(&data, level));
+    }
+}
+
+#[test]
+fn cancel() {
+    let data = repeated_words(200_000);
+
+    for level in 1..=13 {
+        let mut calls = 0;
+
+        let result = compress_with_callback(&data, CompressLevel::new(level), |_, _| {
+            calls += 1;
+
+            if calls == 2 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+
+        assert_eq!(result, Err(CompressError::Cancelled));
+    }
+}
@@ -0,0 +1,298 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use std::{
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+use common::{random, repeated_words};
+use lzo1x::{CompressLevel, compress};
+
+const MAGIC: [u8; 9] = [0x89, b'L', b'Z', b'O', 0x00, 0x0d, 0x0a, 0x1a, 0x0a];
+
+#[test]
+fn round_trip() {
+    let dir = TempDir::new("round_trip");
+
+    for (i, data) in [
+        Vec::new(),
+        b"hello world".to_vec(),
+        repeated_words(100_000),
+        random(300_000, 1),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        for level in ["-1", "-3", "-7", "-9", "--level=13"] {
+            let input = dir.path(&format!("{i}.txt"));
+            fs::write(&input, &data).unwrap();
+
+            let output = lzo1x(&["-f", level, input.to_str().unwrap()], &[]);
+            assert!(output.status.success());
+
+            let compressed = fs::read(dir.path(&format!("{i}.txt.lzo"))).unwrap();
+            assert!(compressed.starts_with(&MAGIC));
+
+            assert!(
+                lzo1x(&["-t", &format!("{}.lzo", input.display())], &[])
+                    .status
+                    .success()
+            );
+
+            let output = lzo1x(&["-d", "-c", &format!("{}.lzo", input.display())], &[]);
+            assert!(output.status.success());
+            assert!(output.stdout == data);
+
+            // Data read from standard input is compressed into the same blocks.
+            let output = lzo1x(&[level], &data);
+            assert!(output.status.success());
+
+            let output = lzo1x(&["-d"], &output.stdout);
+            assert!(output.status.success());
+            assert!(output.stdout == data);
+        }
+    }
+}
+
+#[test]
+fn header() {
+    let dir = TempDir::new("header");
+
+    let data = repeated_words(1000);
+    let file = lzop_file(0o104755, 1_700_000_000, "name.txt", &[&data]);
+
+    let input = dir.path("input.lzo");
+    fs::write(&input, &file).unwrap();
+
+    let output = lzo1x(&["-l", input.to_str().unwrap()], &[]);
+    assert!(output.status.success());
+
+    let list = String::from_utf8(output.stdout).unwrap();
+    let fields: Vec<&str> = list.lines().nth(1).unwrap().split_whitespace().collect();
+
+    assert_eq!(fields[0], "LZO1X-1");
+    assert_eq!(fields[2], data.len().to_string());
+    assert_eq!(fields[4], "name.txt");
+
+    let output = dir.path("output.txt");
+    assert!(
+        lzo1x(
+            &[
+                "-d",
+                "-o",
+                output.to_str().unwrap(),
+                input.to_str().unwrap()
+            ],
+            &[]
+        )
+        .status
+        .success()
+    );
+    assert_eq!(fs::read(&output).unwrap(), data);
+
+    let metadata = fs::metadata(&output).unwrap();
+    assert_eq!(
+        metadata.modified().unwrap(),
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000)
+    );
+
+    // The setuid bit of the header is not restored.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        assert_eq!(metadata.permissions().mode() & 0o7777, 0o755);
+    }
+}
+
+#[test]
+fn lzop_fixture() {
+    // A file compressed by LZO1X-1 of liblzo 2.10, with the header and block layout written by lzop 1.04.
+    let output = lzo1x(&["-d", "-c", "tests/lzop/hello.txt.lzo"], &[]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, fs::read("tests/lzop/hello.txt").unwrap());
+
+    let output = lzo1x(&["-l", "tests/lzop/hello.txt.lzo"], &[]);
+    assert!(output.status.success());
+
+    let list = String::from_utf8(output.stdout).unwrap();
+    assert!(list.contains("LZO1X-1"));
+    assert!(list.contains("274      1950  14.1% hello.txt"));
+}
+
+#[test]
+fn corrupt() {
+    let dir = TempDir::new("corrupt");
+
+    let data = repeated_words(1000);
+    let file = lzop_file(0o100644, 0, "name.txt", &[&data]);
+
+    let header_len = MAGIC.len() + 25 + "name.txt".len() + 4;
+
+    let mut header_checksum = file.clone();
+    header_checksum[header_len - 1] ^= 1;
+
+    let mut block_checksum = file.clone();
+    block_checksum[header_len + 8] ^= 1;
+
+    let mut block_size = file.clone();
+    block_size[header_len..header_len + 4]
+        .copy_from_slice(&(64 * 1024 * 1024 + 1u32).to_be_bytes());
+
+    for (file, error, list) in [
+        (header_checksum, "header checksum error", false),
+        // Listing does not decompress the blocks, so their checksums are not verified.
+        (block_checksum, "checksum error", true),
+        (block_size, "invalid block size", false),
+    ] {
+        let input = dir.path("input.lzo");
+        fs::write(&input, &file).unwrap();
+
+        let output = lzo1x(&["-t", input.to_str().unwrap()], &[]);
+        assert!(!output.status.success());
+        assert!(String::from_utf8(output.stderr).unwrap().contains(error));
+
+        let output = lzo1x(&["-l", input.to_str().unwrap()], &[]);
+        assert_eq!(output.status.success(), list);
+    }
+
+    let output = lzo1x(&["-t"], b"not a lzop file");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("not a lzop file")
+    );
+}
+
+#[test]
+fn raw() {
+    let data = repeated_words(10_000);
+
+    let output = lzo1x(&["--raw", "-c", "-7"], &data);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, compress(&data, CompressLevel::new(11)));
+
+    let compressed = output.stdout;
+
+    let output = lzo1x(
+        &["-d", "--raw", &format!("--size={}", data.len())],
+        &compressed,
+    );
+    assert!(output.status.success());
+    assert!(output.stdout == data);
+
+    let output = lzo1x(
+        &["-t", "--raw", &format!("--size={}", data.len())],
+        &compressed,
+    );
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let output = lzo1x(
+        &["-d", "--raw", &format!("--size={}", data.len() + 1)],
+        &compressed,
+    );
+    assert!(!output.status.success());
+
+    let output = lzo1x(&["-d", "--raw"], &compressed);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("requires '--size'")
+    );
+}
+
+/// Run the command-line tool with the given `args`, writing `stdin` to its standard input.
+fn lzo1x(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lzo1x"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut child_stdin = child.stdin.take().unwrap();
+    let stdin = stdin.to_vec();
+    let writer = std::thread::spawn(move || {
+        let _ = child_stdin.write_all(&stdin);
+    });
+
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap();
+
+    output
+}
+
+/// Returns a lzop file with the given header fields, and the given blocks compressed with LZO1X-1.
+fn lzop_file(mode: u32, mtime: u32, name: &str, blocks: &[&[u8]]) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.extend_from_slice(&0x1040u16.to_be_bytes());
+    header.extend_from_slice(&0x20a0u16.to_be_bytes());
+    header.extend_from_slice(&0x0940u16.to_be_bytes());
+    header.extend_from_slice(&[1, 5]);
+    header.extend_from_slice(&0x03000001u32.to_be_bytes());
+    header.extend_from_slice(&mode.to_be_bytes());
+    header.extend_from_slice(&mtime.to_be_bytes());
+    header.extend_from_slice(&0u32.to_be_bytes());
+    header.push(name.len() as u8);
+    header.extend_from_slice(name.as_bytes());
+
+    let mut file = MAGIC.to_vec();
+    file.extend_from_slice(&header);
+    file.extend_from_slice(&adler32(1, &header).to_be_bytes());
+
+    for block in blocks {
+        let compressed = compress(block, CompressLevel::new(3));
+
+        file.extend_from_slice(&(block.len() as u32).to_be_bytes());
+        file.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        file.extend_from_slice(&adler32(1, block).to_be_bytes());
+        file.extend_from_slice(&compressed);
+    }
+
+    file.extend_from_slice(&0u32.to_be_bytes());
+
+    file
+}
+
+/// Returns the Adler-32 checksum of the given `buf`, as used by lzop.
+fn adler32(adler: u32, buf: &[u8]) -> u32 {
+    let mut s1 = adler & 0xffff;
+    let mut s2 = adler >> 16;
+
+    for &byte in buf {
+        s1 = (s1 + byte as u32) % 65521;
+        s2 = (s2 + s1) % 65521;
+    }
+
+    (s2 << 16) | s1
+}
+
+/// Temporary directory which is removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("lzo1x-cli-{name}-{}", std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
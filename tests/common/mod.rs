@@ -0,0 +1,53 @@
+// Helpers shared by the integration tests, each of which uses only some of them.
+#![allow(dead_code)]
+
+use lzo1x::decompress;
+
+/// Returns `len` pseudo-random bytes, generated by a xorshift generator with the given `seed`.
+pub fn random(len: usize, seed: u32) -> Vec<u8> {
+    let mut state = seed;
+
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+
+            state as u8
+        })
+        .collect()
+}
+
+/// Returns the little-endian bytes of `count` integers which each repeat 7 times, which compress well.
+pub fn repeated_words(count: u32) -> Vec<u8> {
+    (0..count).flat_map(|i| (i / 7).to_le_bytes()).collect()
+}
+
+/// Returns data with different characteristics: text with a small alphabet, which results in long hash chains,
+/// zeros, random bytes and numbers, followed by a repeat of the start of the text.
+pub fn mixed() -> Vec<u8> {
+    let dna: Vec<u8> = random(100_000, 1)
+        .iter()
+        .map(|x| b"ACGT"[*x as usize & 3])
+        .collect();
+    let numbers: Vec<u8> = (0..10_000u32)
+        .flat_map(|i| format!("{:08}\n", i * 7).into_bytes())
+        .collect();
+
+    [
+        &dna[..],
+        &[0; 5000],
+        &random(20_000, 1),
+        &numbers,
+        &dna[..30_000],
+    ]
+    .concat()
+}
+
+/// Check that the given `compressed` data decompresses to `expected`.
+pub fn check(compressed: &[u8], expected: &[u8]) {
+    let mut decompressed = vec![0; expected.len()];
+    decompress(compressed, &mut decompressed).unwrap();
+
+    assert_eq!(decompressed, expected);
+}
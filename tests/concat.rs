@@ -0,0 +1,106 @@
+mod common;
+
+use common::repeated_words;
+use lzo1x::{CompressLevel, DecompressError, compress, concat, decompress};
+
+fn check(a: &[u8], b: &[u8], level: CompressLevel) {
+    let joined = concat(&compress(a, level), &compress(b, level)).unwrap();
+
+    let mut decompressed = vec![0; a.len() + b.len()];
+    decompress(&joined, &mut decompressed).unwrap();
+
+    assert_eq!(decompressed[..a.len()], *a);
+    assert_eq!(decompressed[a.len()..], *b);
+}
+
+#[test]
+fn concat_seams() {
+    let data = [repeated_words(2000), b"abcabcabcdxxxxabxab".repeat(20)].concat();
+
+    for level in [1, 3, 5, 12] {
+        let level = CompressLevel::new(level);
+
+        // Cover every combination of short literal tails and heads at the seam.
+        for a_len in 0..40 {
+            for b_len in 0..40 {
+                check(&data[..a_len], &data[a_len..a_len + b_len], level);
+                check(&data[8000 - a_len..], &data[8000..8000 + b_len], level);
+                check(&data[..a_len], &data[8000..8000 + b_len], level);
+            }
+        }
+
+        for (a_len, b_len) in [(300, 300), (8000, 380), (3, 8000), (5000, 3380)] {
+            check(&data[..a_len], &data[a_len..a_len + b_len], level);
+        }
+    }
+}
+
+#[test]
+fn concat_short_matches() {
+    // Streams starting with 1-3 literals followed by 2 byte matches,
+    // which change their meaning when more literals precede them.
+    let streams: [(&[u8], &[u8]); 5] = [
+        (&[18, b'x', 0, 0, 17, 0, 0], b"xxx"),
+        (&[18, b'x', 1, 0, b'y', 17, 0, 0], b"xxxy"),
+        (&[18, b'x', 1, 0, b'y', 0, 0, 17, 0, 0], b"xxxyyy"),
+        (
+            &[18, b'x', 0, 0, 1, b'a', b'b', b'c', b'd', 17, 0, 0],
+            b"xxxabcd",
+        ),
+        (
+            &[20, b'x', b'y', b'z', 5, 0, b'1', 0, 0, 17, 0, 0],
+            b"xyzyz111",
+        ),
+    ];
+
+    let data = b"abcabcabcdxxxxabxab".repeat(20);
+
+    for (b, expected) in streams {
+        let mut decompressed = vec![0; expected.len()];
+        decompress(b, &mut decompressed).unwrap();
+
+        assert_eq!(decompressed, expected);
+
+        for a_len in 0..40 {
+            let a = &data[..a_len];
+            let joined = concat(&compress(a, CompressLevel::default()), b).unwrap();
+
+            let mut decompressed = vec![0; a_len + b.len()];
+            let len = lzo1x::decompress_into(&joined, &mut decompressed).unwrap();
+
+            assert_eq!(decompressed[..a_len], *a);
+            assert_eq!(decompressed[a_len..len], *expected);
+        }
+    }
+}
+
+#[test]
+fn concat_many() {
+    let data = repeated_words(100_000);
+
+    let mut joined = compress(&[], CompressLevel::default());
+
+    for chunk in data.chunks(12_345) {
+        joined = concat(&joined, &compress(chunk, CompressLevel::default())).unwrap();
+    }
+
+    let mut decompressed = vec![0; data.len()];
+    decompress(&joined, &mut decompressed).unwrap();
+
+    assert_eq!(decompressed, data);
+}
+
+#[test]
+fn concat_errors() {
+    let valid = compress(b"hello hello hello", CompressLevel::default());
+
+    for invalid in [
+        &[][..],
+        &valid[..valid.len() - 1],
+        &[0xff; 10],
+        &[17, 0, 0, 17, 0, 0],
+    ] {
+        assert_eq!(concat(invalid, &valid), Err(DecompressError::InvalidInput));
+        assert_eq!(concat(&valid, invalid), Err(DecompressError::InvalidInput));
+    }
+}
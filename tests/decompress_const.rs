@@ -0,0 +1,36 @@
+mod common;
+
+use common::repeated_words;
+use lzo1x::{CompressLevel, compress, decompress_const};
+
+const LEN: usize = 4096;
+
+const DATA: [u8; 4] = decompress_const(&[18, 0xaa, 33, 0, 0, 17, 0, 0]);
+
+#[test]
+fn decompress_const_eval() {
+    assert_eq!(DATA, [0xaa; 4]);
+}
+
+#[test]
+fn decompress_const_runtime() {
+    let data = repeated_words(LEN as u32 / 4);
+
+    for level in 1..=13 {
+        let compressed = compress(&data, CompressLevel::new(level));
+
+        assert_eq!(decompress_const::<LEN>(&compressed), data.as_slice());
+    }
+}
+
+#[test]
+#[should_panic(expected = "invalid input")]
+fn decompress_const_invalid_input() {
+    decompress_const::<1>(&[18, 0, 0]);
+}
+
+#[test]
+#[should_panic(expected = "output length does not match")]
+fn decompress_const_output_length() {
+    decompress_const::<2>(&[18, 0, 17, 0, 0]);
+}
@@ -1,4 +1,4 @@
-use lzo1x::{DecompressError, decompress};
+use lzo1x::{DecompressError, decompress, decompress_into};
 
 #[test]
 fn errors() {
@@ -23,6 +23,20 @@ fn errors() {
     test_output_length(&[17, 0, 0], &mut [0]);
 }
 
+#[test]
+fn decompress_into_larger() {
+    assert_eq!(decompress_into(&[17, 0, 0], &mut [0; 4]), Ok(0));
+    assert_eq!(decompress_into(&[18, 1, 17, 0, 0], &mut [0; 4]), Ok(1));
+    assert_eq!(
+        decompress_into(&[19, 1, 2, 17, 0, 0], &mut [0; 1]),
+        Err(DecompressError::OutputLength)
+    );
+    assert_eq!(
+        decompress_into(&[18, 1, 17, 0, 0, 0], &mut [0; 4]),
+        Err(DecompressError::InvalidInput)
+    );
+}
+
 fn test_invalid_input(src: &[u8], dst: &mut [u8]) {
     let result = decompress(src, dst);
 
@@ -0,0 +1,250 @@
+mod common;
+
+use std::{
+    cell::Cell,
+    io::{self, Cursor, ErrorKind, Read, Seek, SeekFrom, Write},
+};
+
+use common::{random, repeated_words};
+use lzo1x::{
+    CompressLevel, FrameError, FrameReader, FrameWriter, compress_frame, decompress_frame,
+};
+
+#[test]
+fn round_trip() {
+    let data = repeated_words(200_000);
+
+    for level in [1, 4, 5, 13] {
+        let level = CompressLevel::new(level);
+
+        let frame = compress_frame(&data, level, 30_000);
+        assert_eq!(decompress_frame(&frame).unwrap(), data);
+
+        let mut writer = FrameWriter::with_block_size(Vec::new(), level, 30_000);
+
+        for chunk in data.chunks(7000) {
+            writer.write_all(chunk).unwrap();
+        }
+
+        assert_eq!(writer.finish().unwrap(), frame);
+    }
+
+    let frame = compress_frame(&[], CompressLevel::default(), 30_000);
+    assert_eq!(decompress_frame(&frame).unwrap(), []);
+}
+
+#[test]
+fn stored() {
+    let data = random(100_000, 1);
+
+    let frame = compress_frame(&data, CompressLevel::default(), 30_000);
+
+    assert!(frame.len() < data.len() + 200);
+    assert_eq!(decompress_frame(&frame).unwrap(), data);
+}
+
+#[test]
+fn seek() {
+    let data = repeated_words(200_000);
+    let frame = compress_frame(&data, CompressLevel::default(), 30_000);
+
+    let mut reader = FrameReader::new(Cursor::new(frame)).unwrap();
+    assert_eq!(reader.content_size(), data.len() as u64);
+
+    for (start, len) in [
+        (0, 10),
+        (29_990, 20),
+        (100_000, 90_000),
+        (799_990, 10),
+        (0, 0),
+    ] {
+        reader.seek(SeekFrom::Start(start as u64)).unwrap();
+
+        let mut buf = vec![0; len];
+        reader.read_exact(&mut buf).unwrap();
+
+        assert_eq!(buf, data[start..start + len]);
+    }
+
+    reader.seek(SeekFrom::End(-5)).unwrap();
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+
+    assert_eq!(buf, data[data.len() - 5..]);
+    assert!(reader.seek(SeekFrom::Current(-1_000_000)).is_err());
+}
+
+#[test]
+fn corrupt() {
+    let data = repeated_words(200_000);
+    let mut frame = compress_frame(&data, CompressLevel::default(), 30_000);
+
+    assert_eq!(
+        decompress_frame(&frame[..frame.len() - 1]),
+        Err(FrameError::InvalidInput)
+    );
+    assert!(FrameReader::new(Cursor::new(&frame[..frame.len() - 1])).is_err());
+
+    // Flip a bit in the checksum of the first block.
+    frame[8 + 8] ^= 1;
+
+    assert_eq!(decompress_frame(&frame), Err(FrameError::Checksum));
+
+    let mut reader = FrameReader::new(Cursor::new(&frame)).unwrap();
+
+    let err = reader.read(&mut [0; 10]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    // Blocks which are not read are not checked.
+    reader.seek(SeekFrom::Start(30_000)).unwrap();
+    reader.read_exact(&mut [0; 10]).unwrap();
+}
+
+#[test]
+fn corrupt_seek_table() {
+    let data = repeated_words(200_000);
+    let frame = compress_frame(&data, CompressLevel::default(), 30_000);
+
+    let block_count = u32::from_le_bytes(frame[frame.len() - 16..][..4].try_into().unwrap());
+    let table_pos = frame.len() - 16 - block_count as usize * 8;
+
+    // Change the decompressed length of the first block in the seek table, and set its stored flag.
+    for (pos, mask) in [(table_pos + 4, 1), (table_pos + 3, 0x80)] {
+        let mut frame = frame.clone();
+        frame[pos] ^= mask;
+
+        assert_eq!(decompress_frame(&frame), Err(FrameError::InvalidInput));
+
+        let err = FrameReader::new(Cursor::new(&frame))
+            .and_then(|mut reader| reader.read(&mut [0; 10]))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+fn corrupt_end_mark() {
+    let data = repeated_words(200_000);
+    let frame = compress_frame(&data, CompressLevel::default(), 30_000);
+
+    let block_count = u32::from_le_bytes(frame[frame.len() - 16..][..4].try_into().unwrap());
+    let end_mark_pos = frame.len() - 16 - block_count as usize * 8 - 4;
+
+    for pos in end_mark_pos..end_mark_pos + 4 {
+        let mut frame = frame.clone();
+        frame[pos] = 1;
+
+        assert_eq!(decompress_frame(&frame), Err(FrameError::InvalidInput));
+
+        let err = FrameReader::new(Cursor::new(&frame)).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+fn writer_finish_error() {
+    let failures = Cell::new(0);
+
+    let mut writer = FrameWriter::with_block_size(
+        FailingWriter {
+            len: 0,
+            max_len: 100,
+            failures: &failures,
+        },
+        CompressLevel::default(),
+        64,
+    );
+
+    writer.write_all(&[0xaa; 100]).unwrap();
+
+    assert!(writer.finish().is_err());
+
+    // The trailer is not written again when the writer is dropped.
+    assert_eq!(failures.get(), 1);
+}
+
+#[test]
+fn writer_retry() {
+    let data = repeated_words(20_000);
+    let level = CompressLevel::default();
+
+    let mut writer = FrameWriter::with_block_size(
+        FlakyWriter {
+            inner: Vec::new(),
+            flaky: true,
+            fail: false,
+        },
+        level,
+        10_000,
+    );
+
+    for chunk in data.chunks(3000) {
+        let mut chunk = chunk;
+
+        while !chunk.is_empty() {
+            match writer.write(chunk) {
+                Ok(n) => chunk = &chunk[n..],
+                Err(err) => assert_eq!(err.kind(), ErrorKind::WouldBlock),
+            }
+        }
+    }
+
+    while let Err(err) = writer.flush() {
+        assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    }
+
+    writer.get_mut().flaky = false;
+
+    let frame = writer.finish().unwrap().inner;
+
+    assert_eq!(frame, compress_frame(&data, level, 10_000));
+}
+
+/// Writer which fails once more than `max_len` bytes are written.
+struct FailingWriter<'a> {
+    len: usize,
+    max_len: usize,
+    failures: &'a Cell<usize>,
+}
+
+impl Write for FailingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.len + buf.len() > self.max_len {
+            self.failures.set(self.failures.get() + 1);
+
+            return Err(ErrorKind::Other.into());
+        }
+
+        self.len += buf.len();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer which fails on every other write while `flaky` is set, and writes at most 7 bytes at once.
+struct FlakyWriter {
+    inner: Vec<u8>,
+    flaky: bool,
+    fail: bool,
+}
+
+impl Write for FlakyWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.fail = self.flaky && !self.fail;
+
+        if self.fail {
+            return Err(ErrorKind::WouldBlock.into());
+        }
+
+        self.inner.write(&buf[..buf.len().min(7)])
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
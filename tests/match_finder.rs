@@ -0,0 +1,155 @@
+mod common;
+
+use common::{random, repeated_words};
+use lzo1x::{Match, MatchFinder};
+
+fn data() -> Vec<u8> {
+    let mut data = repeated_words(20_000);
+
+    // Short repeats of random bytes far apart, with a few long runs.
+    for (i, x) in random(60_000, 1).into_iter().enumerate() {
+        data.push(if i % 5000 < 3000 { x & 7 } else { 0 });
+    }
+
+    data
+}
+
+fn match_len(data: &[u8], pos: usize, dist: usize) -> usize {
+    data[pos..]
+        .iter()
+        .zip(&data[pos - dist..])
+        .take(2048)
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+#[test]
+fn match_finder_candidates() {
+    let data = data();
+    let mut finder = MatchFinder::new(&data);
+
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let candidates: Vec<Match> = finder.candidates().collect();
+
+        // Compare with every earlier position in the window for a subset of positions.
+        if pos % 97 == 0 {
+            let expected: Vec<Match> = (1..=pos.min(0xbfff))
+                .map(|dist| Match {
+                    len: match_len(&data, pos, dist),
+                    dist,
+                })
+                .filter(|m| m.len >= 3)
+                .collect();
+
+            assert_eq!(candidates, expected, "position {pos}");
+        } else {
+            for m in &candidates {
+                assert!(m.dist <= 0xbfff && m.dist <= pos);
+                assert_eq!(m.len, match_len(&data, pos, m.dist));
+            }
+        }
+
+        let best = finder.find_best();
+
+        if let Some(best) = best {
+            assert!(best.len >= 2 && best.dist <= 0xbfff);
+            assert_eq!(best.len, match_len(&data, pos, best.dist));
+            assert!(best.len < 3 || candidates.contains(&best));
+        } else {
+            assert!(candidates.is_empty());
+        }
+
+        // Alternate between skipping a match and stepping a single byte.
+        let n = match best {
+            Some(best) if pos % 3 == 0 => best.len,
+            _ => 1,
+        };
+
+        finder.accept(n);
+        pos += n;
+
+        assert_eq!(finder.position(), pos);
+    }
+
+    assert_eq!(finder.find_best(), None);
+    assert_eq!(finder.candidates().count(), 0);
+}
+
+#[test]
+fn match_finder_limits() {
+    let data = random(2200, 1);
+
+    // A short match nearby, followed by a long match farther back.
+    let mut src = data[2000..2100].to_vec();
+    src.extend_from_slice(&data[..1000]);
+    src.extend_from_slice(&data[2000..2020]);
+    src.extend_from_slice(&data[1000..2000]);
+    src.extend_from_slice(&data[2000..2200]);
+
+    let pos = src.len() - 200;
+
+    let mut finder = MatchFinder::new(&src);
+    finder.accept(pos);
+
+    assert_eq!(
+        finder.find_best(),
+        Some(Match {
+            len: 100,
+            dist: pos
+        })
+    );
+    assert_eq!(
+        finder.candidates().collect::<Vec<_>>(),
+        [
+            Match {
+                len: 20,
+                dist: 1020
+            },
+            Match {
+                len: 100,
+                dist: pos
+            }
+        ]
+    );
+
+    for finder in [
+        MatchFinder::new(&src).nice_length(16),
+        MatchFinder::new(&src).max_chain(1),
+    ] {
+        let mut finder = finder;
+        finder.accept(pos);
+
+        assert_eq!(
+            finder.find_best(),
+            Some(Match {
+                len: 20,
+                dist: 1020
+            })
+        );
+        assert_eq!(finder.candidates().count(), 2);
+    }
+}
+
+#[test]
+fn match_finder_short() {
+    let mut finder = MatchFinder::new(b"xyzxy");
+
+    assert_eq!(finder.find_best(), None);
+
+    finder.accept(3);
+
+    assert_eq!(finder.find_best(), Some(Match { len: 2, dist: 3 }));
+    assert_eq!(finder.candidates().count(), 0);
+
+    finder.accept(2);
+
+    assert_eq!(finder.find_best(), None);
+}
+
+#[test]
+#[should_panic]
+fn match_finder_accept_past_end() {
+    MatchFinder::new(b"abc").accept(4);
+}
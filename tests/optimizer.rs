@@ -0,0 +1,130 @@
+use std::io::{Read, Write};
+
+use lzo1x::{
+    CompressLevel, Decoder, Encoder, OptimizeWriter, Optimizer, compress, decompress, optimize,
+};
+
+fn text(len: usize) -> Vec<u8> {
+    let words = [
+        &b"a "[..],
+        b"the ",
+        b"of ",
+        b"lzo ",
+        b"data ",
+        b"match ",
+        b"x",
+        b"yz",
+        b"\n",
+    ];
+
+    let mut state = 1u32;
+    let mut data = Vec::new();
+
+    while data.len() < len {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+
+        data.extend_from_slice(words[(state >> 16) as usize % words.len()]);
+    }
+
+    data.truncate(len);
+
+    data
+}
+
+fn optimize_stream(src: &[u8], piece_len: usize) -> Vec<u8> {
+    let mut optimizer = Optimizer::new();
+    let mut optimized = Vec::new();
+
+    for piece in src.chunks(piece_len) {
+        optimizer.update(piece, &mut optimized).unwrap();
+    }
+
+    optimizer.finish(&mut optimized).unwrap();
+
+    optimized
+}
+
+#[test]
+fn optimizer() {
+    let data = text(200_000);
+
+    for level in 1..=13 {
+        let compressed = compress(&data, CompressLevel::new(level));
+
+        let mut expected = compressed.clone();
+        optimize(&mut expected, data.len());
+
+        for piece_len in [1, 2, 3, 7, 100, 0x10000, usize::MAX] {
+            assert_eq!(optimize_stream(&compressed, piece_len), expected);
+        }
+    }
+}
+
+#[test]
+fn optimizer_small() {
+    for len in 0..100 {
+        let data = text(len);
+        let compressed = compress(&data, CompressLevel::default());
+
+        let mut expected = compressed.clone();
+        optimize(&mut expected, data.len());
+
+        let optimized = optimize_stream(&compressed, 1);
+
+        assert_eq!(optimized, expected);
+
+        let mut decompressed = vec![0; data.len()];
+        decompress(&optimized, &mut decompressed).unwrap();
+
+        assert_eq!(decompressed, data);
+    }
+}
+
+#[test]
+fn optimizer_errors() {
+    let data = text(1000);
+    let compressed = compress(&data, CompressLevel::default());
+
+    let mut optimizer = Optimizer::new();
+    let mut optimized = Vec::new();
+
+    optimizer
+        .update(&compressed[..compressed.len() - 1], &mut optimized)
+        .unwrap();
+
+    assert!(optimizer.finish(&mut optimized).is_err());
+
+    // A match referring to data before the start of the stream.
+    assert!(
+        Optimizer::new()
+            .update(&[1, b'a', b'b', b'c', b'd', 0xff, 0xff], &mut Vec::new())
+            .is_err()
+    );
+}
+
+#[test]
+fn optimize_writer() {
+    let data = text(300_000);
+
+    for level in [1, 3, 7, 12] {
+        let writer = OptimizeWriter::new(Vec::new());
+        let mut encoder = Encoder::new(writer, CompressLevel::new(level));
+
+        for (i, chunk) in data.chunks(10_000).enumerate() {
+            encoder.write_all(chunk).unwrap();
+
+            if i % 4 == 3 {
+                encoder.flush().unwrap();
+            }
+        }
+
+        let optimized = encoder.finish().unwrap().finish().unwrap();
+
+        let mut decompressed = Vec::new();
+        Decoder::new(optimized.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+
+        assert_eq!(decompressed, data);
+    }
+}
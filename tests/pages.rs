@@ -0,0 +1,76 @@
+mod common;
+
+use common::{mixed, random, repeated_words};
+use lzo1x::{CompressLevel, DecompressError, PageCompressor, compress, compress_pages};
+
+#[test]
+fn pages() {
+    // Append pages which do not compress.
+    let data = [repeated_words(16_384), random(8192, 1)].concat();
+
+    for level in 1..=13 {
+        let level = CompressLevel::new(level);
+        let mut compressor = PageCompressor::new(4096, level);
+
+        // Compress twice to ensure the reused state does not affect the result.
+        for _ in 0..2 {
+            let pages = compressor.compress_pages(&data);
+
+            assert_eq!(pages.len(), data.len() / 4096);
+            assert_eq!(pages.offsets().len(), pages.len() + 1);
+            assert_eq!(*pages.offsets().last().unwrap(), pages.data().len());
+
+            for (i, page) in data.chunks(4096).enumerate() {
+                if i < 16 {
+                    assert!(!pages.is_stored(i));
+                    assert_eq!(pages.page(i), compress(page, level));
+                } else {
+                    assert!(pages.is_stored(i));
+                    assert_eq!(pages.page(i), page);
+                }
+
+                let mut decompressed = [0; 4096];
+                pages.decompress_page(i, &mut decompressed).unwrap();
+
+                assert_eq!(decompressed, page);
+            }
+
+            assert_eq!(
+                pages.decompress_page(0, &mut [0; 4095]),
+                Err(DecompressError::OutputLength)
+            );
+        }
+    }
+}
+
+#[test]
+fn pages_dict_reuse() {
+    // Many pages, such that the dictionary is reused across pages and cleared only from time to time.
+    let mut data = mixed();
+    data.truncate(data.len() / 1024 * 1024);
+
+    for level in 1..=4 {
+        let level = CompressLevel::new(level);
+        let pages = compress_pages(&data, 1024, level);
+
+        for (i, page) in data.chunks(1024).enumerate() {
+            if !pages.is_stored(i) {
+                assert_eq!(pages.page(i), compress(page, level));
+            }
+        }
+    }
+}
+
+#[test]
+fn pages_empty() {
+    let pages = compress_pages(&[], 4096, CompressLevel::default());
+
+    assert!(pages.is_empty());
+    assert_eq!(pages.offsets(), [0]);
+}
+
+#[test]
+#[should_panic]
+fn pages_partial() {
+    compress_pages(&[0; 5000], 4096, CompressLevel::default());
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::repeated_words;
+use lzo1x::{
+    CompressLevel, FrameError, FrameReader, compress_frame, compress_frame_parallel,
+    decompress_frame, decompress_frame_parallel,
+};
+
+#[test]
+fn deterministic() {
+    let data = repeated_words(200_000);
+
+    for level in [1, 5] {
+        let level = CompressLevel::new(level);
+        let frame = compress_frame(&data, level, 30_000);
+
+        for threads in [0, 1, 2, 3, 8, 100] {
+            assert_eq!(
+                compress_frame_parallel(&data, level, 30_000, threads),
+                frame
+            );
+            assert_eq!(decompress_frame_parallel(&frame, threads).unwrap(), data);
+        }
+    }
+
+    let frame = compress_frame_parallel(&[], CompressLevel::default(), 30_000, 4);
+
+    assert_eq!(frame, compress_frame(&[], CompressLevel::default(), 30_000));
+    assert_eq!(decompress_frame_parallel(&frame, 4).unwrap(), []);
+}
+
+#[test]
+fn corrupt() {
+    let data = repeated_words(200_000);
+    let mut frame = compress_frame(&data, CompressLevel::default(), 30_000);
+
+    // Flip a bit in the checksum of the first block.
+    frame[8 + 8] ^= 1;
+
+    for threads in [1, 4] {
+        assert_eq!(
+            decompress_frame_parallel(&frame, threads),
+            Err(FrameError::Checksum)
+        );
+    }
+
+    assert_eq!(
+        decompress_frame_parallel(&frame[..frame.len() - 1], 4),
+        decompress_frame(&frame[..frame.len() - 1])
+    );
+}
+
+#[test]
+fn block_lengths() {
+    let compressed = 4;
+    let stored = 4 | 1 << 31;
+
+    for blocks in [
+        // A few hundred bytes which declare 100 GiB of decompressed data.
+        vec![(compressed, 1 << 30); 100],
+        vec![(stored, 8)],
+        vec![(stored, 2)],
+    ] {
+        let frame = frame(&blocks);
+
+        assert_eq!(decompress_frame(&frame), Err(FrameError::InvalidInput));
+        assert_eq!(
+            decompress_frame_parallel(&frame, 4),
+            Err(FrameError::InvalidInput)
+        );
+        assert!(FrameReader::new(Cursor::new(&frame)).is_err());
+    }
+}
+
+/// Returns a frame with a valid structure and seek table, with blocks of the given stored and decompressed lengths.
+fn frame(blocks: &[(u32, u32)]) -> Vec<u8> {
+    let mut frame = b"LZ1X".to_vec();
+    frame.extend_from_slice(&(1u32 << 30).to_le_bytes());
+
+    for &(stored_len, len) in blocks {
+        frame.extend_from_slice(&stored_len.to_le_bytes());
+        frame.extend_from_slice(&len.to_le_bytes());
+        frame.extend_from_slice(&0u32.to_le_bytes());
+        frame.resize(frame.len() + (stored_len & !(1 << 31)) as usize, 0);
+    }
+
+    frame.extend_from_slice(&0u32.to_le_bytes());
+
+    for &(stored_len, len) in blocks {
+        frame.extend_from_slice(&stored_len.to_le_bytes());
+        frame.extend_from_slice(&len.to_le_bytes());
+    }
+
+    let content_size: u64 = blocks.iter().map(|&(_, len)| len as u64).sum();
+
+    frame.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&content_size.to_le_bytes());
+    frame.extend_from_slice(b"LZ1S");
+
+    frame
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use std::io::Write;
+
+use common::repeated_words;
+use lzo1x::{
+    CompressLevel, DecompressError, Encoder, compress, optimize, recompress, recompress_optimized,
+};
+
+#[test]
+fn recompress_levels() {
+    let data = repeated_words(300_000);
+
+    let compressed = compress(&data, CompressLevel::new(1));
+
+    for level in 1..=13 {
+        let level = CompressLevel::new(level);
+        let expected = compress(&data, level);
+
+        assert_eq!(recompress(&compressed, level).unwrap(), expected);
+
+        let mut expected_optimized = expected.clone();
+        optimize(&mut expected_optimized, data.len());
+
+        assert_eq!(
+            recompress_optimized(&compressed, level).unwrap(),
+            expected_optimized
+        );
+    }
+}
+
+#[test]
+fn recompress_small() {
+    for len in [0, 1, 3, 4, 100] {
+        let data: Vec<u8> = (0..len as u8).collect();
+        let compressed = compress(&data, CompressLevel::default());
+
+        for level in [1, 12] {
+            let level = CompressLevel::new(level);
+
+            assert_eq!(
+                recompress(&compressed, level).unwrap(),
+                compress(&data, level)
+            );
+        }
+    }
+}
+
+#[test]
+fn recompress_blocks() {
+    let data = repeated_words(100_000);
+
+    let mut encoder = Encoder::new(Vec::new(), CompressLevel::new(1));
+
+    for chunk in data.chunks(30_000) {
+        encoder.write_all(chunk).unwrap();
+        encoder.flush().unwrap();
+    }
+
+    let compressed = encoder.finish().unwrap();
+
+    assert_eq!(
+        recompress(&compressed, CompressLevel::new(12)).unwrap(),
+        compress(&data, CompressLevel::new(12))
+    );
+}
+
+#[test]
+fn recompress_errors() {
+    let data = [0xaa; 1000];
+    let compressed = compress(&data, CompressLevel::default());
+
+    for src in [
+        &[][..],
+        &compressed[..compressed.len() - 1],
+        &compressed[..1],
+        &[0xff; 10],
+    ] {
+        assert_eq!(
+            recompress(src, CompressLevel::default()),
+            Err(DecompressError::InvalidInput)
+        );
+        assert_eq!(
+            recompress_optimized(src, CompressLevel::default()),
+            Err(DecompressError::InvalidInput)
+        );
+    }
+}
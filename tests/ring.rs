@@ -0,0 +1,112 @@
+mod common;
+
+use common::{random, repeated_words};
+use lzo1x::{CompressLevel, DecompressError, RingDecoder, RingError, compress};
+
+#[test]
+fn ring() {
+    let random = random(40_000, 1);
+
+    // Repeat the data at a distance close to the maximum match distance.
+    let data: Vec<u8> = [
+        random.as_slice(),
+        &[0; 9000],
+        &random,
+        &repeated_words(100_000),
+        &random,
+    ]
+    .concat();
+
+    for level in [1, 3, 7, 13] {
+        let compressed = compress(&data, CompressLevel::new(level));
+
+        for (ring_len, page_len) in [
+            (RingDecoder::MIN_RING_LEN, RingDecoder::MIN_RING_LEN),
+            (48 * 1024, 256),
+            (48 * 1024, 16 * 1024),
+            (64 * 1024, 4096),
+        ] {
+            let mut ring = vec![0; ring_len];
+            let mut decoder = RingDecoder::new(&mut ring, page_len);
+
+            let mut decompressed = Vec::new();
+            let mut pages = 0;
+
+            let len = decoder
+                .decompress(&compressed, |page| {
+                    assert!(
+                        page.len() == page_len || decompressed.len() + page.len() == data.len()
+                    );
+
+                    decompressed.extend_from_slice(page);
+                    pages += 1;
+
+                    Ok::<_, ()>(())
+                })
+                .unwrap();
+
+            assert_eq!(len, data.len());
+            assert_eq!(decompressed, data);
+            assert_eq!(pages, data.len().div_ceil(page_len));
+        }
+    }
+}
+
+#[test]
+fn ring_errors() {
+    let mut ring = [0; 48 * 1024];
+    let mut decoder = RingDecoder::new(&mut ring, 1024);
+
+    let mut output = |_: &[u8]| Ok::<_, ()>(());
+
+    assert_eq!(
+        decoder.decompress(&[], &mut output),
+        Err(RingError::Decompress(DecompressError::InvalidInput))
+    );
+    assert_eq!(
+        decoder.decompress(&[18, 0, 33, 1, 0, 17, 0, 0], &mut output),
+        Err(RingError::Decompress(DecompressError::InvalidInput))
+    );
+    assert_eq!(
+        decoder.decompress(&[18, 0, 17, 0, 0, 0], &mut output),
+        Err(RingError::Decompress(DecompressError::InvalidInput))
+    );
+    assert_eq!(decoder.decompress(&[18, 0, 17, 0, 0], &mut output), Ok(1));
+
+    let compressed = compress(&[0xaa; 5000], CompressLevel::default());
+    let mut calls = 0;
+
+    assert_eq!(
+        decoder.decompress(&compressed, |_| {
+            calls += 1;
+
+            if calls == 3 { Err("full") } else { Ok(()) }
+        }),
+        Err(RingError::Output("full"))
+    );
+    assert_eq!(calls, 3);
+}
+
+#[test]
+#[should_panic]
+fn ring_too_small() {
+    let mut ring = [0; 32 * 1024];
+    RingDecoder::new(&mut ring, 1024);
+}
+
+#[cfg(feature = "embedded-io")]
+#[test]
+fn ring_embedded_io() {
+    let data = [0xaa; 100_000];
+    let compressed = compress(&data, CompressLevel::default());
+
+    let mut ring = [0; 48 * 1024];
+    let mut decompressed = vec![0; data.len()];
+
+    let len = RingDecoder::new(&mut ring, 4096)
+        .decompress_to(&compressed, &mut decompressed.as_mut_slice())
+        .unwrap();
+
+    assert_eq!(len, data.len());
+    assert_eq!(decompressed, data);
+}
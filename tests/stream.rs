@@ -0,0 +1,186 @@
+mod common;
+
+use std::io::{self, ErrorKind, Read, Write};
+
+use common::repeated_words;
+use lzo1x::{CompressLevel, Decoder, Encoder, compress};
+
+#[test]
+fn encoder() {
+    let data = repeated_words(50_000);
+
+    for level in 1..=13 {
+        let level = CompressLevel::new(level);
+
+        let mut encoder = Encoder::new(Vec::new(), level);
+
+        for chunk in data.chunks(1000) {
+            encoder.write_all(chunk).unwrap();
+        }
+
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(compressed, compress(&data, level));
+    }
+}
+
+#[test]
+fn flush() {
+    let data = repeated_words(50_000);
+
+    for level in 1..=13 {
+        let mut encoder = Encoder::new(Vec::new(), CompressLevel::new(level));
+
+        for chunk in data.chunks(50000) {
+            encoder.write_all(chunk).unwrap();
+            encoder.flush().unwrap();
+
+            let compressed = encoder.get_ref().clone();
+
+            let mut decompressed = Vec::new();
+            Decoder::new(compressed.as_slice())
+                .read_to_end(&mut decompressed)
+                .unwrap();
+
+            assert!(data.starts_with(&decompressed));
+        }
+
+        let compressed = encoder.finish().unwrap();
+
+        let mut decompressed = Vec::new();
+        Decoder::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+
+        assert!(decompressed == data);
+    }
+}
+
+#[test]
+fn encoder_retry() {
+    let data = repeated_words(50_000);
+    let level = CompressLevel::default();
+
+    let mut encoder = Encoder::new(
+        FlakyWriter {
+            inner: Vec::new(),
+            flaky: true,
+            fail: false,
+        },
+        level,
+    );
+
+    for chunk in data.chunks(1000) {
+        let mut chunk = chunk;
+
+        while !chunk.is_empty() {
+            match encoder.write(chunk) {
+                Ok(n) => chunk = &chunk[n..],
+                Err(err) => assert_eq!(err.kind(), ErrorKind::WouldBlock),
+            }
+        }
+    }
+
+    encoder.get_mut().flaky = false;
+
+    let compressed = encoder.finish().unwrap().inner;
+
+    assert_eq!(compressed, compress(&data, level));
+}
+
+#[test]
+fn decoder() {
+    let data = repeated_words(50_000);
+    let compressed = compress(&data, CompressLevel::default());
+
+    let mut decoder = Decoder::new(OneByteReader(&compressed));
+    let mut decompressed = Vec::new();
+    let mut buf = [0; 100];
+
+    loop {
+        let n = decoder.read(&mut buf).unwrap();
+
+        if n == 0 {
+            break;
+        }
+
+        decompressed.extend_from_slice(&buf[..n]);
+    }
+
+    assert!(decompressed == data);
+
+    let result = Decoder::new(&compressed[..compressed.len() - 1]).read_to_end(&mut Vec::new());
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn decoder_long_match() {
+    // A literal, followed by a match with an extended length of 100_000 zero bytes, and the end of the stream.
+    let mut compressed = vec![18, b'a', 32];
+    compressed.resize(compressed.len() + 100_000, 0);
+    compressed.extend_from_slice(&[1, 0, 0, 17, 0, 0]);
+
+    let len = 1 + 100_000 * 255 + 1 + 33;
+
+    let mut decoder = Decoder::new(compressed.as_slice());
+    let mut decompressed = Vec::new();
+    let mut buf = vec![0; 1024 * 1024];
+
+    loop {
+        let n = decoder.read(&mut buf).unwrap();
+
+        if n == 0 {
+            break;
+        }
+
+        // The match is decompressed in pieces.
+        assert!(n <= 0x10000);
+
+        decompressed.extend_from_slice(&buf[..n]);
+    }
+
+    assert_eq!(decompressed.len(), len);
+    assert!(decompressed.iter().all(|&byte| byte == b'a'));
+
+    compressed.truncate(compressed.len() - 3);
+
+    let result = Decoder::new(compressed.as_slice()).read_to_end(&mut Vec::new());
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}
+
+struct OneByteReader<'a>(&'a [u8]);
+
+impl Read for OneByteReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.0.len()).min(1);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+
+        Ok(n)
+    }
+}
+
+/// Writer which fails on every other write while `flaky` is set, and writes at most 7 bytes at once.
+struct FlakyWriter {
+    inner: Vec<u8>,
+    flaky: bool,
+    fail: bool,
+}
+
+impl Write for FlakyWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.fail = self.flaky && !self.fail;
+
+        if self.fail {
+            return Err(ErrorKind::WouldBlock.into());
+        }
+
+        self.inner.write(&buf[..buf.len().min(7)])
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
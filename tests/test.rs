@@ -62,6 +62,9 @@ fn add_corpus_tests(tests: &mut Vec<TestDescAndFn>, path: &str) {
 
         let test = create_roundtrip_999_test(file.name(), Arc::clone(&data));
         tests.push(test);
+
+        let test = create_roundtrip_999_optimize_test(file.name(), Arc::clone(&data));
+        tests.push(test);
     }
 }
 
@@ -151,6 +154,30 @@ fn create_roundtrip_999_test(name: &str, data: Arc<Vec<u8>>) -> TestDescAndFn {
     }
 }
 
+fn create_roundtrip_999_optimize_test(name: &str, data: Arc<Vec<u8>>) -> TestDescAndFn {
+    TestDescAndFn {
+        desc: TestDesc {
+            name: TestName::DynTestName(format!("roundtrip 999 optimize {name}")),
+            ignore: false,
+            ignore_message: None,
+            source_file: "",
+            start_line: 0,
+            start_col: 0,
+            end_line: 0,
+            end_col: 0,
+            should_panic: ShouldPanic::No,
+            compile_fail: false,
+            no_run: false,
+            test_type: TestType::IntegrationTest,
+        },
+        testfn: TestFn::DynTestFn(Box::new(move || {
+            roundtrip_999_optimize(&data);
+
+            Ok(())
+        })),
+    }
+}
+
 fn roundtrip_1(data: &[u8]) {
     let compressed = lzo1x::compress(data, CompressLevel::new(3));
 
@@ -188,6 +215,22 @@ fn roundtrip_999(data: &[u8]) {
     assert!(decompressed == data);
 }
 
+fn roundtrip_999_optimize(data: &[u8]) {
+    for level in 5..=13 {
+        let compressed = lzo1x::compress(data, CompressLevel::new(level));
+
+        let mut optimized = compressed.clone();
+        lzo1x::optimize(&mut optimized, data.len());
+
+        assert!(optimized == lzo_sys_optimize(&compressed, data.len()));
+
+        let mut decompressed = vec![0; data.len()];
+        lzo1x::decompress(&optimized, &mut decompressed).unwrap();
+
+        assert!(decompressed == data);
+    }
+}
+
 fn lzo_sys_compress_1(src: &[u8]) -> Vec<u8> {
     lzo_sys_compress(src, lzo_sys::lzo1x::lzo1x_1_compress)
 }
@@ -0,0 +1,150 @@
+mod common;
+
+use common::{check, repeated_words};
+use lzo1x::{CompressLevel, MatchFinder, TokenError, TokenWriter, analyze, compress};
+
+#[test]
+fn token_writer_parse() {
+    let data = [repeated_words(50_000), b"abcabcabcdxxxxabxab".repeat(200)].concat();
+
+    let mut finder = MatchFinder::new(&data);
+    let mut writer = TokenWriter::new();
+
+    // Greedy parse, which takes every match that can be encoded.
+    while finder.position() < data.len() {
+        let pos = finder.position();
+
+        let n = match finder.find_best() {
+            Some(m) if writer.write_match(m.len, m.dist).is_ok() => m.len,
+            _ => {
+                writer.write_literals(&data[pos..pos + 1]);
+
+                1
+            }
+        };
+
+        finder.accept(n);
+
+        assert_eq!(writer.position(), finder.position());
+    }
+
+    let compressed = writer.finish();
+
+    check(&compressed, &data);
+
+    assert!(compressed.len() < compress(&data, CompressLevel::new(1)).len());
+}
+
+#[test]
+fn token_writer_forms() {
+    let data: Vec<u8> = (0..100_000u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+        .collect();
+
+    let mut writer = TokenWriter::new();
+    let mut expected = Vec::new();
+
+    let write_literals = |writer: &mut TokenWriter, expected: &mut Vec<u8>, len: usize| {
+        let pos = expected.len();
+
+        writer.write_literals(&data[pos..pos + len]);
+        expected.extend_from_slice(&data[pos..pos + len]);
+    };
+
+    let write_match =
+        |writer: &mut TokenWriter, expected: &mut Vec<u8>, len: usize, dist: usize| {
+            writer.write_match(len, dist).unwrap();
+
+            for _ in 0..len {
+                expected.push(expected[expected.len() - dist]);
+            }
+        };
+
+    // A long literal run at the start, and after a match.
+    write_literals(&mut writer, &mut expected, 300);
+    write_match(&mut writer, &mut expected, 3, 200);
+    write_literals(&mut writer, &mut expected, 5000);
+
+    // M1 matches after 1 to 3 literals, and after 4 or more literals.
+    for lit in 1..=4 {
+        write_match(&mut writer, &mut expected, 4, 100);
+        write_literals(&mut writer, &mut expected, lit);
+
+        let (len, dist) = if lit < 4 { (2, 300 * lit) } else { (3, 0xa00) };
+        write_match(&mut writer, &mut expected, len, dist);
+    }
+
+    // Extended lengths of M3 and M4 matches, and the largest distance.
+    write_match(&mut writer, &mut expected, 300, 5000);
+    write_match(&mut writer, &mut expected, 45_000, 1);
+    write_literals(&mut writer, &mut expected, 3);
+    write_match(&mut writer, &mut expected, 600, 0x8000);
+    write_match(&mut writer, &mut expected, 4, 0xbfff);
+
+    let compressed = writer.finish();
+
+    check(&compressed, &expected);
+
+    let analysis = analyze(&compressed).unwrap();
+
+    assert_eq!(analysis.m1.count, 4);
+    assert_eq!(analysis.m2.count, 5);
+    assert_eq!(analysis.m3.count, 2);
+    assert_eq!(analysis.m4.count, 2);
+}
+
+#[test]
+fn token_writer_small() {
+    check(&TokenWriter::new().finish(), &[]);
+
+    assert_eq!(
+        TokenWriter::new().finish(),
+        compress(&[], CompressLevel::default())
+    );
+
+    for len in [1, 3, 4, 238, 239, 1000] {
+        let data = vec![b'x'; len];
+
+        let mut writer = TokenWriter::new();
+        writer.write_literals(&data[..len / 2]);
+        writer.write_literals(&[]);
+        writer.write_literals(&data[len / 2..]);
+
+        check(&writer.finish(), &data);
+    }
+}
+
+#[test]
+fn token_writer_errors() {
+    let mut writer = TokenWriter::new();
+
+    assert_eq!(writer.write_match(3, 1), Err(TokenError::Distance));
+
+    writer.write_literals(b"abcde");
+
+    assert_eq!(writer.write_match(1, 1), Err(TokenError::Length));
+    assert_eq!(writer.write_match(2, 1), Err(TokenError::Length));
+    assert_eq!(writer.write_match(3, 0), Err(TokenError::Distance));
+    assert_eq!(writer.write_match(3, 6), Err(TokenError::Distance));
+
+    writer.write_match(3, 5).unwrap();
+
+    assert_eq!(writer.write_match(2, 1), Err(TokenError::Length));
+
+    writer.write_literals(&[0; 0xc000]);
+
+    assert_eq!(writer.write_match(3, 0xc000), Err(TokenError::Distance));
+
+    writer.write_match(3, 0xbfff).unwrap();
+    writer.write_literals(b"x");
+
+    assert_eq!(writer.write_match(2, 1025), Err(TokenError::Distance));
+
+    writer.write_match(2, 1024).unwrap();
+
+    let mut expected = b"abcdeabc".to_vec();
+    expected.extend_from_slice(&[0; 0xc000]);
+    expected.extend_from_slice(&[0, 0, 0, b'x', 0, 0]);
+
+    check(&writer.finish(), &expected);
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    ptr,
+};
+
+use common::repeated_words;
+use lzo1x::{
+    CompressLevel, DecompressError, TryCompressError, TryDecompressError, compress, try_compress,
+    try_decompress_to_vec, try_optimize,
+};
+
+/// Allocator which fails allocations larger than a limit on the current thread.
+struct LimitedAlloc;
+
+thread_local! {
+    static LIMIT: Cell<usize> = const { Cell::new(usize::MAX) };
+}
+
+unsafe impl GlobalAlloc for LimitedAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() > LIMIT.with(Cell::get) {
+            return ptr::null_mut();
+        }
+
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOC: LimitedAlloc = LimitedAlloc;
+
+fn with_limit<T>(limit: usize, f: impl FnOnce() -> T) -> T {
+    LIMIT.with(|cell| cell.set(limit));
+    let result = f();
+    LIMIT.with(|cell| cell.set(usize::MAX));
+
+    result
+}
+
+#[test]
+fn try_compress_out_of_memory() {
+    let data = repeated_words(1000);
+
+    for level in 1..=13 {
+        let level = CompressLevel::new(level);
+
+        assert_eq!(try_compress(&data, level).unwrap(), compress(&data, level));
+
+        // The dictionaries of all levels are larger than 1 KiB.
+        assert_eq!(
+            with_limit(1024, || try_compress(&data, level)),
+            Err(TryCompressError::OutOfMemory)
+        );
+
+        // The output buffer is larger than the limit.
+        assert_eq!(
+            with_limit(1024, || try_compress(&[0; 2000], level)),
+            Err(TryCompressError::OutOfMemory)
+        );
+    }
+
+    // LZO1X-1 needs at most 64 KiB, while LZO1X-999 needs more.
+    assert!(with_limit(0x10000, || try_compress(&data, CompressLevel::new(4))).is_ok());
+    assert_eq!(
+        with_limit(0x10000, || try_compress(&data, CompressLevel::new(13))),
+        Err(TryCompressError::OutOfMemory)
+    );
+}
+
+#[test]
+fn try_decompress_to_vec_out_of_memory() {
+    let data = vec![0xaa; 100_000];
+    let compressed = compress(&data, CompressLevel::default());
+
+    assert_eq!(
+        try_decompress_to_vec(&compressed, data.len()).unwrap(),
+        data
+    );
+    assert_eq!(
+        try_decompress_to_vec(&compressed, data.len() + 1),
+        Err(TryDecompressError::Decompress(
+            DecompressError::OutputLength
+        ))
+    );
+    assert_eq!(
+        with_limit(50_000, || try_decompress_to_vec(&compressed, data.len())),
+        Err(TryDecompressError::OutOfMemory)
+    );
+    assert_eq!(
+        try_decompress_to_vec(&compressed, usize::MAX),
+        Err(TryDecompressError::OutOfMemory)
+    );
+}
+
+#[test]
+fn try_optimize_out_of_memory() {
+    let data = vec![0xaa; 100_000];
+    let compressed = compress(&data, CompressLevel::default());
+
+    let mut src = compressed.clone();
+    assert_eq!(
+        with_limit(50_000, || try_optimize(&mut src, data.len())),
+        Err(DecompressError::OutputLength)
+    );
+    assert_eq!(
+        try_optimize(&mut src, usize::MAX),
+        Err(DecompressError::OutputLength)
+    );
+    assert_eq!(src, compressed);
+
+    assert!(try_optimize(&mut src, data.len()).is_ok());
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{random, repeated_words};
+use lzo1x::{CompressLevel, compress, decompress, optimize, try_optimize};
+
+#[test]
+fn try_optimize_valid() {
+    let data = repeated_words(100_000);
+
+    for level in 1..=13 {
+        let compressed = compress(&data, CompressLevel::new(level));
+
+        let mut expected = compressed.clone();
+        optimize(&mut expected, data.len());
+
+        let mut optimized = compressed.clone();
+        try_optimize(&mut optimized, data.len()).unwrap();
+
+        assert_eq!(optimized, expected);
+    }
+}
+
+#[test]
+fn try_optimize_errors() {
+    let data = b"abcabcabcabcabcabcabcdabcdabcdefefefefef".repeat(10);
+    let compressed = compress(&data, CompressLevel::default());
+
+    for (src, len) in [
+        (&[][..], 0),
+        (&[0xff; 10], 100),
+        (&[17, 0, 0, 0], 0),
+        (&compressed[..compressed.len() - 1], data.len()),
+        (&compressed, data.len() - 1),
+        (&compressed, data.len() + 1),
+    ] {
+        let mut src = src.to_vec();
+        let original = src.clone();
+
+        assert!(try_optimize(&mut src, len).is_err());
+        assert_eq!(src, original);
+    }
+}
+
+#[test]
+fn try_optimize_mutations() {
+    let data = b"abcabcabcabcabcabcabcdabcdabcdefefefefef".repeat(10);
+    let compressed = compress(&data, CompressLevel::default());
+
+    // Each mutation uses 10 random bytes: the number of changed bytes, followed by an index and value for each.
+    for r in random(20_000 * 10, 1).chunks_exact(10) {
+        let mut src = compressed.clone();
+
+        for change in r[1..].chunks_exact(3).take(1 + r[0] as usize % 3) {
+            let i = u16::from_le_bytes([change[0], change[1]]) as usize % src.len();
+            src[i] = change[2];
+        }
+
+        let original = src.clone();
+
+        match try_optimize(&mut src, data.len()) {
+            Ok(()) => {
+                let mut decompressed = vec![0; data.len()];
+                let mut expected = vec![0; data.len()];
+
+                decompress(&src, &mut decompressed).unwrap();
+                decompress(&original, &mut expected).unwrap();
+
+                assert_eq!(decompressed, expected);
+            }
+            Err(_) => assert_eq!(src, original),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::repeated_words;
+use lzo1x::{CompressLevel, compress, compress_vectored};
+
+#[test]
+fn vectored() {
+    let data = repeated_words(100_000);
+
+    for level in 1..=13 {
+        let level = CompressLevel::new(level);
+        let expected = compress(&data, level);
+
+        for lens in [
+            &[data.len()][..],
+            &[0, data.len(), 0],
+            &[1, 2, 3, 4, 5, 6, 7, 8],
+            &[100; 50],
+            &[10, 150_000, 3, 0, 20_000],
+            &[70_000, 70_000, 70_000, 70_000],
+        ] {
+            let mut slices = Vec::new();
+            let mut pos = 0;
+
+            for &len in lens {
+                let len = len.min(data.len() - pos);
+                slices.push(&data[pos..pos + len]);
+                pos += len;
+            }
+
+            slices.push(&data[pos..]);
+
+            assert_eq!(compress_vectored(&slices, level), expected);
+        }
+    }
+
+    assert_eq!(
+        compress_vectored(&[], CompressLevel::default()),
+        compress(&[], CompressLevel::default())
+    );
+}
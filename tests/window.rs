@@ -0,0 +1,178 @@
+mod common;
+
+use std::io::Write;
+
+use common::{random, repeated_words};
+use lzo1x::{CompressLevel, CompressOptions, Encoder, compress, compress_with_options, decompress};
+
+#[test]
+fn window() {
+    let data = test_data();
+
+    for level in 1..=13 {
+        let level = CompressLevel::new(level);
+
+        assert_eq!(
+            compress_with_options(&data, CompressOptions::new(level)),
+            compress(&data, level)
+        );
+
+        assert!(max_match(&compress(&data, level)).0 > 16384);
+
+        for (max_offset, max_len) in [
+            (2048, usize::MAX),
+            (4096, 64),
+            (16384, 300),
+            (100, 3),
+            (1, 9),
+        ] {
+            let options = CompressOptions::new(level)
+                .max_offset(max_offset)
+                .max_len(max_len);
+
+            let compressed = compress_with_options(&data, options);
+
+            let mut decompressed = vec![0; data.len()];
+            decompress(&compressed, &mut decompressed).unwrap();
+
+            assert_eq!(decompressed, data);
+
+            let (offset, len) = max_match(&compressed);
+
+            assert!(
+                offset <= max_offset,
+                "level {level}: {offset} > {max_offset}"
+            );
+            assert!(len <= max_len, "level {level}: {len} > {max_len}");
+        }
+    }
+}
+
+#[test]
+fn window_encoder() {
+    let data = test_data();
+
+    for level in [1, 13] {
+        let options = CompressOptions::new(CompressLevel::new(level)).max_offset(4096);
+        let mut encoder = Encoder::with_options(Vec::new(), options);
+
+        for chunk in data.chunks(10_000) {
+            encoder.write_all(chunk).unwrap();
+            encoder.flush().unwrap();
+        }
+
+        let compressed = encoder.finish().unwrap();
+
+        assert!(max_match(&compressed).0 <= 4096);
+    }
+}
+
+fn test_data() -> Vec<u8> {
+    let random = random(20_000, 1);
+
+    [
+        random.as_slice(),
+        &[0; 5000],
+        &random,
+        &repeated_words(50_000),
+        &random,
+    ]
+    .concat()
+}
+
+/// Returns the largest match offset and match length in the given compressed data, which can consist of multiple blocks.
+fn max_match(src: &[u8]) -> (usize, usize) {
+    let mut max_offset = 0;
+    let mut max_len = 0;
+
+    let mut pos = 0;
+    let mut state = 0;
+
+    let ext_len = |pos: &mut usize, base: usize| {
+        let mut len = 0;
+
+        while src[*pos] == 0 {
+            len += 255;
+            *pos += 1;
+        }
+
+        *pos += 1;
+
+        len + base + src[*pos - 1] as usize
+    };
+
+    while pos < src.len() {
+        let insn = src[pos] as usize;
+        pos += 1;
+
+        let (len, offset, lit) = if state == 0 && insn > 17 {
+            pos += insn - 17;
+            state = if insn - 17 < 4 { 1 } else { 4 };
+            continue;
+        } else if insn < 16 {
+            match state {
+                0 | 0x10 => {
+                    let lit = if insn == 0 {
+                        ext_len(&mut pos, 15)
+                    } else {
+                        insn
+                    } + 3;
+                    pos += lit;
+                    state = 4;
+                    continue;
+                }
+                4 => (3, 2049 + (insn >> 2) + ((src[pos] as usize) << 2), insn & 3),
+                _ => (2, 1 + (insn >> 2) + ((src[pos] as usize) << 2), insn & 3),
+            }
+        } else if insn < 32 {
+            let len = if insn & 7 == 0 {
+                ext_len(&mut pos, 7)
+            } else {
+                insn & 7
+            } + 2;
+            let offset = 16384
+                + ((insn & 8) << 11)
+                + (src[pos] as usize >> 2)
+                + ((src[pos + 1] as usize) << 6);
+
+            pos += 1;
+
+            if offset == 16384 {
+                pos += 1;
+                state = 0;
+                continue;
+            }
+
+            (len, offset, src[pos - 1] as usize & 3)
+        } else if insn < 64 {
+            let len = if insn & 31 == 0 {
+                ext_len(&mut pos, 31)
+            } else {
+                insn & 31
+            } + 2;
+            pos += 1;
+
+            (
+                len,
+                1 + (src[pos - 1] as usize >> 2) + ((src[pos] as usize) << 6),
+                src[pos - 1] as usize & 3,
+            )
+        } else {
+            (
+                (insn >> 5) + 1,
+                1 + ((insn >> 2) & 7) + ((src[pos] as usize) << 3),
+                insn & 3,
+            )
+        };
+
+        pos += 1;
+        pos += lit;
+
+        max_offset = max_offset.max(offset);
+        max_len = max_len.max(len);
+
+        state = if lit == 0 { 0x10 } else { lit };
+    }
+
+    (max_offset, max_len)
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::repeated_words;
+use lzo1x::{
+    CompressLevel, LZO1X_1_11_MEM_COMPRESS, LZO1X_1_12_MEM_COMPRESS, LZO1X_1_15_MEM_COMPRESS,
+    LZO1X_1_MEM_COMPRESS, LZO1X_999_MEM_COMPRESS, Workspace999, compress, compress_bound,
+    compress_with_workspace,
+};
+
+#[test]
+fn workspace() {
+    let data = repeated_words(100_000);
+
+    let mut dst = vec![0; compress_bound(data.len())];
+
+    // Reuse dirty workspaces to ensure they are reinitialized.
+    let mut workspace = vec![0xff; LZO1X_1_15_MEM_COMPRESS];
+    let mut workspace_999 = Box::new(Workspace999::new());
+
+    for (level, workspace_len) in [
+        (1, LZO1X_1_11_MEM_COMPRESS),
+        (2, LZO1X_1_12_MEM_COMPRESS),
+        (3, LZO1X_1_MEM_COMPRESS),
+        (4, LZO1X_1_15_MEM_COMPRESS),
+    ] {
+        let level = CompressLevel::new(level);
+
+        for src in [&data[..], &[], &data[..10], &data[..10_000]] {
+            let len =
+                compress_with_workspace(src, &mut dst, level, &mut workspace[..workspace_len]);
+
+            assert_eq!(dst[..len], compress(src, level));
+        }
+    }
+
+    for level in 5..=13 {
+        let level = CompressLevel::new(level);
+
+        for src in [&data[..], &[], &data[..10], &data[..10_000]] {
+            let len = compress_with_workspace(src, &mut dst, level, &mut *workspace_999);
+
+            assert_eq!(dst[..len], compress(src, level));
+        }
+    }
+}
+
+#[test]
+fn workspace_sizes() {
+    assert_eq!(LZO1X_1_MEM_COMPRESS, 32768);
+    assert_eq!(LZO1X_999_MEM_COMPRESS, size_of::<Workspace999>());
+}
+
+#[test]
+#[should_panic]
+fn workspace_too_small() {
+    let mut dst = [0; compress_bound(100)];
+    let mut workspace = [0; LZO1X_1_MEM_COMPRESS - 1];
+
+    compress_with_workspace(
+        &[0; 100],
+        &mut dst,
+        CompressLevel::new(3),
+        &mut workspace[..],
+    );
+}
+
+#[test]
+#[should_panic]
+fn workspace_wrong_level() {
+    let mut dst = [0; compress_bound(100)];
+    let mut workspace = Box::new(Workspace999::new());
+
+    compress_with_workspace(&[0; 100], &mut dst, CompressLevel::new(3), &mut *workspace);
+}
+
+#[test]
+#[should_panic]
+fn dst_too_small() {
+    let mut dst = [0; compress_bound(100) - 1];
+    let mut workspace = [0; LZO1X_1_MEM_COMPRESS];
+
+    compress_with_workspace(
+        &[0; 100],
+        &mut dst,
+        CompressLevel::new(3),
+        &mut workspace[..],
+    );
+}